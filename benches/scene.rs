@@ -8,8 +8,9 @@ use raytracer::materials::DiffuseLight;
 use raytracer::texture::SolidColor;
 use raytracer::color::Color;
 use raytracer::objects::rect::*;
-use raytracer::hitable::{Hitable, HitableList};
+use raytracer::hitable::HitableList;
 use raytracer::render;
+use raytracer::scene::Scene;
 
 fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("scene/cornell/10x10x4", |b| {
@@ -53,11 +54,11 @@ fn criterion_benchmark(c: &mut Criterion) {
         hitable_list.push(XzRect::new(  0.,   2.,   0.,   2.,   2., white.clone()));
         hitable_list.push(XyRect::new(  0.,   2.,   0.,   2.,   2., white.clone()));
 
-        let world: Box<dyn Hitable> = Box::new(hitable_list);
+        let scene = Scene::new(hitable_list);
         
         b.iter_batched(
             | | (),
-            |_| render(&camera, &world, image_width, image_height, samples_per_pixel, max_depth),
+            |_| render(&camera, &scene, image_width, image_height, samples_per_pixel, max_depth),
             BatchSize::SmallInput,
         );
     });
@@ -7,12 +7,16 @@ use raytracer::color::Color;
 use raytracer::hitable::{Hitable, HitableList};
 use raytracer::materials::{Lambertian, DiffuseLight};
 use raytracer::structures::vec3::Vec3;
-use raytracer::render;
+use raytracer::{render, render_aovs};
+use raytracer::tonemap::ToneMapping;
+use raytracer::denoise::Denoiser;
+use raytracer::scene::Scene;
 use raytracer::io::png;
 use raytracer::texture::*;
 use raytracer::objects::rect::{XyRect, XzRect, YzRect};
 use raytracer::objects::rectbox::RectBox;
-use raytracer::objects::{Translate, RotateY};
+use raytracer::objects::{Translate, RotateY, FlipFace};
+use raytracer::lights::area::AreaLight;
 
 fn main() {
     // image
@@ -44,13 +48,12 @@ fn main() {
     let red   = Arc::new(Lambertian::new(SolidColor::new(Color::new(0.65, 0.05, 0.05))));
     let white = Arc::new(Lambertian::new(SolidColor::new(Color::new(0.73, 0.73, 0.73))));
     let green = Arc::new(Lambertian::new(SolidColor::new(Color::new(0.12, 0.45, 0.15))));
-    let light = Arc::new(DiffuseLight::new(SolidColor::new(Color::new(15., 15., 15.))));
+    let light = Arc::new(DiffuseLight::new(SolidColor::new(Color::new(15., 15., 15.))).one_sided());
 
     let mut hitable_list = HitableList::default();
 
     hitable_list.push(YzRect::new(  0.0, 555.0,   0.0, 555.0, 555.0, green)); 
     hitable_list.push(YzRect::new(  0.0, 555.0,   0.0, 555.0,   0.0,   red));
-    hitable_list.push(XzRect::new(213.0, 343.0, 227.0, 332.0, 554.0, light.clone()));
     hitable_list.push(XzRect::new(  0.0, 555.0,   0.0, 555.0, 555.0, white.clone()));
     hitable_list.push(XzRect::new(  0.0, 555.0,   0.0, 555.0,   0.0, white.clone()));
     hitable_list.push(XyRect::new(  0.0, 555.0,   0.0, 555.0, 555.0, white.clone()));
@@ -59,8 +62,17 @@ fn main() {
     hitable_list.push(Translate::translate(RotateY::new(RectBox::new(&Vec3::new(0., 0., 0.), &Vec3::new(165., 330., 165.), white.clone()),  15.0), &Vec3::new(265., 0., 295.)));
 
     let world: Box<dyn Hitable> = Box::new(hitable_list);
+
+    // the ceiling light only shines down into the box
+    let scene = Scene::new(world)
+        .with_light(AreaLight::new(FlipFace::new(XzRect::new(213.0, 343.0, 227.0, 332.0, 554.0, light))));
     
     // render
-    let image_data = render(&camera, &world, image_width, image_height, samples_per_pixel, max_depth);
+    let image_data = render(&camera, &scene, image_width, image_height, samples_per_pixel, max_depth);
     png::write_png("out/cornell.png", image_width, image_height, &image_data);
+
+    // a quick preview at a fraction of the samples, denoised with the first hit features
+    let aovs = render_aovs(&camera, &scene, image_width, image_height, 64, max_depth);
+    let image_data = ToneMapping::default().to_ldr(&Denoiser::new().denoise(&aovs));
+    png::write_png("out/cornell_denoised.png", image_width, image_height, &image_data);
 }
\ No newline at end of file
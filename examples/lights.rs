@@ -0,0 +1,86 @@
+extern crate raytracer;
+
+use std::sync::Arc;
+
+use raytracer::io::{png, hdr};
+use raytracer::objects::rect::{XzRect, XyRect};
+use raytracer::objects::sphere::Sphere;
+use raytracer::structures::camera::Camera;
+use raytracer::color::*;
+use raytracer::hitable::{Hitable, HitableList};
+use raytracer::lights::{PointLight, SpotLight, DirectionalLight};
+use raytracer::materials::{Lambertian, DiffuseLight};
+use raytracer::materials::conductor::Conductor;
+use raytracer::materials::principled::{Principled, constant};
+use raytracer::structures::vec3::*;
+use raytracer::render_light_groups;
+use raytracer::scene::Scene;
+use raytracer::tonemap::{ToneMap, ToneMapping};
+use raytracer::post::bloom::Bloom;
+use raytracer::post::vignette::Vignette;
+use raytracer::texture::*;
+
+fn main() {
+    // image
+    const ASPECT_RATIO: f32 = 16.0 / 9.0;
+    let image_width: u32 = 400;
+    let image_height: u32 = (image_width as f32 / ASPECT_RATIO) as u32;
+    let samples_per_pixel: u32 = 100;
+    let max_depth: u32 = 20;
+
+    // camera
+    let lookfrom = Vec3::new(0.0, 2.0, 7.0);
+    let lookat = Vec3::new(0.0, 1.0, 0.0);
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    let dist_to_focus = 10.0;
+    let aperture = 0.0;
+
+    let camera: Camera = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        50.0,
+        ASPECT_RATIO,
+        aperture,
+        dist_to_focus,
+        0.0,
+        1.0);
+
+    // a dim moonlight, a warm bulb, a spot on the gold sphere and a small
+    // emissive sphere, distances are in meters. Each light gets its own
+    // group for relighting.
+    let scene = Scene::new(world())
+        .with_light(DirectionalLight::new(Vec3::new(0.3, -1.0, -0.4), Color::new(0.6, 0.7, 1.0), 0.1).with_group("moon"))
+        .with_light(PointLight::from_power(Vec3::new(-2.0, 3.0, 1.5), Color::new(1.0, 0.8, 0.6), 150.0).with_group("bulb"))
+        .with_light(SpotLight::new(Vec3::new(3.0, 4.0, 2.0), Vec3::new(-0.6, -0.8, -0.4), WHITE, 40.0, 25.0).with_falloff(0.3).with_group("spot"))
+        .with_effect(Bloom::new(2.0, 0.1))
+        .with_effect(Vignette::new(0.4));
+
+    let images = render_light_groups(&camera, &scene, image_width, image_height, samples_per_pixel, max_depth);
+
+    // the bulb and the orb would clip without a filmic curve
+    let beauty = scene.post.apply(image_width, image_height, &images.beauty());
+    let image_data = ToneMapping::new(ToneMap::Aces).with_exposure(0.5).to_ldr(&beauty);
+    png::write_png("out/lights.png", image_width, image_height, &image_data);
+
+    for (group, pixels) in images.groups.iter() {
+        hdr::write_hdr(&format!("out/lights_{}.hdr", group), image_width, image_height, pixels);
+    }
+}
+
+fn world() -> Box<dyn Hitable> {
+    let mut world = HitableList::default();
+
+    let white = Arc::new(Lambertian::new(SolidColor::new(Color::new(0.7, 0.7, 0.7))));
+    world.push(XzRect::new(-20.0, 20.0, -20.0, 20.0, 0.0, white.clone()));
+    world.push(XyRect::new(-20.0, 20.0, 0.0, 20.0, -3.0, white));
+
+    let plastic = Principled { roughness: constant(0.3), ..Principled::new(SolidColor::new(Color::new(0.7, 0.1, 0.1))) };
+    world.push(Sphere::new(Vec3::new(-1.8, 0.8, 0.0), 0.8, Arc::new(plastic)));
+    world.push(Sphere::new(Vec3::new(0.6, 0.8, -0.5), 0.8, Arc::new(Conductor::gold(SolidColor::new(Color::new(0.3, 0.3, 0.3))))));
+
+    let emitter = Arc::new(DiffuseLight::new(SolidColor::new(Color::new(4.0, 6.0, 8.0))).with_group("orb"));
+    world.push(Sphere::new(Vec3::new(2.2, 0.3, 1.0), 0.3, emitter));
+
+    Box::new(world)
+}
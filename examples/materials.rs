@@ -0,0 +1,140 @@
+extern crate raytracer;
+
+use std::sync::Arc;
+
+use raytracer::objects::sphere::Sphere;
+use raytracer::structures::camera::Camera;
+use raytracer::color::Color;
+use raytracer::hitable::{Hitable, HitableList};
+use raytracer::materials::{Lambertian, Metal, DiffuseLight};
+use raytracer::materials::conductor::Conductor;
+use raytracer::materials::rough_dielectric::RoughDielectric;
+use raytracer::materials::principled::{Principled, constant};
+use raytracer::materials::mix::MixMaterial;
+use raytracer::materials::layered::Layered;
+use raytracer::materials::oren_nayar::OrenNayar;
+use raytracer::materials::velvet::Velvet;
+use raytracer::materials::thin_film::ThinFilm;
+use raytracer::materials::conductor::{ALUMINIUM_ETA, ALUMINIUM_K};
+use raytracer::structures::vec3::Vec3;
+use raytracer::render;
+use raytracer::scene::Scene;
+use raytracer::io::png;
+use raytracer::texture::*;
+use raytracer::bvh::BVH;
+
+fn main() {
+    // image
+    const ASPECT_RATIO: f32 = 2.0;
+    let image_width: u32 = 600;
+    let image_height: u32 = (image_width as f32 / ASPECT_RATIO) as u32;
+    let samples_per_pixel: u32 = 500;
+    let max_depth: u32 = 50;
+
+    // camera
+    let lookfrom = Vec3::new(0.0, 5.0, 9.0);
+    let lookat = Vec3::new(0.0, 0.5, -2.5);
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    let dist_to_focus = 12.5;
+    let aperture = 0.0;
+
+    let camera: Camera = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        35.0,
+        ASPECT_RATIO,
+        aperture,
+        dist_to_focus,
+        0.0,
+        1.0);
+
+    // world
+    let mut hitable_list = HitableList::default();
+
+    // ground
+    let ground = Arc::new(Lambertian::new(CheckerTexture::new(SolidColor::new(Color::new(0.2, 0.2, 0.2)), SolidColor::new(Color::new(0.8, 0.8, 0.8)))));
+    hitable_list.push(Sphere::new(Vec3::new(0.0, -1000.0, 0.0), 1000.0, ground));
+
+    // light
+    let light = Arc::new(DiffuseLight::new(SolidColor::new(Color::new(4.0, 4.0, 4.0))));
+    hitable_list.push(Sphere::new(Vec3::new(0.0, 40.0, 20.0), 15.0, light));
+
+    // rough conductors
+    let gold = Arc::new(Conductor::gold(SolidColor::new(Color::new(0.2, 0.2, 0.2))));
+    hitable_list.push(Sphere::new(Vec3::new(-3.3, 0.5, 0.0), 0.5, gold));
+
+    let copper = Arc::new(Conductor::copper(SolidColor::new(Color::new(0.4, 0.4, 0.4))));
+    hitable_list.push(Sphere::new(Vec3::new(-1.1, 0.5, 0.0), 0.5, copper));
+
+    // roughness driven by a texture
+    let aluminium = Arc::new(Conductor::aluminium(CheckerTexture::new(SolidColor::new(Color::new(0.05, 0.05, 0.05)), SolidColor::new(Color::new(0.5, 0.5, 0.5)))));
+    hitable_list.push(Sphere::new(Vec3::new(1.1, 0.5, 0.0), 0.5, aluminium));
+
+    // frosted glass
+    let frosted = Arc::new(RoughDielectric::new(1.5, SolidColor::new(Color::new(0.3, 0.3, 0.3))));
+    hitable_list.push(Sphere::new(Vec3::new(3.3, 0.5, 0.0), 0.5, frosted));
+
+    // principled
+    let plastic = Arc::new(Principled {
+        roughness: constant(0.3),
+        clearcoat: constant(1.0),
+        ..Principled::new(SolidColor::new(Color::new(0.8, 0.1, 0.1)))
+    });
+    hitable_list.push(Sphere::new(Vec3::new(-3.3, 0.5, -2.0), 0.5, plastic));
+
+    let velvet = Arc::new(Principled {
+        roughness: constant(1.0),
+        sheen: constant(1.0),
+        ..Principled::new(SolidColor::new(Color::new(0.1, 0.1, 0.5)))
+    });
+    hitable_list.push(Sphere::new(Vec3::new(-1.1, 0.5, -2.0), 0.5, velvet));
+
+    let brushed = Arc::new(Principled {
+        metallic: constant(1.0),
+        roughness: constant(0.4),
+        ..Principled::new(SolidColor::new(Color::new(0.9, 0.6, 0.3)))
+    });
+    hitable_list.push(Sphere::new(Vec3::new(1.1, 0.5, -2.0), 0.5, brushed));
+
+    let tinted_glass = Arc::new(Principled {
+        roughness: constant(0.05),
+        transmission: constant(1.0),
+        ior: constant(1.5),
+        ..Principled::new(SolidColor::new(Color::new(0.7, 0.9, 0.8)))
+    });
+    hitable_list.push(Sphere::new(Vec3::new(3.3, 0.5, -2.0), 0.5, tinted_glass));
+
+    // 70% diffuse and 30% metal
+    let diffuse = Arc::new(Lambertian::new(SolidColor::new(Color::new(0.2, 0.5, 0.2))));
+    let metal = Arc::new(Metal::new(Color::new(0.9, 0.9, 0.9), 0.1));
+    let mix = Arc::new(MixMaterial::new(diffuse, metal, SolidColor::new(Color::new(0.3, 0.3, 0.3))));
+    hitable_list.push(Sphere::new(Vec3::new(-1.1, 0.5, -4.0), 0.5, mix));
+
+    // varnish over a checkered base
+    let checker = Arc::new(Lambertian::new(CheckerTexture::new(SolidColor::new(Color::new(0.4, 0.2, 0.1)), SolidColor::new(Color::new(0.6, 0.4, 0.2)))));
+    let varnish = Arc::new(Layered::new(checker, 1.5, Color::new(0.95, 0.85, 0.6)));
+    hitable_list.push(Sphere::new(Vec3::new(1.1, 0.5, -4.0), 0.5, varnish));
+
+    // rough diffuse clay
+    let clay = Arc::new(OrenNayar::new(SolidColor::new(Color::new(0.7, 0.4, 0.3)), 30.0));
+    hitable_list.push(Sphere::new(Vec3::new(-3.3, 0.5, -4.0), 0.5, clay));
+
+    // fabric
+    let fabric = Arc::new(Velvet::new(SolidColor::new(Color::new(0.3, 0.02, 0.1)), SolidColor::new(Color::new(0.9, 0.6, 0.7)), 0.3));
+    hitable_list.push(Sphere::new(Vec3::new(3.3, 0.5, -4.0), 0.5, fabric));
+
+    // soap bubble, a film of water with air on both sides
+    let bubble = Arc::new(ThinFilm::dielectric(1.0, SolidColor::new(Color::new(380.0, 380.0, 380.0)), 1.33));
+    hitable_list.push(Sphere::new(Vec3::new(-1.1, 0.5, -6.0), 0.5, bubble));
+
+    // oil on metal with varying thickness
+    let oily = Arc::new(ThinFilm::conductor(ALUMINIUM_ETA, ALUMINIUM_K, CheckerTexture::new(SolidColor::new(Color::new(300.0, 300.0, 300.0)), SolidColor::new(Color::new(550.0, 550.0, 550.0))), 1.5));
+    hitable_list.push(Sphere::new(Vec3::new(1.1, 0.5, -6.0), 0.5, oily));
+
+    let world: Box<dyn Hitable> = Box::new(BVH::new(hitable_list.list, 0.0, 0.0));
+
+    // render
+    let image_data = render(&camera, &Scene::new(world), image_width, image_height, samples_per_pixel, max_depth);
+    png::write_png("out/materials.png", image_width, image_height, &image_data);
+}
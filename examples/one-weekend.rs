@@ -13,6 +13,7 @@ use raytracer::materials::Lambertian;
 use raytracer::materials::Metal;
 use raytracer::structures::vec3::Vec3;
 use raytracer::render;
+use raytracer::scene::Scene;
 use raytracer::io::png;
 use raytracer::bvh::BVH;
 use raytracer::texture::*;
@@ -93,6 +94,6 @@ fn main() {
     // let world: Box<dyn Hitable> = Box::new(world);
 
     // render
-    let image_data = render(&camera, &world, image_width, image_height, samples_per_pixel, max_depth);
+    let image_data = render(&camera, &Scene::new(world), image_width, image_height, samples_per_pixel, max_depth);
     png::write_png("out/one-weekend.png", image_width, image_height, &image_data);
 }
\ No newline at end of file
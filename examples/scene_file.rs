@@ -0,0 +1,19 @@
+extern crate raytracer;
+
+use std::env;
+use std::path::Path;
+
+use raytracer::io::png;
+use raytracer::io::scene_file::SceneFile;
+use raytracer::render;
+
+// renders the scene file given as the first argument to out/<name>.png
+fn main() {
+    let path = env::args().nth(1).unwrap_or_else(|| String::from("files/scenes/principled.scene"));
+    let scene_file = SceneFile::load(&path);
+
+    let image_data = render(&scene_file.camera, &scene_file.scene, scene_file.width, scene_file.height, scene_file.samples_per_pixel, scene_file.max_depth);
+
+    let name = Path::new(&path).file_stem().and_then(|name| name.to_str()).unwrap_or("scene");
+    png::write_png(&format!("out/{}.png", name), scene_file.width, scene_file.height, &image_data);
+}
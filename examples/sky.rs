@@ -0,0 +1,64 @@
+extern crate raytracer;
+
+use std::sync::Arc;
+
+use raytracer::io::png;
+use raytracer::objects::rect::XzRect;
+use raytracer::objects::sphere::Sphere;
+use raytracer::structures::camera::Camera;
+use raytracer::color::*;
+use raytracer::hitable::{Hitable, HitableList};
+use raytracer::materials::{Lambertian, Metal, Dielectric};
+use raytracer::environment::sky::{PreethamSky, sun_direction};
+use raytracer::structures::vec3::*;
+use raytracer::render;
+use raytracer::scene::Scene;
+use raytracer::texture::*;
+
+fn main() {
+    // image
+    const ASPECT_RATIO: f32 = 16.0 / 9.0;
+    let image_width: u32 = 400;
+    let image_height: u32 = (image_width as f32 / ASPECT_RATIO) as u32;
+    let samples_per_pixel: u32 = 200;
+    let max_depth: u32 = 50;
+
+    // camera looking north
+    let lookfrom = Vec3::new(0.0, 1.5, 8.0);
+    let lookat = Vec3::new(0.0, 1.5, 0.0);
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    let dist_to_focus = 10.0;
+    let aperture = 0.0;
+
+    let camera: Camera = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        60.0,
+        ASPECT_RATIO,
+        aperture,
+        dist_to_focus,
+        0.0,
+        1.0);
+
+    // late afternoon in Munich on the summer solstice
+    let sun = sun_direction(48.14, 11.58, 6, 21, 17.0);
+    let sky = PreethamSky::new(sun, 3.0).with_ground_albedo(Color::new(0.25, 0.22, 0.18));
+
+    let image_data = render(&camera, &Scene::new(world()).with_environment(sky), image_width, image_height, samples_per_pixel, max_depth);
+
+    png::write_png("out/sky.png", image_width, image_height, &image_data);
+}
+
+fn world() -> Box<dyn Hitable> {
+    let mut world = HitableList::default();
+
+    let ground = Arc::new(Lambertian::new(SolidColor::new(Color::new(0.4, 0.38, 0.35))));
+    world.push(XzRect::new(-100.0, 100.0, -100.0, 100.0, 0.0, ground));
+
+    world.push(Sphere::new(Vec3::new(-2.2, 1.0, 0.0), 1.0, Arc::new(Lambertian::new(SolidColor::new(Color::new(0.7, 0.7, 0.7))))));
+    world.push(Sphere::new(Vec3::new(0.0, 1.0, 0.0), 1.0, Arc::new(Dielectric::new(1.5))));
+    world.push(Sphere::new(Vec3::new(2.2, 1.0, 0.0), 1.0, Arc::new(Metal::new(Color::new(0.9, 0.9, 0.9), 0.05))));
+
+    Box::new(world)
+}
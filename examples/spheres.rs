@@ -7,8 +7,10 @@ use raytracer::structures::camera::Camera;
 use raytracer::color::{Color, BLACK, WHITE};
 use raytracer::hitable::{Hitable, HitableList};
 use raytracer::materials::{Lambertian, Metal, Dielectric, DiffuseLight};
+use raytracer::materials::normal_map::Bump;
 use raytracer::structures::vec3::Vec3;
 use raytracer::render;
+use raytracer::scene::Scene;
 use raytracer::io::png;
 use raytracer::texture::*;
 use raytracer::bvh::BVH;
@@ -59,16 +61,22 @@ fn main() {
     hitable_list.push(jupiter);
 
     // earth
-    let earth = Sphere::new(Vec3::new(-0.4, 0.089, 0.5), 0.089, Arc::new(Lambertian::new(ImageTexture::new("files/earthmap.png"))));
+    // grayscale height maps, the land of the earth rises above the sea and
+    // the bright highlands of the moon above its maria
+    let earth_surface = Arc::new(Lambertian::new(ImageTexture::new("files/earthmap.png")));
+    let earth_height = ImageTexture::open("files/earthbump.png", ColorSpace::Raw);
+    let earth = Sphere::new(Vec3::new(-0.4, 0.089, 0.5), 0.089, Arc::new(Bump::new(earth_surface, earth_height, 0.0005)));
     hitable_list.push(earth);
   
     // moon
-    let moon = Sphere::new(Vec3::new(-0.3, 0.089 * 0.2731, 0.60), 0.089 * 0.2731, Arc::new(Lambertian::new(ImageTexture::new("files/moonmap1k.jpg"))));
+    let moon_surface = Arc::new(Lambertian::new(ImageTexture::new("files/moonmap1k.jpg")));
+    let moon_height = ImageTexture::open("files/moonbump.png", ColorSpace::Raw);
+    let moon = Sphere::new(Vec3::new(-0.3, 0.089 * 0.2731, 0.60), 0.089 * 0.2731, Arc::new(Bump::new(moon_surface, moon_height, 0.0002)));
     hitable_list.push(moon);
     
     let world: Box<dyn Hitable> = Box::new(BVH::new(hitable_list.list, 0.0, 0.0));
     
     // render
-    let image_data = render(&camera, &world, image_width, image_height, samples_per_pixel, max_depth);
+    let image_data = render(&camera, &Scene::new(world), image_width, image_height, samples_per_pixel, max_depth);
     png::write_png("out/spheres.png", image_width, image_height, &image_data);
 }
\ No newline at end of file
@@ -0,0 +1,89 @@
+extern crate raytracer;
+
+use std::sync::Arc;
+
+use raytracer::io::png;
+use raytracer::io::obj::load_file;
+use raytracer::objects::sphere::Sphere;
+use raytracer::objects::subsurface_volume::SubsurfaceVolume;
+use raytracer::objects::Translate;
+use raytracer::structures::camera::Camera;
+use raytracer::color::*;
+use raytracer::hitable::{Hitable, HitableList};
+use raytracer::materials::{Lambertian, DiffuseLight};
+use raytracer::materials::subsurface::Subsurface;
+use raytracer::structures::vec3::*;
+use raytracer::render;
+use raytracer::scene::Scene;
+use raytracer::texture::*;
+use raytracer::bvh::BVH;
+
+fn main() {
+    // image
+    const ASPECT_RATIO: f32 = 3.0 / 2.0;
+    let image_width: u32 = 400;
+    let image_height: u32 = (image_width as f32 / ASPECT_RATIO) as u32;
+    let samples_per_pixel: u32 = 500;
+    // random walks need many more bounces than surfaces
+    let max_depth: u32 = 256;
+
+    // camera
+    let lookfrom = Vec3::new(0.0, 8.0, 14.0);
+    let lookat = Vec3::new(1.0, 1.2, 0.0);
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    let dist_to_focus = 10.0;
+    let aperture = 0.0;
+
+    let camera: Camera = Camera::new(
+        lookfrom,
+        lookat,
+        vup,
+        30.0,
+        ASPECT_RATIO,
+        aperture,
+        dist_to_focus,
+        0.0,
+        1.0);
+
+    // world
+    let world = world();
+
+    let image_data = render(&camera, &Scene::new(world), image_width, image_height, samples_per_pixel, max_depth);
+
+    png::write_png("out/subsurface.png", image_width, image_height, &image_data);
+}
+
+fn world() -> Box<dyn Hitable> {
+    let mut world = HitableList::default();
+
+    // ground
+    let material_ground = Arc::new(Lambertian::new(SolidColor::new(Color::new(0.5, 0.5, 0.5))));
+    world.push(Sphere::new(Vec3::new(0.0, -1000.0, 0.0), 1000.0, material_ground));
+
+    // wax teapot
+    let wax = Arc::new(Subsurface::new(Color::new(0.9, 0.75, 0.5), Color::new(0.6, 0.3, 0.15), 1.4));
+    if let Some(model) = load_file("files/teapot.obj", wax.clone()) {
+        world.push(SubsurfaceVolume::new(BVH::new(model, 0.0, 0.0), wax));
+    };
+
+    // jade dragon, left out unless the model has been downloaded to files/
+    let jade = Arc::new(Subsurface::new(Color::new(0.3, 0.8, 0.4), Color::new(0.05, 0.2, 0.08), 1.6));
+    if let Some(model) = load_file("files/dragon.obj", jade.clone()) {
+        world.push(Translate::translate(SubsurfaceVolume::new(BVH::new(model, 0.0, 0.0), jade), &Vec3::new(4.5, 0.6, 1.0)));
+    };
+
+    // marble sphere
+    let marble = Arc::new(Subsurface::new(Color::new(0.85, 0.85, 0.8), Color::new(0.4, 0.35, 0.3), 1.5));
+    world.push(SubsurfaceVolume::new(Sphere::new(Vec3::new(-4.5, 1.0, 2.0), 1.0, marble.clone()), marble));
+
+    // light
+    let main_light_color = Arc::new(DiffuseLight::new(SolidColor::new(Color::new(1.0, 0.95, 0.95))));
+    let main_light = Sphere::new(Vec3::new(-100.0, 75.0, 0.0), 70.0, main_light_color);
+    world.push(main_light);
+
+    let other_light_color = Arc::new(DiffuseLight::new(SolidColor::new(Color::new(1.0, 0.95, 0.75))));
+    let second_light = Sphere::new(Vec3::new(50.0, 75.0, -30.0), 40.0, other_light_color);
+    world.push(second_light);
+
+    Box::new(BVH::new(world.list, 0.0, 0.0))
+}
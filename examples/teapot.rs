@@ -11,6 +11,7 @@ use raytracer::hitable::{Hitable, HitableList};
 use raytracer::materials::{Lambertian, Dielectric, Metal, DiffuseLight};
 use raytracer::structures::vec3::*;
 use raytracer::render;
+use raytracer::scene::Scene;
 use raytracer::texture::*;
 use raytracer::bvh::BVH;
 
@@ -43,7 +44,7 @@ fn main() {
     // world
     let world = world();
 
-    let image_data = render(&camera, &world, image_width, image_height, samples_per_pixel, max_depth);
+    let image_data = render(&camera, &Scene::new(world), image_width, image_height, samples_per_pixel, max_depth);
     
     png::write_png("out/teapot.png", image_width, image_height, &image_data);
 }
@@ -0,0 +1,199 @@
+use crate::color::{Color, BLACK};
+
+// arbitrary output variables, auxiliary images taken from the first surface
+// the camera rays hit and filtered over the samples around a pixel. The light
+// is filtered like the beauty image, the features of the surface only with
+// the positive part of the filter so they stay averages of their samples.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Aov {
+    // distance in front of the camera along the viewing direction
+    Depth,
+    // world space position
+    Position,
+    // world space shading normal facing the camera
+    Normal,
+    Albedo,
+    // texture coordinates in red and green
+    Uv,
+    // movement in pixels over the shutter interval in red (right) and
+    // green (down)
+    Motion,
+    // light reflected by the first surface straight from the lights and the
+    // environment, or after more bounces, through its diffuse or specular
+    // lobes. With the emission seen directly they add up to the beauty image.
+    DirectDiffuse,
+    DirectSpecular,
+    IndirectDiffuse,
+    IndirectSpecular,
+}
+
+pub const AOVS: [Aov; 10] = [
+    Aov::Depth,
+    Aov::Position,
+    Aov::Normal,
+    Aov::Albedo,
+    Aov::Uv,
+    Aov::Motion,
+    Aov::DirectDiffuse,
+    Aov::DirectSpecular,
+    Aov::IndirectDiffuse,
+    Aov::IndirectSpecular,
+];
+
+impl Aov {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Depth => "depth",
+            Aov::Position => "position",
+            Aov::Normal => "normal",
+            Aov::Albedo => "albedo",
+            Aov::Uv => "uv",
+            Aov::Motion => "motion",
+            Aov::DirectDiffuse => "direct_diffuse",
+            Aov::DirectSpecular => "direct_specular",
+            Aov::IndirectDiffuse => "indirect_diffuse",
+            Aov::IndirectSpecular => "indirect_specular",
+        }
+    }
+
+    // the outputs holding light, which add up to the beauty image
+    pub fn is_radiance(&self) -> bool {
+        matches!(self, Aov::DirectDiffuse | Aov::DirectSpecular | Aov::IndirectDiffuse | Aov::IndirectSpecular)
+    }
+}
+
+// the values of all outputs for one camera sample, misses leave them black
+#[derive(Clone, Copy)]
+pub struct AovSample {
+    values: [Color; AOVS.len()],
+}
+
+impl AovSample {
+    pub fn new() -> Self {
+        AovSample { values: [BLACK; AOVS.len()] }
+    }
+
+    pub fn get(&self, aov: Aov) -> Color {
+        self.values[aov as usize]
+    }
+
+    pub fn set(&mut self, aov: Aov, value: Color) {
+        self.values[aov as usize] = value;
+    }
+
+    pub fn add(&mut self, aov: Aov, value: Color) {
+        self.values[aov as usize] = self.values[aov as usize] + value;
+    }
+
+    pub fn merge(mut self, other: AovSample) -> Self {
+        for (value, other) in self.values.iter_mut().zip(other.values.iter()) {
+            *value = *value + *other;
+        }
+
+        self
+    }
+}
+
+impl Default for AovSample {
+    fn default() -> Self {
+        AovSample::new()
+    }
+}
+
+// all outputs with rows from the top. The ids are read from a single ray
+// through the center of every pixel, zero where nothing was hit. Materials
+// are numbered from one in the order they are first seen.
+pub struct AovImages {
+    pub width: u32,
+    pub height: u32,
+    pub beauty: Vec<Color>,
+    pub layers: Vec<Vec<Color>>,
+    pub material_id: Vec<u32>,
+    pub object_id: Vec<u32>,
+}
+
+impl AovImages {
+    pub fn new(width: u32, height: u32) -> Self {
+        let pixels = (width * height) as usize;
+
+        AovImages {
+            width,
+            height,
+            beauty: vec![BLACK; pixels],
+            layers: vec![vec![BLACK; pixels]; AOVS.len()],
+            material_id: vec![0; pixels],
+            object_id: vec![0; pixels],
+        }
+    }
+
+    pub fn get(&self, aov: Aov) -> &[Color] {
+        &self.layers[aov as usize]
+    }
+
+    // `scale` weights the light and `feature_scale` all other outputs
+    pub fn add(&mut self, index: usize, sample: &AovSample, scale: f32, feature_scale: f32) {
+        for ((layer, value), aov) in self.layers.iter_mut().zip(sample.values.iter()).zip(AOVS.iter()) {
+            let scale = if aov.is_radiance() { scale } else { feature_scale };
+            layer[index] = layer[index] + scale * *value;
+        }
+    }
+
+    // every output as a named layer for `io::exr`, ids are stored as exact
+    // float values in all three channels
+    pub fn exr_layers(&self) -> Vec<(&'static str, Vec<Color>)> {
+        let ids = |ids: &[u32]| ids.iter().map(|id| Color::new(*id as f32, *id as f32, *id as f32)).collect();
+
+        let mut layers = vec![("", self.beauty.clone())];
+        layers.extend(AOVS.iter().map(|aov| (aov.name(), self.get(*aov).to_vec())));
+        layers.push(("material_id", ids(&self.material_id)));
+        layers.push(("object_id", ids(&self.object_id)));
+
+        layers
+    }
+}
+
+// a distinct color for every id to look at id images, black for zero
+pub fn id_color(id: u32) -> Color {
+    if id == 0 {
+        return BLACK;
+    }
+
+    // spreading consecutive ids around the hue circle by the golden angle
+    let hue = (id as f32 * 0.618034).fract() * 6.0;
+    let x = 1.0 - (hue % 2.0 - 1.0).abs();
+
+    match hue as u32 {
+        0 => Color::new(1.0, x, 0.0),
+        1 => Color::new(x, 1.0, 0.0),
+        2 => Color::new(0.0, 1.0, x),
+        3 => Color::new(0.0, x, 1.0),
+        4 => Color::new(x, 0.0, 1.0),
+        _ => Color::new(1.0, 0.0, x),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn samples_are_averaged_into_layers() {
+        let mut sample = AovSample::new();
+        sample.set(Aov::Depth, Color::new(2.0, 2.0, 2.0));
+        sample.add(Aov::DirectDiffuse, Color::new(1.0, 0.0, 0.0));
+        let sample = sample.merge(sample);
+
+        let mut images = AovImages::new(2, 1);
+        images.add(1, &sample, 0.5, 0.5);
+        images.material_id[1] = 16777216;
+
+        assert_eq!(images.get(Aov::Depth)[1].g, 2.0);
+        assert_eq!(images.get(Aov::DirectDiffuse)[1].r, 1.0);
+        assert_eq!(images.get(Aov::IndirectSpecular)[1].r, 0.0);
+
+        let layers = images.exr_layers();
+        assert_eq!(layers.len(), AOVS.len() + 3);
+        assert_eq!(layers[AOVS.len() + 1].1[1].r, 16777216.0);
+    }
+}
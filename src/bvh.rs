@@ -73,14 +73,14 @@ impl BVH {
 }
 
 impl Hitable for BVH {
-    fn hit(&self, ray: &Ray, t_min: f32, mut t_max: f32) -> Option<HitRecord> {
-        if self.bbox.hit(&ray, t_min, t_max) {
+    fn hit(&self, ray: &Ray, t_min: f32, mut t_max: f32) -> Option<HitRecord<'_>> {
+        if self.bbox.hit(ray, t_min, t_max) {
             match &self.tree {
-                BVHNode::Leaf(leaf) => leaf.hit(&ray, t_min, t_max),
+                BVHNode::Leaf(leaf) => leaf.hit(ray, t_min, t_max),
                 BVHNode::Branch { left, right} => {
-                    let left = left.hit(&ray, t_min, t_max);
+                    let left = left.hit(ray, t_min, t_max);
                     if let Some(l) = &left { t_max = l.t };
-                    let right = right.hit(&ray, t_min, t_max);
+                    let right = right.hit(ray, t_min, t_max);
                     if right.is_some() { right } else { left }
                 }
             }
@@ -15,6 +15,10 @@ impl Color {
         Color {r, g, b}
     }
 
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
     pub fn random() -> Color {
         Color {
             r: random_double(),
@@ -87,18 +91,60 @@ impl Mul<f32> for Color {
     }
 }
 
-pub fn gamma_correct(color: Color) -> Color {
+// sRGB transfer functions, see IEC 61966-2-1
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        12.92 * value
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub fn encode_srgb(color: Color) -> Color {
     Color {
-        r: color.r.sqrt(),
-        g: color.g.sqrt(),
-        b: color.b.sqrt(),
+        r: linear_to_srgb(color.r.max(0.0)),
+        g: linear_to_srgb(color.g.max(0.0)),
+        b: linear_to_srgb(color.b.max(0.0)),
     }
 }
 
+// a row major 3x3 matrix applied to the color
+pub fn transform(m: [[f32; 3]; 3], c: Color) -> Color {
+    Color::new(
+        m[0][0] * c.r + m[0][1] * c.g + m[0][2] * c.b,
+        m[1][0] * c.r + m[1][1] * c.g + m[1][2] * c.b,
+        m[2][0] * c.r + m[2][1] * c.g + m[2][2] * c.b,
+    )
+}
+
 pub fn map_color_256(color: Color) -> [u8; 3] {
     [
         (256.0 * utils::clamp(color.r, 0.0, 0.999)) as u8,
         (256.0 * utils::clamp(color.g, 0.0, 0.999)) as u8, 
         (256.0 * utils::clamp(color.b, 0.0, 0.999)) as u8,
     ]
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn srgb_roundtrip() {
+        for i in 0..=20 {
+            let value = i as f32 / 20.0;
+            assert!((linear_to_srgb(srgb_to_linear(value)) - value).abs() < 0.0001);
+        }
+
+        assert!((srgb_to_linear(0.5) - 0.214).abs() < 0.001);
+    }
+}
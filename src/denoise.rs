@@ -0,0 +1,176 @@
+use rayon::prelude::*;
+
+use crate::aov::{Aov, AovImages};
+use crate::color::{Color, BLACK};
+
+// B3 spline taps of the à-trous wavelet, spread 2^i pixels apart in the
+// i-th iteration
+const KERNEL: [f32; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+// Edge avoiding à-trous wavelet filter of Dammertz et al. 2010, "Edge-Avoiding
+// À-Trous Wavelet Transform for fast Global Illumination Filtering", with the
+// normal and depth weights of SVGF. The lighting is filtered with the albedo
+// divided out so textures stay sharp, and neighbours only contribute where
+// the albedo, normal, depth and color are similar. Larger sigmas blur more.
+pub struct Denoiser {
+    pub iterations: u32,
+    pub color_sigma: f32,
+    pub albedo_sigma: f32,
+    // exponent of the cosine between normals
+    pub normal_power: f32,
+    // relative to the depth and the distance between the pixels
+    pub depth_sigma: f32,
+}
+
+impl Denoiser {
+    pub fn new() -> Self {
+        Denoiser {
+            iterations: 5,
+            color_sigma: 0.5,
+            albedo_sigma: 0.1,
+            normal_power: 64.0,
+            depth_sigma: 0.05,
+        }
+    }
+
+    pub fn with_iterations(self, iterations: u32) -> Self {
+        Denoiser { iterations, ..self }
+    }
+
+    // the denoised beauty image of `images`, rows from the top
+    pub fn denoise(&self, images: &AovImages) -> Vec<Color> {
+        let albedo = images.get(Aov::Albedo);
+        let normal = images.get(Aov::Normal);
+        let depth = images.get(Aov::Depth);
+
+        let mut lighting: Vec<Color> = images.beauty.iter().zip(albedo.iter())
+            .map(|(color, albedo)| demodulate(*color, *albedo))
+            .collect();
+
+        for iteration in 0..self.iterations {
+            let step = 1 << iteration;
+            // the color weight narrows as the noise is removed
+            let color_sigma = self.color_sigma / (1 << iteration) as f32;
+
+            lighting = (0..lighting.len()).into_par_iter().map(|index| {
+                let (x, y) = ((index % images.width as usize) as i32, (index / images.width as usize) as i32);
+                let compressed = compress(lighting[index]);
+
+                let mut sum = BLACK;
+                let mut total_weight = 0.0;
+
+                for (j, ky) in KERNEL.iter().enumerate() {
+                    for (i, kx) in KERNEL.iter().enumerate() {
+                        let (qx, qy) = (x + (i as i32 - 2) * step, y + (j as i32 - 2) * step);
+                        if qx < 0 || qy < 0 || qx >= images.width as i32 || qy >= images.height as i32 {
+                            continue;
+                        }
+
+                        let q = (qy * images.width as i32 + qx) as usize;
+
+                        let color_weight = (-distance_squared(compressed, compress(lighting[q])) / (color_sigma * color_sigma)).exp();
+                        let albedo_weight = (-distance_squared(albedo[index], albedo[q]) / (self.albedo_sigma * self.albedo_sigma)).exp();
+                        let normal_weight = self.normal_weight(normal[index], normal[q]);
+                        let depth_weight = self.depth_weight(depth[index].r, depth[q].r, step);
+
+                        let weight = kx * ky * color_weight * albedo_weight * normal_weight * depth_weight;
+                        sum = sum + weight * lighting[q];
+                        total_weight += weight;
+                    }
+                }
+
+                if total_weight > 0.0 { (1.0 / total_weight) * sum } else { lighting[index] }
+            })
+            .collect();
+        }
+
+        lighting.iter().zip(albedo.iter())
+            .map(|(lighting, albedo)| remodulate(*lighting, *albedo))
+            .collect()
+    }
+
+    fn normal_weight(&self, a: Color, b: Color) -> f32 {
+        let lengths = (distance_squared(a, BLACK) * distance_squared(b, BLACK)).sqrt();
+
+        // pixels that hit nothing only blend with each other
+        if lengths <= 0.0 {
+            return if a.r == b.r && a.g == b.g && a.b == b.b { 1.0 } else { 0.0 };
+        }
+
+        let cos_theta = (a.r * b.r + a.g * b.g + a.b * b.b) / lengths;
+
+        cos_theta.max(0.0).powf(self.normal_power)
+    }
+
+    fn depth_weight(&self, a: f32, b: f32, step: i32) -> f32 {
+        let scale = self.depth_sigma * step as f32 * a.abs().max(b.abs());
+
+        if scale > 0.0 { (-(a - b).abs() / scale).exp() } else { 1.0 }
+    }
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Denoiser::new()
+    }
+}
+
+// an albedo this dark carries no texture worth keeping
+const MIN_ALBEDO: f32 = 0.01;
+
+fn demodulate(color: Color, albedo: Color) -> Color {
+    let channel = |c: f32, a: f32| if a > MIN_ALBEDO { c / a } else { c };
+
+    Color::new(channel(color.r, albedo.r), channel(color.g, albedo.g), channel(color.b, albedo.b))
+}
+
+fn remodulate(lighting: Color, albedo: Color) -> Color {
+    let channel = |l: f32, a: f32| if a > MIN_ALBEDO { l * a } else { l };
+
+    Color::new(channel(lighting.r, albedo.r), channel(lighting.g, albedo.g), channel(lighting.b, albedo.b))
+}
+
+// bright and dark noise compared on a similar scale
+fn compress(color: Color) -> Color {
+    Color::new(color.r / (1.0 + color.r.max(0.0)), color.g / (1.0 + color.g.max(0.0)), color.b / (1.0 + color.b.max(0.0)))
+}
+
+fn distance_squared(a: Color, b: Color) -> f32 {
+    let (r, g, b) = (a.r - b.r, a.g - b.g, a.b - b.b);
+
+    r * r + g * g + b * b
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::random::random_double;
+
+    #[test]
+    fn noise_is_removed_without_crossing_albedo_edges() {
+        let (width, height) = (32, 32);
+        let mut images = AovImages::new(width, height);
+
+        for i in 0..(width * height) as usize {
+            let albedo = if i % width as usize <= 15 { Color::new(0.8, 0.8, 0.8) } else { Color::new(0.2, 0.2, 0.2) };
+            let noise = 0.5 + random_double();
+
+            images.beauty[i] = noise * albedo;
+            images.layers[Aov::Albedo as usize][i] = albedo;
+            images.layers[Aov::Normal as usize][i] = Color::new(0.0, 0.0, 1.0);
+            images.layers[Aov::Depth as usize][i] = Color::new(5.0, 5.0, 5.0);
+        }
+
+        let denoised = Denoiser::new().denoise(&images);
+
+        let error = |pixels: &[Color], x: usize| -> f32 {
+            let expected = images.get(Aov::Albedo)[x].r;
+            (0..height as usize).map(|y| (pixels[y * width as usize + x].r - expected).abs()).sum::<f32>() / height as f32
+        };
+
+        for x in [2, 15, 16, 29].iter() {
+            assert!(error(&denoised, *x) < 0.5 * error(&images.beauty, *x));
+        }
+    }
+}
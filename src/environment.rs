@@ -0,0 +1,254 @@
+pub mod sky;
+
+use std::f32::consts::PI;
+
+use crate::color::{Color, linear_blend, srgb_to_linear};
+use crate::io::image_file;
+use crate::random::random_double;
+use crate::structures::distribution::Distribution2D;
+use crate::structures::vec3::Vec3;
+use crate::utils;
+
+// light arriving from infinitely far away, seen by rays that leave the scene
+pub trait Environment: Sync + Send {
+    fn radiance(&self, direction: &Vec3) -> Color;
+
+    // a direction towards the environment, its radiance and the solid angle
+    // density it was sampled with
+    fn sample(&self) -> Option<(Vec3, Color, f32)>;
+
+    fn pdf(&self, direction: &Vec3) -> f32;
+
+    // the light group the environment is written to, if any
+    fn group(&self) -> Option<&str> {
+        None
+    }
+}
+
+// equirectangular map with +y up, `rotation` turns it counterclockwise
+// around the y axis in degrees and `intensity` scales its radiance
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    texels: Vec<Color>,
+    distribution: Distribution2D,
+    rotation: f32,
+    pub intensity: f32,
+    pub group: Option<String>,
+}
+
+impl EnvironmentMap {
+    // float images are read as linear and all others as sRGB
+    pub fn new(path: &str) -> Self {
+        EnvironmentMap::read(path).unwrap_or_else(|error| panic!("Environment map {} could not be loaded: {}", path, error))
+    }
+
+    pub fn read(path: &str) -> Result<Self, String> {
+        let image = image_file::read(path)?;
+        let texels = if image.float {
+            image.texels
+        } else {
+            image.texels.iter()
+                .map(|c| Color::new(srgb_to_linear(c.r), srgb_to_linear(c.g), srgb_to_linear(c.b)))
+                .collect()
+        };
+
+        Ok(EnvironmentMap::from_texels(image.width, image.height, texels))
+    }
+
+    pub fn from_texels(width: usize, height: usize, texels: Vec<Color>) -> Self {
+        assert!(width > 0 && height > 0 && texels.len() == width * height);
+
+        let mut map = EnvironmentMap {
+            width,
+            height,
+            texels,
+            distribution: Distribution2D::new(&[1.0], 1),
+            rotation: 0.0,
+            intensity: 1.0,
+            group: None,
+        };
+
+        // the bilinear lookup spreads every texel over its neighbours, taking
+        // the brightest of them keeps the density above zero wherever there
+        // is light. Rows near the poles cover less solid angle.
+        let function: Vec<f32> = (0..width * height)
+            .map(|i| {
+                let (x, y) = ((i % width) as i32, (i / width) as i32);
+                let brightest = (-1..=1)
+                    .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                    .map(|(dx, dy)| map.texel(x + dx, y + dy).luminance())
+                    .fold(0.0f32, f32::max);
+                let elevation = PI * (0.5 - (y as f32 + 0.5) / height as f32);
+
+                brightest * elevation.cos()
+            })
+            .collect();
+
+        map.distribution = Distribution2D::new(&function, width);
+
+        map
+    }
+
+    pub fn with_rotation(self, degrees: f32) -> Self {
+        EnvironmentMap { rotation: degrees.to_radians(), ..self }
+    }
+
+    pub fn with_intensity(self, intensity: f32) -> Self {
+        EnvironmentMap { intensity, ..self }
+    }
+
+    pub fn with_group(self, group: &str) -> Self {
+        EnvironmentMap { group: Some(group.to_string()), ..self }
+    }
+
+    fn texel(&self, x: i32, y: i32) -> Color {
+        let x = x.rem_euclid(self.width as i32) as usize;
+        let y = y.max(0).min(self.height as i32 - 1) as usize;
+
+        self.texels[y * self.width + x]
+    }
+
+    // (s, t) in [0, 1] with t going down the image, matching the uv
+    // parameterisation of spheres
+    fn direction_to_st(&self, direction: &Vec3) -> (f32, f32) {
+        let d = rotate_y(&direction.normalize(), -self.rotation);
+        let phi = d.z.atan2(d.x);
+        let elevation = utils::clamp(d.y, -1.0, 1.0).asin();
+
+        (1.0 - (phi + PI) / (2.0 * PI), 0.5 - elevation / PI)
+    }
+
+    fn st_to_direction(&self, s: f32, t: f32) -> Vec3 {
+        let phi = (1.0 - s) * 2.0 * PI - PI;
+        let elevation = (0.5 - t) * PI;
+        let d = Vec3::new(elevation.cos() * phi.cos(), elevation.sin(), elevation.cos() * phi.sin());
+
+        rotate_y(&d, self.rotation)
+    }
+}
+
+impl Environment for EnvironmentMap {
+    fn radiance(&self, direction: &Vec3) -> Color {
+        let (s, t) = self.direction_to_st(direction);
+        let x = s * self.width as f32 - 0.5;
+        let y = t * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor() as i32, y.floor() as i32);
+        let (dx, dy) = (x - x.floor(), y - y.floor());
+
+        let color = linear_blend(dy,
+            linear_blend(dx, self.texel(x0, y0), self.texel(x0 + 1, y0)),
+            linear_blend(dx, self.texel(x0, y0 + 1), self.texel(x0 + 1, y0 + 1)));
+
+        self.intensity * color
+    }
+
+    fn sample(&self) -> Option<(Vec3, Color, f32)> {
+        let ((s, t), pdf) = self.distribution.sample(random_double(), random_double());
+        let cos_elevation = ((0.5 - t) * PI).cos();
+
+        if pdf <= 0.0 || cos_elevation <= 0.0 {
+            return None;
+        }
+
+        let direction = self.st_to_direction(s, t);
+
+        Some((direction, self.radiance(&direction), pdf / (2.0 * PI * PI * cos_elevation)))
+    }
+
+    fn pdf(&self, direction: &Vec3) -> f32 {
+        let (s, t) = self.direction_to_st(direction);
+        let cos_elevation = ((0.5 - t) * PI).cos();
+
+        if cos_elevation <= 0.0 {
+            return 0.0;
+        }
+
+        self.distribution.pdf(s, t) / (2.0 * PI * PI * cos_elevation)
+    }
+
+    fn group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+}
+
+// counterclockwise around the y axis when looking down from +y
+fn rotate_y(v: &Vec3, radians: f32) -> Vec3 {
+    let (sin, cos) = radians.sin_cos();
+
+    Vec3::new(cos * v.x + sin * v.z, v.y, -sin * v.x + cos * v.z)
+}
+
+// uniform radiance from every direction
+pub struct UniformEnvironment {
+    pub color: Color,
+    pub group: Option<String>,
+}
+
+impl UniformEnvironment {
+    pub fn new(color: Color) -> Self {
+        UniformEnvironment { color, group: None }
+    }
+
+    pub fn with_group(self, group: &str) -> Self {
+        UniformEnvironment { group: Some(group.to_string()), ..self }
+    }
+}
+
+impl Environment for UniformEnvironment {
+    fn radiance(&self, _direction: &Vec3) -> Color {
+        self.color
+    }
+
+    fn sample(&self) -> Option<(Vec3, Color, f32)> {
+        if self.color.luminance() <= 0.0 {
+            return None;
+        }
+
+        let z = 1.0 - 2.0 * random_double();
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * random_double();
+
+        Some((Vec3::new(r * phi.cos(), r * phi.sin(), z), self.color, 1.0 / (4.0 * PI)))
+    }
+
+    fn pdf(&self, _direction: &Vec3) -> f32 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::color::BLACK;
+
+    #[test]
+    fn directions_roundtrip_through_the_map() {
+        let map = EnvironmentMap::from_texels(4, 2, vec![BLACK; 8]).with_rotation(30.0);
+        let direction = Vec3::new(0.3, 0.5, -0.8).normalize();
+        let (s, t) = map.direction_to_st(&direction);
+        let back = map.st_to_direction(s, t);
+
+        assert!((back - direction).length() < 0.0001);
+    }
+
+    #[test]
+    fn sampling_finds_the_bright_texel() {
+        let mut texels = vec![BLACK; 16 * 8];
+        texels[3 * 16 + 5] = Color::new(10.0, 10.0, 10.0);
+        let map = EnvironmentMap::from_texels(16, 8, texels);
+
+        let (direction, radiance, pdf) = map.sample().unwrap();
+        let (s, t) = map.direction_to_st(&direction);
+
+        // the bright texel and its neighbours are the only ones sampled
+        assert!(((s * 16.0) as i32 - 5).abs() <= 1 && ((t * 8.0) as i32 - 3).abs() <= 1);
+        assert!(radiance.r >= 0.0);
+        assert!((map.pdf(&direction) - pdf).abs() < 0.001 * pdf);
+    }
+}
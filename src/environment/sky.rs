@@ -0,0 +1,323 @@
+use std::f32::consts::{PI, FRAC_PI_2};
+
+use crate::color::{Color, BLACK};
+use crate::environment::Environment;
+use crate::random::random_double;
+use crate::spectrum::{xyz_to_linear_srgb, LAMBDA_RED, LAMBDA_GREEN, LAMBDA_BLUE};
+use crate::structures::onb::Onb;
+use crate::structures::vec3::{Vec3, random_unit_vector};
+use crate::utils;
+
+// converts luminance in cd/m^2 to radiance in render units, so a white
+// diffuse surface under a high sun comes out around 1
+const LUMINANCE_SCALE: f32 = 1.0 / 30_000.0;
+
+// luminance of the sun disk above the atmosphere
+const SUN_LUMINANCE: f32 = 1.88e9;
+
+// as seen from earth, in degrees
+pub const SUN_ANGULAR_DIAMETER: f32 = 0.53;
+
+// the sun as a small disk of constant radiance, dimmed and reddened by the
+// atmosphere it shines through
+pub struct Sun {
+    // towards the sun
+    pub direction: Vec3,
+    pub radiance: Color,
+    // 1 - cos of the angular radius
+    cone: f32,
+}
+
+impl Sun {
+    pub fn new(direction: Vec3, turbidity: f32) -> Self {
+        let direction = direction.normalize();
+        let radiance = (SUN_LUMINANCE * LUMINANCE_SCALE) * transmittance(direction.y, turbidity);
+
+        Sun { direction, radiance, cone: 0.0 }.with_angular_diameter(SUN_ANGULAR_DIAMETER)
+    }
+
+    pub fn with_angular_diameter(self, degrees: f32) -> Self {
+        let half_radius = (0.25 * degrees).to_radians().sin();
+
+        Sun { cone: 2.0 * half_radius * half_radius, ..self }
+    }
+
+    pub fn solid_angle(&self) -> f32 {
+        2.0 * PI * self.cone
+    }
+
+    fn contains(&self, direction: &Vec3) -> bool {
+        // |a - b|^2 = 2 - 2 cos, which keeps its precision for tiny angles
+        (direction.normalize() - self.direction).length_squared() <= 2.0 * self.cone
+    }
+
+    fn sample(&self) -> Vec3 {
+        let one_minus_cos = random_double() * self.cone;
+        let cos_theta = 1.0 - one_minus_cos;
+        let sin_theta = (one_minus_cos * (2.0 - one_minus_cos)).max(0.0).sqrt();
+        let phi = 2.0 * PI * random_double();
+
+        Onb::from_w(&self.direction).local(&Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta))
+    }
+}
+
+// Rayleigh and aerosol extinction along the path to the sun for the red,
+// green and blue wavelengths, see the appendix of Preetham et al. 1999
+fn transmittance(cos_zenith: f32, turbidity: f32) -> Color {
+    if cos_zenith <= 0.0 {
+        return BLACK;
+    }
+
+    let zenith = cos_zenith.acos().to_degrees();
+    let air_mass = 1.0 / (cos_zenith + 0.15 * (93.885 - zenith).powf(-1.253));
+    let beta = 0.04608 * turbidity - 0.04586;
+
+    let extinction = |lambda: f32| {
+        let lambda = lambda / 1000.0;
+        let rayleigh = 0.008735 * lambda.powf(-4.08);
+        let aerosol = beta * lambda.powf(-1.3);
+
+        (-(rayleigh + aerosol) * air_mass).exp()
+    };
+
+    Color::new(extinction(LAMBDA_RED), extinction(LAMBDA_GREEN), extinction(LAMBDA_BLUE))
+}
+
+// clear sky model of Preetham, Shirley and Smits 1999 with +y up. Below the
+// horizon is a diffuse ground lit by the sky and sun. The model is only
+// valid for the sun above the horizon and turbidities from 2 to 10.
+pub struct PreethamSky {
+    sun_direction: Vec3,
+    // the (Y, x, y) color at the zenith and the perez coefficients for each
+    zenith: [f32; 3],
+    perez: [[f32; 5]; 3],
+    // cosine weighted and plain integrals of the sky over the upper hemisphere
+    sky_irradiance: Color,
+    sky_power: f32,
+    pub sun: Option<Sun>,
+    pub ground_albedo: Color,
+    pub intensity: f32,
+    pub group: Option<String>,
+}
+
+impl PreethamSky {
+    // a sky with the matching sun and a gray ground
+    pub fn new(sun_direction: Vec3, turbidity: f32) -> Self {
+        let sun_direction = sun_direction.normalize();
+        let t = utils::clamp(turbidity, 2.0, 10.0);
+        let theta_s = sun_direction.y.max(0.0).acos().min(FRAC_PI_2 - 0.01);
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let (theta2, theta3) = (theta_s * theta_s, theta_s * theta_s * theta_s);
+        let zenith_x = t * t * (0.00166 * theta3 - 0.00375 * theta2 + 0.00209 * theta_s)
+            + t * (-0.02903 * theta3 + 0.06377 * theta2 - 0.03202 * theta_s + 0.00394)
+            + (0.11693 * theta3 - 0.21196 * theta2 + 0.06052 * theta_s + 0.25886);
+        let zenith_y = t * t * (0.00275 * theta3 - 0.00610 * theta2 + 0.00317 * theta_s)
+            + t * (-0.04214 * theta3 + 0.08970 * theta2 - 0.04153 * theta_s + 0.00516)
+            + (0.15346 * theta3 - 0.26756 * theta2 + 0.06670 * theta_s + 0.26688);
+
+        let perez = [
+            [0.1787 * t - 1.4630, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251, 0.1206 * t - 2.5771, -0.0670 * t + 0.3703],
+            [-0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125, -0.0641 * t - 0.8989, -0.0033 * t + 0.0452],
+            [-0.0167 * t - 0.2608, -0.0950 * t + 0.0092, -0.0079 * t + 0.2102, -0.0441 * t - 1.6537, -0.0109 * t + 0.0529],
+        ];
+
+        // the zenith values are normalized by the perez function at the zenith
+        let mut zenith = [zenith_luminance.max(0.0) * 1000.0, zenith_x, zenith_y];
+        for (value, coefficients) in zenith.iter_mut().zip(perez.iter()) {
+            *value /= perez_function(coefficients, 1.0, theta_s.cos());
+        }
+
+        let mut sky = PreethamSky {
+            sun_direction,
+            zenith,
+            perez,
+            sky_irradiance: BLACK,
+            sky_power: 0.0,
+            sun: Some(Sun::new(sun_direction, t)),
+            ground_albedo: Color::new(0.3, 0.3, 0.3),
+            intensity: 1.0,
+            group: None,
+        };
+
+        // midpoint rule over the upper hemisphere
+        let (rows, columns) = (32, 64);
+        let solid_angle = |row: usize| (PI / 2.0 / rows as f32) * (2.0 * PI / columns as f32) * ((row as f32 + 0.5) / rows as f32 * FRAC_PI_2).sin();
+        for row in 0..rows {
+            let theta = (row as f32 + 0.5) / rows as f32 * FRAC_PI_2;
+            for column in 0..columns {
+                let phi = (column as f32 + 0.5) / columns as f32 * 2.0 * PI;
+                let direction = Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+                let radiance = sky.sky_radiance(&direction);
+
+                sky.sky_irradiance = sky.sky_irradiance + (theta.cos() * solid_angle(row)) * radiance;
+                sky.sky_power += radiance.luminance() * solid_angle(row);
+            }
+        }
+
+        sky
+    }
+
+    pub fn with_sun(self, sun: Option<Sun>) -> Self {
+        PreethamSky { sun, ..self }
+    }
+
+    pub fn with_ground_albedo(self, ground_albedo: Color) -> Self {
+        PreethamSky { ground_albedo, ..self }
+    }
+
+    pub fn with_intensity(self, intensity: f32) -> Self {
+        PreethamSky { intensity, ..self }
+    }
+
+    pub fn with_group(self, group: &str) -> Self {
+        PreethamSky { group: Some(group.to_string()), ..self }
+    }
+
+    fn sky_radiance(&self, direction: &Vec3) -> Color {
+        let direction = direction.normalize();
+        let cos_theta = direction.y.max(0.001);
+        let cos_gamma = utils::clamp(direction.dot(&self.sun_direction), -1.0, 1.0);
+
+        let luminance = self.zenith[0] * perez_function(&self.perez[0], cos_theta, cos_gamma);
+        let x = self.zenith[1] * perez_function(&self.perez[1], cos_theta, cos_gamma);
+        let y = self.zenith[2] * perez_function(&self.perez[2], cos_theta, cos_gamma);
+
+        if y <= 0.0 {
+            return BLACK;
+        }
+
+        let color = xyz_to_linear_srgb(x / y * luminance, luminance, (1.0 - x - y) / y * luminance);
+
+        LUMINANCE_SCALE * Color::new(color.r.max(0.0), color.g.max(0.0), color.b.max(0.0))
+    }
+
+    // the ground reflects the light falling on a horizontal plane
+    fn ground_radiance(&self) -> Color {
+        let sun_irradiance = match &self.sun {
+            Some(sun) => (sun.solid_angle() * sun.direction.y.max(0.0)) * sun.radiance,
+            None => BLACK,
+        };
+
+        (1.0 / PI) * (self.ground_albedo * (self.sky_irradiance + sun_irradiance))
+    }
+
+    // probability of sampling the sun rather than the rest of the sky
+    fn sun_probability(&self) -> f32 {
+        match &self.sun {
+            Some(sun) => {
+                let sun_power = sun.radiance.luminance() * sun.solid_angle();
+                let rest_power = self.sky_power + 2.0 * PI * self.ground_radiance().luminance();
+
+                if sun_power > 0.0 { utils::clamp(sun_power / (sun_power + rest_power), 0.1, 0.9) } else { 0.0 }
+            },
+            None => 0.0,
+        }
+    }
+}
+
+fn perez_function(coefficients: &[f32; 5], cos_theta: f32, cos_gamma: f32) -> f32 {
+    let [a, b, c, d, e] = *coefficients;
+    let gamma = cos_gamma.acos();
+
+    (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+}
+
+impl Environment for PreethamSky {
+    fn radiance(&self, direction: &Vec3) -> Color {
+        let radiance = if direction.y < 0.0 {
+            self.ground_radiance()
+        } else {
+            match &self.sun {
+                Some(sun) if sun.contains(direction) => self.sky_radiance(direction) + sun.radiance,
+                _ => self.sky_radiance(direction),
+            }
+        };
+
+        self.intensity * radiance
+    }
+
+    // the sun is sampled within its cone and the rest uniformly
+    fn sample(&self) -> Option<(Vec3, Color, f32)> {
+        let direction = match &self.sun {
+            Some(sun) if random_double() < self.sun_probability() => sun.sample(),
+            _ => random_unit_vector(),
+        };
+
+        Some((direction, self.radiance(&direction), self.pdf(&direction)))
+    }
+
+    fn pdf(&self, direction: &Vec3) -> f32 {
+        let sun_probability = self.sun_probability();
+        let sun_pdf = match &self.sun {
+            Some(sun) if sun.contains(direction) => sun_probability / sun.solid_angle(),
+            _ => 0.0,
+        };
+
+        sun_pdf + (1.0 - sun_probability) / (4.0 * PI)
+    }
+
+    fn group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+}
+
+// direction towards the sun with +x east, +y up and -z north, for a place at
+// `latitude` and `longitude` in degrees (north and east positive) on a date
+// at a time in hours UTC. Uses the approximations of the NOAA solar
+// calculator, accurate to a fraction of a degree.
+pub fn sun_direction(latitude: f32, longitude: f32, month: u32, day: u32, utc_hours: f32) -> Vec3 {
+    const DAYS_BEFORE_MONTH: [u32; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+    let day_of_year = DAYS_BEFORE_MONTH[(utils::clamp(month as f32, 1.0, 12.0) as usize) - 1] + day;
+
+    // fractional year in radians
+    let gamma = 2.0 * PI / 365.0 * (day_of_year as f32 - 1.0 + (utc_hours - 12.0) / 24.0);
+    let declination = 0.006918 - 0.399912 * gamma.cos() + 0.070257 * gamma.sin()
+        - 0.006758 * (2.0 * gamma).cos() + 0.000907 * (2.0 * gamma).sin()
+        - 0.002697 * (3.0 * gamma).cos() + 0.00148 * (3.0 * gamma).sin();
+    // in minutes
+    let equation_of_time = 229.18 * (0.000075 + 0.001868 * gamma.cos() - 0.032077 * gamma.sin()
+        - 0.014615 * (2.0 * gamma).cos() - 0.040849 * (2.0 * gamma).sin());
+
+    let solar_time = utc_hours + longitude / 15.0 + equation_of_time / 60.0;
+    let hour_angle = (15.0 * (solar_time - 12.0)).to_radians();
+    let latitude = latitude.to_radians();
+
+    let east = -declination.cos() * hour_angle.sin();
+    let north = declination.sin() * latitude.cos() - declination.cos() * hour_angle.cos() * latitude.sin();
+    let up = declination.sin() * latitude.sin() + declination.cos() * hour_angle.cos() * latitude.cos();
+
+    Vec3::new(east, up, -north).normalize()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn noon_at_the_equinox() {
+        // Quito is almost on the equator, solar noon there is around 17:00 UTC
+        let direction = sun_direction(0.0, -78.5, 3, 20, 17.0 + 14.0 / 60.0);
+        assert!(direction.y > 0.99);
+
+        // north of the tropics the noon sun is in the south
+        let direction = sun_direction(48.1, 11.6, 6, 21, 11.2);
+        assert!(direction.z > 0.0 && (direction.y.asin().to_degrees() - 65.3).abs() < 1.0);
+    }
+
+    #[test]
+    fn sun_is_sampled_within_its_disk() {
+        let sky = PreethamSky::new(Vec3::new(0.3, 0.8, -0.2), 3.0);
+        let sun = sky.sun.as_ref().unwrap();
+
+        for _ in 0..16 {
+            assert!(sun.contains(&sun.sample()));
+        }
+
+        assert!(sky.radiance(&sun.direction).luminance() > 1000.0 * sky.radiance(&Vec3::new(0.0, 1.0, 0.0)).luminance());
+        assert!(sky.pdf(&sun.direction) > 1000.0);
+        assert!(sun.radiance.b < sun.radiance.r && sun.radiance.r < SUN_LUMINANCE * LUMINANCE_SCALE);
+    }
+}
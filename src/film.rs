@@ -0,0 +1,388 @@
+use std::f32::consts::PI;
+
+use crate::aov::{AovImages, AovSample, AOVS};
+use crate::light_groups::{GroupedRadiance, LightGroupImages};
+
+// Reconstruction filters weighting a sample by its offset from the center of
+// a pixel, in pixels. They are separable and reach `radius` pixels in x and
+// y. Mitchell-Netravali and Lanczos have negative lobes that sharpen the
+// image but can ring around very bright edges.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    // every sample only counts for the pixel it is in with a radius of 0.5
+    Box { radius: f32 },
+    Tent { radius: f32 },
+    // larger alpha is narrower, the curve is shifted to reach zero at the radius
+    Gaussian { radius: f32, alpha: f32 },
+    // B = C = 1/3 is the recommended trade-off between blurring and ringing
+    MitchellNetravali { radius: f32, b: f32, c: f32 },
+    // sinc windowed by a sinc stretched by tau
+    Lanczos { radius: f32, tau: f32 },
+}
+
+impl Filter {
+    pub fn radius(&self) -> f32 {
+        match *self {
+            Filter::Box { radius } => radius,
+            Filter::Tent { radius } => radius,
+            Filter::Gaussian { radius, .. } => radius,
+            Filter::MitchellNetravali { radius, .. } => radius,
+            Filter::Lanczos { radius, .. } => radius,
+        }
+    }
+
+    pub fn evaluate(&self, x: f32, y: f32) -> f32 {
+        self.evaluate_1d(x) * self.evaluate_1d(y)
+    }
+
+    fn evaluate_1d(&self, x: f32) -> f32 {
+        let x = x.abs();
+        if x >= self.radius() {
+            return 0.0;
+        }
+
+        match *self {
+            Filter::Box { .. } => 1.0,
+            Filter::Tent { radius } => radius - x,
+            Filter::Gaussian { radius, alpha } => (-alpha * x * x).exp() - (-alpha * radius * radius).exp(),
+            Filter::MitchellNetravali { radius, b, c } => mitchell(2.0 * x / radius, b, c),
+            Filter::Lanczos { tau, .. } => sinc(x) * sinc(x / tau),
+        }
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter::Box { radius: 0.5 }
+    }
+}
+
+// the cubic of Mitchell and Netravali 1988 for 0 <= x < 2
+fn mitchell(x: f32, b: f32, c: f32) -> f32 {
+    let (x2, x3) = (x * x, x * x * x);
+
+    let value = if x < 1.0 {
+        (12.0 - 9.0 * b - 6.0 * c) * x3 + (-18.0 + 12.0 * b + 6.0 * c) * x2 + (6.0 - 2.0 * b)
+    } else {
+        (-b - 6.0 * c) * x3 + (6.0 * b + 30.0 * c) * x2 + (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)
+    };
+
+    value / 6.0
+}
+
+fn sinc(x: f32) -> f32 {
+    if x < 1e-5 { 1.0 } else { (PI * x).sin() / (PI * x) }
+}
+
+// samples taken before the estimate of a pixel is trusted to reject others
+const OUTLIER_WARMUP: u32 = 8;
+// estimates below this count as this, so that a single bright sample in an
+// otherwise black pixel isn't rejected for being infinitely brighter
+const OUTLIER_MIN_ESTIMATE: f32 = 0.01;
+
+// Accumulates the radiance of the samples of a render per light group.
+// Positions are in pixels from the top left corner of the image, pixel
+// centers are at half pixels. Camera samples are spread over the pixels in
+// the filter radius and normalized by the filter weights, splats, like the
+// contributions of paths traced from the lights, are only added to the pixel
+// they land in and scaled when the images are read. The renders of this crate
+// only take camera samples, splats are for integrators built on the film.
+// The AOVs of the camera samples, when enabled, hold light filtered like the
+// radiance and surface features filtered without negative lobes.
+pub struct Film {
+    pub width: u32,
+    pub height: u32,
+    pub filter: Filter,
+    // when set, a sample of a pixel is left out if its luminance is more than
+    // this many times the mean of the samples before it. Biased, but removes
+    // fireflies that would take far too many samples to average out.
+    pub outlier_rejection: Option<f32>,
+    samples: LightGroupImages,
+    weights: Vec<f32>,
+    splats: LightGroupImages,
+    aovs: Option<AovImages>,
+    aov_weights: Vec<f32>,
+    feature_weights: Vec<f32>,
+}
+
+impl Film {
+    pub fn new(width: u32, height: u32, filter: Filter) -> Self {
+        Film {
+            width,
+            height,
+            filter,
+            outlier_rejection: None,
+            samples: LightGroupImages::new(width, height),
+            weights: vec![0.0; (width * height) as usize],
+            splats: LightGroupImages::new(width, height),
+            aovs: None,
+            aov_weights: Vec::new(),
+            feature_weights: Vec::new(),
+        }
+    }
+
+    pub fn with_outlier_rejection(self, ratio: f32) -> Self {
+        Film { outlier_rejection: Some(ratio), ..self }
+    }
+
+    pub fn with_aovs(self) -> Self {
+        Film {
+            aovs: Some(AovImages::new(self.width, self.height)),
+            aov_weights: vec![0.0; (self.width * self.height) as usize],
+            feature_weights: vec![0.0; (self.width * self.height) as usize],
+            ..self
+        }
+    }
+
+    // the samples of one pixel in the order they were taken with the
+    // luminance they are compared by, returns how many were rejected as
+    // outliers. Emitters seen directly are bright without being fireflies,
+    // so their light is usually left out of the luminance.
+    pub fn add_pixel_samples<'a, 'b: 'a, I>(&mut self, samples: I) -> u32
+    where I: IntoIterator<Item = (f32, f32, &'a GroupedRadiance<'b>, f32)> {
+        let mut rejected = 0;
+        let (mut sum, mut count) = (0.0, 0);
+
+        for (x, y, radiance, luminance) in samples {
+            if let Some(ratio) = self.outlier_rejection {
+                let estimate = if count > 0 { sum / count as f32 } else { 0.0 };
+
+                if count >= OUTLIER_WARMUP && luminance > ratio * estimate.max(OUTLIER_MIN_ESTIMATE) {
+                    rejected += 1;
+                    continue;
+                }
+            }
+
+            sum += luminance;
+            count += 1;
+            self.add_sample(x, y, radiance);
+        }
+
+        rejected
+    }
+
+    pub fn add_sample(&mut self, x: f32, y: f32, radiance: &GroupedRadiance) {
+        let (samples, weights) = (&mut self.samples, &mut self.weights);
+
+        for_each_weight(self.filter, self.width, self.height, x, y, |index, weight| {
+            samples.add(index, radiance, weight);
+            weights[index] += weight;
+        });
+    }
+
+    // the outputs of a camera sample, ignored unless the film has AOVs.
+    // Outliers are only rejected from the radiance. Negative lobes would
+    // push depths, normals and the like past the values of their samples,
+    // so features only take the positive filter weights.
+    pub fn add_aov_sample(&mut self, x: f32, y: f32, sample: &AovSample) {
+        let (aovs, weights, feature_weights) = match &mut self.aovs {
+            Some(aovs) => (aovs, &mut self.aov_weights, &mut self.feature_weights),
+            None => return,
+        };
+
+        for_each_weight(self.filter, self.width, self.height, x, y, |index, weight| {
+            aovs.add(index, sample, weight, weight.max(0.0));
+            weights[index] += weight;
+            feature_weights[index] += weight.max(0.0);
+        });
+    }
+
+    pub fn add_splat(&mut self, x: f32, y: f32, radiance: &GroupedRadiance) {
+        if x < 0.0 || y < 0.0 || x >= self.width as f32 || y >= self.height as f32 {
+            return;
+        }
+
+        let index = (y as u32 * self.width + x as u32) as usize;
+        self.splats.add(index, radiance, 1.0);
+    }
+
+    // the filtered samples plus the splats times `splat_scale`, usually one
+    // over the number of samples per pixel
+    pub fn images(&self, splat_scale: f32) -> LightGroupImages {
+        let mut images = LightGroupImages::new(self.width, self.height);
+
+        for (index, weight) in self.weights.iter().enumerate() {
+            // pixels whose negative lobes cancel out are left black
+            let scale = if weight.abs() > 1e-6 { 1.0 / weight } else { 0.0 };
+            images.add(index, &self.radiance(&self.samples, index), scale);
+            images.add(index, &self.radiance(&self.splats, index), splat_scale);
+        }
+
+        images
+    }
+
+    // the filtered AOVs without ids or beauty, None unless enabled
+    pub fn aovs(&self) -> Option<AovImages> {
+        let samples = self.aovs.as_ref()?;
+        let mut aovs = AovImages::new(self.width, self.height);
+
+        let scale = |weight: f32| if weight.abs() > 1e-6 { 1.0 / weight } else { 0.0 };
+
+        for (index, (weight, feature_weight)) in self.aov_weights.iter().zip(self.feature_weights.iter()).enumerate() {
+            for ((layer, sample_layer), aov) in aovs.layers.iter_mut().zip(samples.layers.iter()).zip(AOVS.iter()) {
+                let scale = if aov.is_radiance() { scale(*weight) } else { scale(*feature_weight) };
+                layer[index] = scale * sample_layer[index];
+            }
+        }
+
+        Some(aovs)
+    }
+
+    fn radiance<'a>(&self, images: &'a LightGroupImages, index: usize) -> GroupedRadiance<'a> {
+        GroupedRadiance {
+            ungrouped: images.ungrouped[index],
+            groups: images.groups.iter().map(|(name, pixels)| (name.as_str(), pixels[index])).collect(),
+        }
+    }
+}
+
+// calls `f` with the index and filter weight of every pixel whose center is
+// within the filter radius of (x, y)
+fn for_each_weight(filter: Filter, width: u32, height: u32, x: f32, y: f32, mut f: impl FnMut(usize, f32)) {
+    let radius = filter.radius();
+
+    let x0 = ((x - 0.5 - radius).ceil() as i64).max(0);
+    let x1 = ((x - 0.5 + radius).floor() as i64).min(width as i64 - 1);
+    let y0 = ((y - 0.5 - radius).ceil() as i64).max(0);
+    let y1 = ((y - 0.5 + radius).floor() as i64).min(height as i64 - 1);
+
+    for pixel_y in y0..=y1 {
+        for pixel_x in x0..=x1 {
+            let weight = filter.evaluate(x - (pixel_x as f32 + 0.5), y - (pixel_y as f32 + 0.5));
+            if weight == 0.0 {
+                continue;
+            }
+
+            f((pixel_y * width as i64 + pixel_x) as usize, weight);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::aov::Aov;
+    use crate::color::Color;
+
+    fn radiance(value: f32) -> GroupedRadiance<'static> {
+        let mut radiance = GroupedRadiance::new();
+        radiance.add(Some("key"), Color::new(value, value, value));
+        radiance
+    }
+
+    #[test]
+    fn filters_vanish_at_the_radius() {
+        let filters = [
+            Filter::default(),
+            Filter::Tent { radius: 1.0 },
+            Filter::Gaussian { radius: 1.5, alpha: 2.0 },
+            Filter::MitchellNetravali { radius: 2.0, b: 1.0 / 3.0, c: 1.0 / 3.0 },
+            Filter::Lanczos { radius: 3.0, tau: 3.0 },
+        ];
+
+        for filter in filters.iter() {
+            assert!(filter.evaluate(0.0, 0.0) > 0.0, "{:?}", filter);
+            assert!(filter.evaluate(0.0, 0.0) >= filter.evaluate(0.3, 0.1), "{:?}", filter);
+            assert_eq!(filter.evaluate(filter.radius(), 0.0), 0.0);
+            assert_eq!(filter.evaluate(0.0, filter.radius() + 0.5), 0.0);
+
+            // continuous at the radius
+            assert!(filter.evaluate(filter.radius() - 0.001, 0.0).abs() < 0.01 || *filter == Filter::default(), "{:?}", filter);
+        }
+    }
+
+    #[test]
+    fn box_filter_averages_the_samples_of_a_pixel() {
+        let mut film = Film::new(3, 2, Filter::default());
+        film.add_sample(1.2, 0.9, &radiance(1.0));
+        film.add_sample(1.8, 0.1, &radiance(3.0));
+        film.add_splat(0.5, 1.5, &radiance(4.0));
+
+        let images = film.images(0.5);
+        let beauty = images.beauty();
+
+        assert_eq!(beauty[1].r, 2.0);
+        assert_eq!(beauty[3].g, 2.0);
+        assert_eq!(beauty[0].b, 0.0);
+        assert_eq!(images.groups[0].1[1].r, 2.0);
+    }
+
+    #[test]
+    fn outliers_are_rejected_after_the_warmup() {
+        let samples: Vec<GroupedRadiance> = (0..32).map(|i| radiance(if i == 20 { 1000.0 } else { 1.0 })).collect();
+        let with_luminance = |radiance| (0.5, 0.5, radiance, GroupedRadiance::total(radiance).luminance());
+
+        let mut film = Film::new(1, 1, Filter::default());
+        assert_eq!(film.add_pixel_samples(samples.iter().map(with_luminance)), 0);
+        assert!(film.images(1.0).beauty()[0].r > 10.0);
+
+        let mut film = Film::new(1, 1, Filter::default()).with_outlier_rejection(10.0);
+        assert_eq!(film.add_pixel_samples(samples.iter().map(with_luminance)), 1);
+        assert_eq!(film.images(1.0).beauty()[0].r, 1.0);
+
+        // too early to tell
+        let mut film = Film::new(1, 1, Filter::default()).with_outlier_rejection(10.0);
+        assert_eq!(film.add_pixel_samples(samples[16..].iter().map(with_luminance)), 0);
+
+        // judged by the luminance given, not the radiance
+        let mut film = Film::new(1, 1, Filter::default()).with_outlier_rejection(10.0);
+        assert_eq!(film.add_pixel_samples(samples.iter().map(|radiance| (0.5, 0.5, radiance, 1.0))), 0);
+    }
+
+    #[test]
+    fn wide_filters_spread_into_neighbours() {
+        let mut film = Film::new(3, 1, Filter::Tent { radius: 2.5 });
+        film.add_sample(0.5, 0.5, &radiance(1.0));
+        film.add_sample(2.5, 0.5, &radiance(3.0));
+
+        let beauty = film.images(1.0).beauty();
+
+        // the center pixel gets both samples with the same weight
+        assert!((beauty[1].r - 2.0).abs() < 1e-6);
+        assert!(beauty[0].r > 1.0 && beauty[0].r < 2.0);
+    }
+
+    #[test]
+    fn aovs_are_filtered_like_the_radiance() {
+        let aov = |value: f32| {
+            let mut sample = AovSample::new();
+            sample.set(Aov::Depth, Color::new(value, value, value));
+            sample
+        };
+
+        let mut film = Film::new(3, 1, Filter::Tent { radius: 2.5 }).with_aovs();
+        for (x, value) in [(0.5, 1.0), (2.5, 3.0)].iter() {
+            film.add_sample(*x, 0.5, &radiance(*value));
+            film.add_aov_sample(*x, 0.5, &aov(*value));
+        }
+
+        let (beauty, aovs) = (film.images(1.0).beauty(), film.aovs().unwrap());
+        for (pixel, depth) in beauty.iter().zip(aovs.get(Aov::Depth)) {
+            assert!((pixel.r - depth.r).abs() < 1e-6);
+        }
+
+        assert!(Film::new(3, 1, Filter::default()).aovs().is_none());
+    }
+
+    #[test]
+    fn features_ignore_negative_lobes() {
+        let aov = |value: f32| {
+            let mut sample = AovSample::new();
+            sample.set(Aov::Depth, Color::new(value, value, value));
+            sample.set(Aov::DirectDiffuse, Color::new(value, value, value));
+            sample
+        };
+
+        let mut film = Film::new(3, 1, Filter::MitchellNetravali { radius: 2.0, b: 1.0 / 3.0, c: 1.0 / 3.0 }).with_aovs();
+        for (x, value) in [(0.5, 1.0), (1.9, 3.0)].iter() {
+            film.add_aov_sample(*x, 0.5, &aov(*value));
+        }
+
+        // the light overshoots next to the edge, the depth stays between
+        // the depths of the samples
+        let aovs = film.aovs().unwrap();
+        assert!(aovs.get(Aov::DirectDiffuse)[0].r < 1.0);
+        assert!(aovs.get(Aov::Depth).iter().all(|depth| depth.r >= 1.0 - 1e-6 && depth.r <= 3.0 + 1e-6));
+    }
+}
@@ -3,11 +3,29 @@ use crate::materials::HitRecord;
 use crate::aabb::{AABB, surrounding_box};
 use crate::structures::vec3::Vec3;
 
-pub trait Hitable: Sync {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord>;
+pub trait Hitable: Sync + Send {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>>;
     fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB>;
 }
 
+// a hitable whose points can be looked up by the (u, v) of its hit records,
+// letting lights pick points on it
+pub trait Surface: Hitable {
+    // the point at (u, v), its outward normal and the area around it per
+    // unit of u and v
+    fn point_at(&self, u: f32, v: f32) -> (Vec3, Vec3, f32);
+}
+
+impl<T: Hitable + ?Sized> Hitable for Box<T> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        (**self).hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+        (**self).bounding_box(t0, t1)
+    }
+}
+
 #[derive(Default)]
 pub struct HitableList {
     pub list: Vec<Box<dyn Hitable>>
@@ -20,7 +38,7 @@ impl HitableList {
 }
 
 impl Hitable for HitableList {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let mut closest_so_far = t_max;
         let mut closest_hit_record: Option<HitRecord> = None;
 
@@ -60,4 +78,45 @@ impl Hitable for HitableList {
 
         Some(output_box)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::sync::Arc;
+    use crate::bvh::BVH;
+    use crate::color::{Color, BLACK, WHITE};
+    use crate::materials::{Material, Lambertian};
+    use crate::materials::alpha_mask::AlphaMask;
+    use crate::objects::rect::XyRect;
+    use crate::texture::SolidColor;
+    use crate::texture::procedural::UvCheckerTexture;
+
+    // a quad at z = 1 cut out where x < 0, in front of a whole one at z = 0
+    fn cutout_in_front() -> Vec<Box<dyn Hitable>> {
+        let material: Arc<dyn Material> = Arc::new(Lambertian::new(SolidColor::new(Color::new(0.5, 0.5, 0.5))));
+        let opacity = UvCheckerTexture::new(SolidColor::new(WHITE), SolidColor::new(BLACK), 2.0, 1.0);
+        let cutout = Arc::new(AlphaMask::new(material.clone(), opacity));
+
+        vec![
+            Box::new(XyRect::new(-1.0, 1.0, -1.0, 1.0, 1.0, cutout)),
+            Box::new(XyRect::new(-1.0, 1.0, -1.0, 1.0, 0.0, material)),
+        ]
+    }
+
+    #[test]
+    fn rays_pass_through_cutouts() {
+        let list = HitableList { list: cutout_in_front() };
+        let bvh = BVH::new(cutout_in_front(), 0.0, 0.0);
+
+        for world in [&list as &dyn Hitable, &bvh].iter() {
+            let t = |x: f32| {
+                let ray = Ray::new(Vec3::new(x, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+                world.hit(&ray, 0.001, f32::INFINITY).map(|hit_record| hit_record.t)
+            };
+
+            assert_eq!(t(0.5), Some(4.0));
+            assert_eq!(t(-0.5), Some(5.0));
+        }
+    }
+}
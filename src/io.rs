@@ -1,3 +1,9 @@
 pub mod ppm;
 pub mod obj;
-pub mod png;
\ No newline at end of file
+pub mod png;
+pub mod hdr;
+pub mod exr;
+pub mod image_file;
+pub mod ies;
+pub mod cube;
+pub mod scene_file;
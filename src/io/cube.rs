@@ -0,0 +1,115 @@
+use std::fs;
+
+use crate::color::Color;
+use crate::utils;
+
+// a 3D color lookup table read from an Adobe/Resolve .cube file. 1D tables
+// are not supported.
+pub struct CubeLut {
+    pub title: Option<String>,
+    size: usize,
+    domain_min: Color,
+    domain_max: Color,
+    // red changes fastest, then green, then blue
+    table: Vec<Color>,
+}
+
+impl CubeLut {
+    pub fn load(path: &str) -> Self {
+        let text = fs::read_to_string(path).expect("LUT file could not be read");
+
+        CubeLut::parse(&text).unwrap_or_else(|error| panic!("Invalid .cube file {}: {}", path, error))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut title = None;
+        let mut size = 0;
+        let mut domain_min = Color::new(0.0, 0.0, 0.0);
+        let mut domain_max = Color::new(1.0, 1.0, 1.0);
+        let mut table = Vec::new();
+
+        let triple = |values: &[&str]| -> Result<Color, String> {
+            let numbers = values.iter()
+                .map(|token| token.parse::<f32>().map_err(|_| format!("invalid number '{}'", token)))
+                .collect::<Result<Vec<f32>, String>>()?;
+
+            match numbers[..] {
+                [r, g, b] => Ok(Color::new(r, g, b)),
+                _ => Err(format!("expected three values, found {}", numbers.len())),
+            }
+        };
+
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+
+            match tokens[0] {
+                "TITLE" => title = Some(line["TITLE".len()..].trim().trim_matches('"').to_string()),
+                "LUT_3D_SIZE" => size = tokens.get(1).and_then(|size| size.parse().ok()).ok_or("invalid LUT_3D_SIZE")?,
+                "LUT_1D_SIZE" => return Err("1D tables are not supported".to_string()),
+                "DOMAIN_MIN" => domain_min = triple(&tokens[1..])?,
+                "DOMAIN_MAX" => domain_max = triple(&tokens[1..])?,
+                _ => table.push(triple(&tokens)?),
+            }
+        }
+
+        if size < 2 {
+            return Err("missing LUT_3D_SIZE".to_string());
+        }
+        if table.len() != size * size * size {
+            return Err(format!("expected {} entries, found {}", size * size * size, table.len()));
+        }
+
+        Ok(CubeLut { title, size, domain_min, domain_max, table })
+    }
+
+    // trilinear interpolation, values outside of the domain are clamped
+    pub fn lookup(&self, color: Color) -> Color {
+        let last = (self.size - 1) as f32;
+        let coordinate = |value: f32, min: f32, max: f32| utils::clamp((value - min) / (max - min), 0.0, 1.0) * last;
+
+        let r = coordinate(color.r, self.domain_min.r, self.domain_max.r);
+        let g = coordinate(color.g, self.domain_min.g, self.domain_max.g);
+        let b = coordinate(color.b, self.domain_min.b, self.domain_max.b);
+
+        let (r0, g0, b0) = (r.floor() as usize, g.floor() as usize, b.floor() as usize);
+        let (r1, g1, b1) = ((r0 + 1).min(self.size - 1), (g0 + 1).min(self.size - 1), (b0 + 1).min(self.size - 1));
+        let (fr, fg, fb) = (r - r0 as f32, g - g0 as f32, b - b0 as f32);
+
+        let at = |r: usize, g: usize, b: usize| self.table[(b * self.size + g) * self.size + r];
+        let lerp = |t: f32, a: Color, b: Color| (1.0 - t) * a + t * b;
+
+        let plane = |b: usize| lerp(fg, lerp(fr, at(r0, g0, b), at(r1, g0, b)), lerp(fr, at(r0, g1, b), at(r1, g1, b)));
+
+        lerp(fb, plane(b0), plane(b1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn parse_and_interpolate() {
+        // swaps red and blue
+        let mut text = String::from("# comment\nTITLE \"swap\"\nLUT_3D_SIZE 2\n\n");
+        for b in 0..2 {
+            for g in 0..2 {
+                for r in 0..2 {
+                    text.push_str(&format!("{} {} {}\n", b, g, r));
+                }
+            }
+        }
+
+        let lut = CubeLut::parse(&text).unwrap();
+        assert_eq!(lut.title.as_deref(), Some("swap"));
+
+        let color = lut.lookup(Color::new(0.25, 0.5, 0.75));
+        assert!((color.r - 0.75).abs() < 1e-6 && (color.g - 0.5).abs() < 1e-6 && (color.b - 0.25).abs() < 1e-6);
+
+        // clamped to the domain
+        assert!((lut.lookup(Color::new(2.0, 0.0, 0.0)).b - 1.0).abs() < 1e-6);
+
+        assert!(CubeLut::parse("LUT_3D_SIZE 2\n0 0 0\n").is_err());
+    }
+}
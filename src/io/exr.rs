@@ -0,0 +1,410 @@
+use std::convert::TryInto;
+use std::fs;
+
+use miniz_oxide::inflate::decompress_to_vec_zlib;
+
+use crate::color::Color;
+
+// writes named RGB layers with rows from the top to an uncompressed OpenEXR
+// file with 32 bit float channels, a layer named "" is the main RGB image
+pub fn write_exr(path: &str, width: u32, height: u32, layers: &[(&str, &[Color])]) {
+    match fs::write(path, encode(width, height, layers)) {
+        Ok(()) => println!("Image saved to {:?}", path),
+        Err(error) => panic!("Image could not be saved: {:?}", error)
+    }
+}
+
+pub fn encode(width: u32, height: u32, layers: &[(&str, &[Color])]) -> Vec<u8> {
+    assert!(layers.iter().all(|(_, pixels)| pixels.len() == (width * height) as usize));
+
+    // readers expect the channels sorted by name
+    let mut channels: Vec<(String, &[Color], usize)> = layers.iter()
+        .flat_map(|(layer, pixels)| ["R", "G", "B"].iter().enumerate().map(move |(component, name)| {
+            let name = if layer.is_empty() { name.to_string() } else { format!("{}.{}", layer, name) };
+            (name, *pixels, component)
+        }))
+        .collect();
+    channels.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut channel_list = Vec::new();
+    for (name, _, _) in channels.iter() {
+        channel_list.extend_from_slice(name.as_bytes());
+        channel_list.push(0);
+        // 32 bit float, not perceptually linear, reserved, x and y sampling
+        channel_list.extend_from_slice(&2i32.to_le_bytes());
+        channel_list.extend_from_slice(&[0, 0, 0, 0]);
+        channel_list.extend_from_slice(&1i32.to_le_bytes());
+        channel_list.extend_from_slice(&1i32.to_le_bytes());
+    }
+    channel_list.push(0);
+
+    let window: Vec<u8> = [0, 0, width as i32 - 1, height as i32 - 1].iter().flat_map(|v| v.to_le_bytes().to_vec()).collect();
+
+    let mut data = Vec::new();
+    data.extend_from_slice(&20000630i32.to_le_bytes());
+    data.extend_from_slice(&2i32.to_le_bytes());
+
+    attribute(&mut data, "channels", "chlist", &channel_list);
+    attribute(&mut data, "compression", "compression", &[0]);
+    attribute(&mut data, "dataWindow", "box2i", &window);
+    attribute(&mut data, "displayWindow", "box2i", &window);
+    attribute(&mut data, "lineOrder", "lineOrder", &[0]);
+    attribute(&mut data, "pixelAspectRatio", "float", &1.0f32.to_le_bytes());
+    attribute(&mut data, "screenWindowCenter", "v2f", &[0; 8]);
+    attribute(&mut data, "screenWindowWidth", "float", &1.0f32.to_le_bytes());
+    data.push(0);
+
+    // one scanline per chunk, the offset table points at every chunk
+    let line_size = 4 * width as usize * channels.len();
+    let first_chunk = data.len() + 8 * height as usize;
+    for y in 0..height as usize {
+        data.extend_from_slice(&((first_chunk + y * (8 + line_size)) as u64).to_le_bytes());
+    }
+
+    for y in 0..height as usize {
+        data.extend_from_slice(&(y as i32).to_le_bytes());
+        data.extend_from_slice(&(line_size as i32).to_le_bytes());
+
+        for (_, pixels, component) in channels.iter() {
+            for color in &pixels[y * width as usize..(y + 1) * width as usize] {
+                let value = match component {
+                    0 => color.r,
+                    1 => color.g,
+                    _ => color.b,
+                };
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+
+    data
+}
+
+// reads the RGB channels of a scanline OpenEXR file, rows from the top.
+// Files without compression or with ZIP compression of one or 16 lines are
+// supported, with half, float or uint channels. Images with a single Y
+// channel are read as gray.
+pub fn decode(data: &[u8]) -> Result<(u32, u32, Vec<Color>), String> {
+    let mut reader = Reader { data, position: 0 };
+
+    if reader.i32()? != 20000630 {
+        return Err("not an OpenEXR file".to_string());
+    }
+    // tiled, deep and multi-part files set these flags
+    if reader.i32()? & 0x1a00 != 0 {
+        return Err("only single part scanline images are supported".to_string());
+    }
+
+    let mut channels = Vec::new();
+    let mut compression = None;
+    let mut window = None;
+
+    loop {
+        let name = reader.string()?;
+        if name.is_empty() {
+            break;
+        }
+        let _kind = reader.string()?;
+        let size = reader.i32()? as usize;
+        let value = reader.bytes(size)?;
+
+        match name {
+            "channels" => channels = channel_list(value)?,
+            "compression" => compression = value.first().copied(),
+            "dataWindow" if size == 16 => {
+                let mut window_reader = Reader { data: value, position: 0 };
+                window = Some([window_reader.i32()?, window_reader.i32()?, window_reader.i32()?, window_reader.i32()?]);
+            }
+            _ => {}
+        }
+    }
+
+    let [x_min, y_min, x_max, y_max] = window.ok_or("missing data window")?;
+    if x_max < x_min || y_max < y_min {
+        return Err("empty data window".to_string());
+    }
+    let (width, height) = ((x_max - x_min + 1) as usize, (y_max - y_min + 1) as usize);
+
+    let lines_per_chunk = match compression.ok_or("missing compression")? {
+        0 | 2 => 1,
+        3 => 16,
+        other => return Err(format!("unsupported compression {}", other)),
+    };
+
+    // the main layer is made of the channels without a layer prefix
+    let find = |name: &str| channels.iter().position(|(channel, _)| channel == name);
+    let components = match (find("R"), find("G"), find("B"), find("Y")) {
+        (Some(r), Some(g), Some(b), _) => [r, g, b],
+        (_, _, _, Some(y)) => [y, y, y],
+        _ => return Err("no RGB or Y channels".to_string()),
+    };
+
+    // channels follow each other in a line, every one with a sample per pixel
+    let starts: Vec<usize> = channels.iter()
+        .scan(0, |start, (_, kind)| {
+            let channel_start = *start;
+            *start += kind.size() * width;
+            Some(channel_start)
+        })
+        .collect();
+    let line_size: usize = channels.iter().map(|(_, kind)| kind.size() * width).sum();
+    let chunks = height.div_ceil(lines_per_chunk);
+    let offsets = (0..chunks).map(|_| reader.u64()).collect::<Result<Vec<_>, _>>()?;
+
+    let mut pixels = vec![Color::new(0.0, 0.0, 0.0); width * height];
+
+    for offset in offsets {
+        let mut chunk = Reader { data, position: offset as usize };
+        let first_line = chunk.i32()? - y_min;
+        let size = chunk.i32()? as usize;
+        let packed = chunk.bytes(size)?;
+
+        if first_line < 0 || first_line as usize >= height {
+            return Err("scanline outside the data window".to_string());
+        }
+        let lines = lines_per_chunk.min(height - first_line as usize);
+
+        // chunks that wouldn't get any smaller are stored as they are
+        let unpacked;
+        let block = if compression == Some(0) || size == lines * line_size {
+            packed
+        } else {
+            unpacked = unzip(packed)?;
+            &unpacked
+        };
+
+        if block.len() != lines * line_size {
+            return Err("scanline data has the wrong size".to_string());
+        }
+
+        for (line, bytes) in block.chunks_exact(line_size).enumerate() {
+            let row = &mut pixels[(first_line as usize + line) * width..][..width];
+
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let sample = |component: usize| {
+                    let channel = components[component];
+                    let kind = channels[channel].1;
+                    let at = starts[channel] + x * kind.size();
+                    kind.value(&bytes[at..at + kind.size()])
+                };
+
+                *pixel = Color::new(sample(0), sample(1), sample(2));
+            }
+        }
+    }
+
+    Ok((width as u32, height as u32, pixels))
+}
+
+#[derive(Clone, Copy)]
+enum PixelType {
+    Uint,
+    Half,
+    Float,
+}
+
+impl PixelType {
+    fn size(self) -> usize {
+        match self {
+            PixelType::Half => 2,
+            PixelType::Uint | PixelType::Float => 4,
+        }
+    }
+
+    fn value(self, bytes: &[u8]) -> f32 {
+        match self {
+            PixelType::Uint => u32::from_le_bytes(bytes.try_into().unwrap()) as f32,
+            PixelType::Half => half_to_f32(u16::from_le_bytes(bytes.try_into().unwrap())),
+            PixelType::Float => f32::from_le_bytes(bytes.try_into().unwrap()),
+        }
+    }
+}
+
+// names and types of the channels, in the order they are stored
+fn channel_list(value: &[u8]) -> Result<Vec<(String, PixelType)>, String> {
+    let mut reader = Reader { data: value, position: 0 };
+    let mut channels = Vec::new();
+
+    loop {
+        let name = reader.string()?;
+        if name.is_empty() {
+            return Ok(channels);
+        }
+
+        let kind = match reader.i32()? {
+            0 => PixelType::Uint,
+            1 => PixelType::Half,
+            2 => PixelType::Float,
+            other => return Err(format!("unknown pixel type {}", other)),
+        };
+        // linearity, reserved bytes and the sampling rates
+        reader.bytes(4)?;
+        if reader.i32()? != 1 || reader.i32()? != 1 {
+            return Err("subsampled channels are not supported".to_string());
+        }
+
+        channels.push((name.to_string(), kind));
+    }
+}
+
+// ZIP chunks are zlib streams of the bytes split into two interleaved halves,
+// stored as differences to the previous byte
+fn unzip(packed: &[u8]) -> Result<Vec<u8>, String> {
+    let mut predicted = decompress_to_vec_zlib(packed).map_err(|error| format!("invalid ZIP data: {:?}", error))?;
+
+    for i in 1..predicted.len() {
+        predicted[i] = (predicted[i - 1] as i32 + predicted[i] as i32 - 128) as u8;
+    }
+
+    let (first, second) = predicted.split_at(predicted.len().div_ceil(2));
+    let mut bytes = Vec::with_capacity(predicted.len());
+    for i in 0..first.len() {
+        bytes.push(first[i]);
+        if i < second.len() {
+            bytes.push(second[i]);
+        }
+    }
+
+    Ok(bytes)
+}
+
+fn half_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((half >> 10) & 0x1f) as i32;
+    let mantissa = (half & 0x3ff) as f32;
+
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        31 if mantissa == 0.0 => f32::INFINITY,
+        31 => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let bytes = self.data.get(self.position..self.position + count).ok_or("unexpected end of file")?;
+        self.position += count;
+
+        Ok(bytes)
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        Ok(i32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<&'a str, String> {
+        let length = self.data[self.position.min(self.data.len())..].iter().position(|byte| *byte == 0)
+            .ok_or("unterminated string")?;
+        let bytes = self.bytes(length + 1)?;
+
+        std::str::from_utf8(&bytes[..length]).map_err(|_| "invalid attribute name".to_string())
+    }
+}
+
+fn attribute(data: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    data.extend_from_slice(name.as_bytes());
+    data.push(0);
+    data.extend_from_slice(kind.as_bytes());
+    data.push(0);
+    data.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    data.extend_from_slice(value);
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn scanlines_follow_the_header() {
+        let pixels = vec![Color::new(1.0, 2.0, 3.0); 6];
+        let data = encode(3, 2, &[("", &pixels), ("depth", &pixels)]);
+
+        assert_eq!(&data[0..4], &[0x76, 0x2f, 0x31, 0x01]);
+
+        // the offset of the last scanline leads to its y and size
+        let line_size = 4 * 3 * 6;
+        let last = data.len() - (8 + line_size);
+        let offsets = last - (8 + line_size) - 8;
+        assert_eq!(u64::from_le_bytes(data[offsets..offsets + 8].try_into().unwrap()), last as u64);
+        assert_eq!(i32::from_le_bytes(data[last..last + 4].try_into().unwrap()), 1);
+
+        // channels are sorted, B comes first and depth.R is last
+        let first_value = f32::from_le_bytes(data[last + 8..last + 12].try_into().unwrap());
+        let last_value = f32::from_le_bytes(data[data.len() - 4..].try_into().unwrap());
+        assert_eq!((first_value, last_value), (3.0, 1.0));
+    }
+
+    #[test]
+    fn decoding_reads_back_the_main_layer() {
+        let pixels: Vec<Color> = (0..6).map(|i| Color::new(i as f32, 0.5 * i as f32, -1.0)).collect();
+        let depth = vec![Color::new(9.0, 9.0, 9.0); 6];
+
+        let (width, height, decoded) = decode(&encode(3, 2, &[("", &pixels), ("depth", &depth)])).unwrap();
+        assert_eq!((width, height), (3, 2));
+        assert!(decoded.iter().zip(pixels.iter()).all(|(a, b)| a.r == b.r && a.g == b.g && a.b == b.b));
+
+        assert!(decode(&[0x76, 0x2f, 0x31, 0x01, 2, 0]).is_err());
+    }
+
+    #[test]
+    fn decoding_unzips_half_channels() {
+        // 2x3 pixels of R, G and B halves in a single 16 line ZIP chunk,
+        // R = 1, G = 0.5 and B = 2 negated on every other pixel
+        let values = [0x3c00u16, 0x3800, 0x4000];
+
+        let mut line_bytes = Vec::new();
+        for y in 0..3 {
+            for value in values.iter().rev() {
+                for x in 0..2 {
+                    let value = if (x + y) % 2 == 0 { *value } else { *value ^ 0x8000 };
+                    line_bytes.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+        }
+
+        // the reverse of `unzip`
+        let split: Vec<u8> = line_bytes.iter().step_by(2).chain(line_bytes.iter().skip(1).step_by(2)).cloned().collect();
+        let predicted: Vec<u8> = (0..split.len())
+            .map(|i| if i == 0 { split[0] } else { split[i].wrapping_sub(split[i - 1]).wrapping_add(128) })
+            .collect();
+        let packed = miniz_oxide::deflate::compress_to_vec_zlib(&predicted, 6);
+
+        let mut channel_list = Vec::new();
+        for name in ["B", "G", "R"].iter() {
+            channel_list.extend_from_slice(name.as_bytes());
+            channel_list.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0]);
+        }
+        channel_list.push(0);
+        let window: Vec<u8> = [0, 0, 1, 2].iter().flat_map(|v: &i32| v.to_le_bytes().to_vec()).collect();
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&20000630i32.to_le_bytes());
+        data.extend_from_slice(&2i32.to_le_bytes());
+        attribute(&mut data, "channels", "chlist", &channel_list);
+        attribute(&mut data, "compression", "compression", &[3]);
+        attribute(&mut data, "dataWindow", "box2i", &window);
+        data.push(0);
+        data.extend_from_slice(&((data.len() + 8) as u64).to_le_bytes());
+        data.extend_from_slice(&0i32.to_le_bytes());
+        data.extend_from_slice(&(packed.len() as i32).to_le_bytes());
+        data.extend_from_slice(&packed);
+
+        let (width, height, decoded) = decode(&data).unwrap();
+        assert_eq!((width, height), (2, 3));
+        for (i, color) in decoded.iter().enumerate() {
+            let sign = if (i % 2 + i / 2) % 2 == 0 { 1.0 } else { -1.0 };
+            assert_eq!((color.r, color.g, color.b), (sign * 1.0, sign * 0.5, sign * 2.0));
+        }
+    }
+}
@@ -0,0 +1,20 @@
+extern crate image;
+
+use std::fs::File;
+use std::io::BufWriter;
+
+use image::Rgb;
+use image::hdr::HdrEncoder;
+
+use crate::color::Color;
+
+// writes linear floating point colors to a Radiance .hdr file, rows from the top
+pub fn write_hdr(path: &str, width: u32, height: u32, data: &[Color]) {
+    let pixels: Vec<Rgb<f32>> = data.iter().map(|c| Rgb([c.r.max(0.0), c.g.max(0.0), c.b.max(0.0)])).collect();
+    let file = File::create(path).unwrap_or_else(|error| panic!("Image could not be saved: {:?}", error));
+
+    match HdrEncoder::new(BufWriter::new(file)).encode(&pixels, width as usize, height as usize) {
+        Ok(()) => println!("Image saved to {:?}", path),
+        Err(error) => panic!("Image could not be saved: {:?}", error)
+    }
+}
@@ -0,0 +1,225 @@
+use std::fs;
+
+use crate::utils;
+
+// goniometric distribution of a luminaire read from an IES LM-63 file, in
+// candela. Vertical angles start at 0 straight down, horizontal angles go
+// around that axis. Only type C photometry, used by nearly all
+// architectural fixtures, is supported.
+pub struct IesProfile {
+    vertical_angles: Vec<f32>,
+    horizontal_angles: Vec<f32>,
+    // one row of vertical samples per horizontal angle
+    candela: Vec<Vec<f32>>,
+    max_candela: f32,
+}
+
+impl IesProfile {
+    pub fn load(path: &str) -> Self {
+        IesProfile::read(path).unwrap_or_else(|error| panic!("Invalid IES file {}: {}", path, error))
+    }
+
+    pub fn read(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|error| error.to_string())?;
+
+        IesProfile::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        // the header and keyword lines end with the tilt line, everything
+        // after it is whitespace or comma separated numbers
+        let mut lines = text.lines();
+        let tilt = lines.by_ref()
+            .map(str::trim)
+            .find(|line| line.starts_with("TILT="))
+            .ok_or("missing TILT line")?;
+
+        let rest: Vec<&str> = lines.collect();
+        let mut numbers = rest.iter()
+            .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+            .filter(|token| !token.is_empty())
+            .map(|token| token.parse::<f32>().map_err(|_| format!("invalid number '{}'", token)));
+        let mut next = || numbers.next().unwrap_or_else(|| Err("unexpected end of file".to_string()));
+
+        // tilt data describes how the output changes when the lamp is
+        // tilted, which doesn't apply to a fixed light
+        if tilt == "TILT=INCLUDE" {
+            next()?;
+            let pairs = next()? as usize;
+            for _ in 0..2 * pairs {
+                next()?;
+            }
+        }
+
+        let _lamps = next()?;
+        let _lumens_per_lamp = next()?;
+        let multiplier = next()?;
+        let vertical_count = next()? as usize;
+        let horizontal_count = next()? as usize;
+        let photometric_type = next()?;
+        let _units = next()?;
+        let (_width, _length, _height) = (next()?, next()?, next()?);
+        let ballast_factor = next()?;
+        let ballast_lamp_factor = next()?;
+        let _input_watts = next()?;
+
+        if photometric_type != 1.0 {
+            return Err(format!("unsupported photometric type {}", photometric_type));
+        }
+        if vertical_count == 0 || horizontal_count == 0 {
+            return Err("no angles".to_string());
+        }
+
+        let vertical_angles = (0..vertical_count).map(|_| next()).collect::<Result<Vec<f32>, String>>()?;
+        let horizontal_angles = (0..horizontal_count).map(|_| next()).collect::<Result<Vec<f32>, String>>()?;
+        let scale = multiplier * ballast_factor * ballast_lamp_factor;
+
+        let mut candela = Vec::with_capacity(horizontal_count);
+        for _ in 0..horizontal_count {
+            let row = (0..vertical_count).map(|_| next().map(|value| scale * value)).collect::<Result<Vec<f32>, String>>()?;
+            candela.push(row);
+        }
+
+        let ascending = |angles: &[f32]| angles.windows(2).all(|pair| pair[0] < pair[1]);
+        if !ascending(&vertical_angles) || !ascending(&horizontal_angles) {
+            return Err("angles are not in ascending order".to_string());
+        }
+
+        let max_candela = candela.iter().flatten().fold(0.0f32, |max, value| max.max(*value));
+
+        Ok(IesProfile { vertical_angles, horizontal_angles, candela, max_candela })
+    }
+
+    pub fn max_candela(&self) -> f32 {
+        self.max_candela
+    }
+
+    // intensity towards the given angles in degrees, interpolated bilinearly
+    pub fn candela(&self, vertical: f32, horizontal: f32) -> f32 {
+        let horizontal = self.fold_horizontal(horizontal);
+
+        match locate(&self.horizontal_angles, horizontal) {
+            Some((i, t)) if t > 0.0 => {
+                (1.0 - t) * self.vertical_candela(i, vertical) + t * self.vertical_candela(i + 1, vertical)
+            },
+            Some((i, _)) => self.vertical_candela(i, vertical),
+            None => 0.0,
+        }
+    }
+
+    fn vertical_candela(&self, row: usize, vertical: f32) -> f32 {
+        match locate(&self.vertical_angles, vertical) {
+            Some((i, t)) if t > 0.0 => (1.0 - t) * self.candela[row][i] + t * self.candela[row][i + 1],
+            Some((i, _)) => self.candela[row][i],
+            None => 0.0,
+        }
+    }
+
+    // maps an angle in [0, 360) into the range covered by the file using
+    // the symmetry implied by the last horizontal angle
+    fn fold_horizontal(&self, horizontal: f32) -> f32 {
+        let horizontal = horizontal.rem_euclid(360.0);
+        let first = self.horizontal_angles[0];
+        let last = self.horizontal_angles[self.horizontal_angles.len() - 1];
+
+        if self.horizontal_angles.len() == 1 {
+            first
+        } else if first == 90.0 && last == 270.0 {
+            // mirrored across the 90-270 plane
+            if horizontal < 90.0 {
+                180.0 - horizontal
+            } else if horizontal > 270.0 {
+                540.0 - horizontal
+            } else {
+                horizontal
+            }
+        } else if last <= 90.0 {
+            let h = if horizontal > 180.0 { 360.0 - horizontal } else { horizontal };
+            if h > 90.0 { 180.0 - h } else { h }
+        } else if last <= 180.0 {
+            if horizontal > 180.0 { 360.0 - horizontal } else { horizontal }
+        } else {
+            horizontal
+        }
+    }
+}
+
+// the segment of the ascending `angles` containing `angle` and the position
+// within it, None outside of the covered range
+fn locate(angles: &[f32], angle: f32) -> Option<(usize, f32)> {
+    let (first, last) = (angles[0], angles[angles.len() - 1]);
+
+    if angle < first || angle > last {
+        return None;
+    }
+    if angles.len() == 1 || angle == last {
+        return Some((angles.len() - 1, 0.0));
+    }
+
+    let i = angles.partition_point(|a| *a <= angle) - 1;
+    let t = (angle - angles[i]) / (angles[i + 1] - angles[i]);
+
+    Some((i, utils::clamp(t, 0.0, 1.0)))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.001
+    }
+
+    #[test]
+    fn isotropic_profile() {
+        let profile = IesProfile::parse(include_str!("../../files/ies/isotropic.ies")).unwrap();
+
+        assert!(close(profile.max_candela(), 100.0));
+        assert!(close(profile.candela(0.0, 0.0), 100.0));
+        assert!(close(profile.candela(123.0, 271.0), 100.0));
+    }
+
+    #[test]
+    fn downlight_is_scaled_and_cut_off() {
+        let profile = IesProfile::parse(include_str!("../../files/ies/downlight.ies")).unwrap();
+
+        assert!(close(profile.max_candela(), 2000.0));
+        assert!(close(profile.candela(15.0, 40.0), 1800.0));
+        assert!(close(profile.candela(90.0, 0.0), 0.0));
+        assert!(close(profile.candela(135.0, 0.0), 0.0));
+    }
+
+    #[test]
+    fn bilateral_profile_with_tilt_data() {
+        let profile = IesProfile::parse(include_str!("../../files/ies/wallwasher.ies")).unwrap();
+
+        // the ballast factor halves every value
+        assert!(close(profile.candela(0.0, 0.0), 100.0));
+        assert!(close(profile.candela(45.0, 90.0), 100.0));
+        assert!(close(profile.candela(45.0, 135.0), 125.0));
+        assert!(close(profile.candela(22.5, 45.0), 112.5));
+        // mirrored across the 0-180 plane
+        assert!(close(profile.candela(45.0, 270.0), profile.candela(45.0, 90.0)));
+        assert!(close(profile.candela(90.0, 200.0), profile.candela(90.0, 160.0)));
+    }
+
+    #[test]
+    fn profile_from_90_to_270_degrees() {
+        let profile = IesProfile::parse(include_str!("../../files/ies/streetlight.ies")).unwrap();
+
+        assert!(close(profile.candela(45.0, 180.0), 150.0));
+        assert!(close(profile.candela(45.0, 135.0), 225.0));
+        // mirrored across the 90-270 plane
+        assert!(close(profile.candela(45.0, 0.0), profile.candela(45.0, 180.0)));
+        assert!(close(profile.candela(45.0, 45.0), profile.candela(45.0, 135.0)));
+        assert!(close(profile.candela(90.0, 300.0), profile.candela(90.0, 240.0)));
+    }
+
+    #[test]
+    fn malformed_files_are_rejected() {
+        assert!(IesProfile::parse("IESNA:LM-63-2002\n1 -1 1 3 1 1 1 0 0 0").is_err());
+        assert!(IesProfile::parse("IESNA:LM-63-2002\nTILT=NONE\n1 -1 1 3 1 1 1 0 0 0\n1 1 100\n0 90 180\n0\n100 100").is_err());
+        assert!(IesProfile::parse("IESNA:LM-63-2002\nTILT=NONE\n1 -1 1 2 1 2 1 0 0 0\n1 1 100\n0 90\n0\n100 100").is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+
+use image::{DynamicImage, GenericImageView, ImageBuffer, Rgba};
+use image::buffer::ConvertBuffer;
+use image::hdr::HdrDecoder;
+
+use crate::color::Color;
+use crate::io::exr;
+
+// decoded image with channels in [0, 1], or unbounded for float images.
+// `alpha` is only present when the file has an alpha channel.
+pub struct ImageData {
+    pub width: usize,
+    pub height: usize,
+    pub texels: Vec<Color>,
+    pub alpha: Option<Vec<f32>>,
+    // float images hold linear values, the others are usually sRGB encoded
+    pub float: bool,
+}
+
+pub fn load(path: &str) -> ImageData {
+    read(path).unwrap_or_else(|error| panic!("Texture image {} could not be loaded: {}", path, error))
+}
+
+pub fn read(path: &str) -> Result<ImageData, String> {
+    let extension = Path::new(path).extension().and_then(|extension| extension.to_str()).unwrap_or("");
+
+    if extension.eq_ignore_ascii_case("hdr") {
+        return load_hdr(path);
+    }
+    if extension.eq_ignore_ascii_case("exr") {
+        return load_exr(path);
+    }
+
+    let image = image::open(path).map_err(|error| error.to_string())?;
+    let (width, height) = image.dimensions();
+    let has_alpha = image.color().has_alpha();
+
+    // 16 bit images keep their precision, everything else is read as 8 bit
+    let rgba16: Option<ImageBuffer<Rgba<u16>, Vec<u16>>> = match &image {
+        DynamicImage::ImageLuma16(buffer) => Some(buffer.convert()),
+        DynamicImage::ImageLumaA16(buffer) => Some(buffer.convert()),
+        DynamicImage::ImageRgb16(buffer) => Some(buffer.convert()),
+        DynamicImage::ImageRgba16(buffer) => Some(buffer.clone()),
+        _ => None,
+    };
+
+    let (texels, alpha): (Vec<Color>, Vec<f32>) = match rgba16 {
+        Some(buffer) => {
+            let scale = 1. / 65535.;
+            buffer.pixels()
+                .map(|p| (Color::new(p[0] as f32 * scale, p[1] as f32 * scale, p[2] as f32 * scale), p[3] as f32 * scale))
+                .unzip()
+        }
+        None => {
+            let scale = 1. / 255.;
+            image.to_rgba().pixels()
+                .map(|p| (Color::new(p[0] as f32 * scale, p[1] as f32 * scale, p[2] as f32 * scale), p[3] as f32 * scale))
+                .unzip()
+        }
+    };
+
+    Ok(ImageData {
+        width: width as usize,
+        height: height as usize,
+        texels,
+        alpha: if has_alpha { Some(alpha) } else { None },
+        float: false,
+    })
+}
+
+fn load_exr(path: &str) -> Result<ImageData, String> {
+    let data = fs::read(path).map_err(|error| error.to_string())?;
+    let (width, height, texels) = exr::decode(&data)?;
+
+    Ok(ImageData {
+        width: width as usize,
+        height: height as usize,
+        texels,
+        alpha: None,
+        float: true,
+    })
+}
+
+fn load_hdr(path: &str) -> Result<ImageData, String> {
+    let file = File::open(path).map_err(|error| error.to_string())?;
+    let decoder = HdrDecoder::new(BufReader::new(file)).map_err(|error| error.to_string())?;
+    let metadata = decoder.metadata();
+    let pixels = decoder.read_image_hdr().map_err(|error| error.to_string())?;
+
+    Ok(ImageData {
+        width: metadata.width as usize,
+        height: metadata.height as usize,
+        texels: pixels.iter().map(|p| Color::new(p[0], p[1], p[2])).collect(),
+        alpha: None,
+        float: true,
+    })
+}
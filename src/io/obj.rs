@@ -1,61 +1,290 @@
 use std::convert::TryFrom;
+use std::path::Path;
 use std::sync::Arc;
 
 use crate::objects::triangle::Triangle;
 use crate::materials::Material;
+use crate::materials::principled::{Principled, constant};
+use crate::materials::alpha_mask::AlphaMask;
 use crate::structures::vec3::Vec3;
 use crate::hitable::Hitable;
+use crate::color::Color;
+use crate::texture::{Texture, SolidColor, ImageTexture, OpacityTexture, ColorSpace};
+use crate::utils;
 
-// TODO: support more than one model
+// None, with the reason printed, when the file can't be read or holds no
+// triangles
 pub fn load_file(path: &str, material: Arc<dyn Material>) -> Option<Vec<Box<dyn Hitable>>> {
+    let triangles = match read_file(path, material) {
+        Ok(triangles) => triangles,
+        Err(error) => {
+            eprintln!("Failed to load {}: {}", path, error);
+            return None;
+        }
+    };
+
+    if triangles.is_empty(){
+        return None;
+    }
+
+    Some(triangles)
+}
+
+// TODO: support more than one model
+pub fn read_file(path: &str, material: Arc<dyn Material>) -> Result<Vec<Box<dyn Hitable>>, String> {
 
-    let (models, _) = tobj::load_obj(path, false).expect("Failed to load file");
+    let (models, _) = tobj::load_obj(path, false).map_err(|error| error.to_string())?;
     println!("Loaded {}, found {} models", path, models.len());
-    
-    assert!(models.len() == 1);
-    
+
+    if models.len() != 1 {
+        return Err(format!("expected one model, found {}", models.len()));
+    }
+
+    let mut triangles: Vec<Box<dyn Hitable>> = Vec::new();
+
+    for m in models.iter() {
+        triangles.append(&mut mesh_triangles(&m.mesh, &material));
+    }
+
+    Ok(triangles)
+}
+
+// loads all models of the file with the materials of its MTL library mapped
+// onto `Principled`, masked by `map_d` or a dissolve below one. Models
+// without a material use `default_material`
+pub fn load_file_with_materials(path: &str, default_material: Arc<dyn Material>) -> Option<Vec<Box<dyn Hitable>>> {
+    let triangles = read_file_with_materials(path, default_material)
+        .unwrap_or_else(|error| panic!("Failed to load {}: {}", path, error));
+
+    if triangles.is_empty(){
+        return None;
+    }
+
+    Some(triangles)
+}
+
+pub fn read_file_with_materials(path: &str, default_material: Arc<dyn Material>) -> Result<Vec<Box<dyn Hitable>>, String> {
+
+    let (models, mtl_materials) = tobj::load_obj(path, false).map_err(|error| error.to_string())?;
+    println!("Loaded {}, found {} models and {} materials", path, models.len(), mtl_materials.len());
+
+    let directory = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+    let materials: Vec<Arc<dyn Material>> = mtl_materials.iter()
+        .map(|m| material_from_mtl(m, directory))
+        .collect::<Result<_, _>>()?;
+
     let mut triangles: Vec<Box<dyn Hitable>> = Vec::new();
 
-    for (_, m) in models.iter().enumerate() {
-        
-        let mesh = &m.mesh;
+    for m in models.iter() {
+        let material = match m.mesh.material_id {
+            Some(id) if id < materials.len() => &materials[id],
+            _ => &default_material,
+        };
 
-        assert!(mesh.positions.len() % 3 == 0);
+        triangles.append(&mut mesh_triangles(&m.mesh, material));
+    }
 
-        let mut next_face = 0;
+    Ok(triangles)
+}
 
-        for f in 0..mesh.num_face_indices.len() {
-            let end = next_face + mesh.num_face_indices[f] as usize;
-            let face_indices: Vec<_> = mesh.indices[next_face..end].iter().collect();
-           
-            let v1_idx = usize::try_from(*face_indices[0]).unwrap();
-            let v2_idx = usize::try_from(*face_indices[1]).unwrap();
-            let v3_idx = usize::try_from(*face_indices[2]).unwrap();
-            
-            let p1x = mesh.positions[3 * v1_idx];
-            let p1y = mesh.positions[3 * v1_idx + 1];
-            let p1z = mesh.positions[3 * v1_idx + 2];
-            let p0 = Vec3::new(p1x, p1y, p1z);
+fn mesh_triangles(mesh: &tobj::Mesh, material: &Arc<dyn Material>) -> Vec<Box<dyn Hitable>> {
 
-            let p2x = mesh.positions[3 * v2_idx];
-            let p2y = mesh.positions[3 * v2_idx + 1];
-            let p2z = mesh.positions[3 * v2_idx + 2];
-            let p1 = Vec3::new(p2x, p2y, p2z);
+    assert!(mesh.positions.len().is_multiple_of(3));
 
-            let p3x = mesh.positions[3 * v3_idx];
-            let p3y = mesh.positions[3 * v3_idx + 1];
-            let p3z = mesh.positions[3 * v3_idx + 2];
-            let p2 = Vec3::new(p3x, p3y, p3z);
+    let mut triangles: Vec<Box<dyn Hitable>> = Vec::new();
+    let mut next_face = 0;
+
+    for f in 0..mesh.num_face_indices.len() {
+        let end = next_face + mesh.num_face_indices[f] as usize;
+        let face_indices: Vec<_> = mesh.indices[next_face..end].iter().collect();
+
+        let v1_idx = usize::try_from(*face_indices[0]).unwrap();
+        let v2_idx = usize::try_from(*face_indices[1]).unwrap();
+        let v3_idx = usize::try_from(*face_indices[2]).unwrap();
+
+        let p1x = mesh.positions[3 * v1_idx];
+        let p1y = mesh.positions[3 * v1_idx + 1];
+        let p1z = mesh.positions[3 * v1_idx + 2];
+        let p0 = Vec3::new(p1x, p1y, p1z);
+
+        let p2x = mesh.positions[3 * v2_idx];
+        let p2y = mesh.positions[3 * v2_idx + 1];
+        let p2z = mesh.positions[3 * v2_idx + 2];
+        let p1 = Vec3::new(p2x, p2y, p2z);
 
-            triangles.push(Box::new(Triangle::new(p0, p1, p2, material.clone())));
+        let p3x = mesh.positions[3 * v3_idx];
+        let p3y = mesh.positions[3 * v3_idx + 1];
+        let p3z = mesh.positions[3 * v3_idx + 2];
+        let p2 = Vec3::new(p3x, p3y, p3z);
 
-            next_face = end;
+        let triangle = Triangle::new(p0, p1, p2, material.clone());
+
+        if mesh.texcoords.is_empty() {
+            triangles.push(Box::new(triangle));
+        } else {
+            let uv = |idx: usize| (mesh.texcoords[2 * idx], mesh.texcoords[2 * idx + 1]);
+            triangles.push(Box::new(triangle.with_uvs(uv(v1_idx), uv(v2_idx), uv(v3_idx))));
         }
+
+        next_face = end;
     }
 
-    if triangles.is_empty(){
-        return None;
+    triangles
+}
+
+// `d` and `map_d` are the opacity of the surface, not glass: an opacity map
+// cuts the surface out, a constant partial opacity lets rays through with
+// that probability
+fn material_from_mtl(mtl: &tobj::Material, directory: &Path) -> Result<Arc<dyn Material>, String> {
+    let material = Arc::new(principled_from_mtl(mtl, directory)?) as Arc<dyn Material>;
+
+    if !mtl.dissolve_texture.is_empty() {
+        let opacity = OpacityTexture::read(&texture_path(directory, &mtl.dissolve_texture)?)?;
+        return Ok(Arc::new(AlphaMask::new(material, opacity)));
     }
 
-    Some(triangles)
-}
\ No newline at end of file
+    let opacity = opacity_from_mtl(mtl);
+    if opacity < 1.0 {
+        return Ok(Arc::new(AlphaMask::stochastic(material, SolidColor::new(Color::new(opacity, opacity, opacity)))));
+    }
+
+    Ok(material)
+}
+
+fn texture_path(directory: &Path, name: &str) -> Result<String, String> {
+    let path = directory.join(name);
+
+    path.to_str().map(String::from).ok_or_else(|| format!("texture path {} is not valid unicode", path.display()))
+}
+
+// `d` with `Tr = 1 - d` as the fallback some exporters write instead
+fn opacity_from_mtl(mtl: &tobj::Material) -> f32 {
+    let transparency = mtl.unknown_param.get("Tr").and_then(|value| value.parse::<f32>().ok());
+
+    match transparency {
+        Some(transparency) if mtl.dissolve >= 1.0 => utils::clamp(1.0 - transparency, 0.0, 1.0),
+        _ => utils::clamp(mtl.dissolve, 0.0, 1.0),
+    }
+}
+
+// refraction comes from the PBR extension `Pt`, or from the transmission
+// filter `Tf` of the illumination models with refraction (4, 6, 7 and 9)
+fn transmission_from_mtl(mtl: &tobj::Material) -> f32 {
+    let parameter = |key: &str| mtl.unknown_param.get(key);
+
+    if let Some(transmission) = parameter("Pt").and_then(|value| value.parse::<f32>().ok()) {
+        return utils::clamp(transmission, 0.0, 1.0);
+    }
+
+    let refracting = matches!(mtl.illumination_model, Some(4) | Some(6) | Some(7) | Some(9));
+    let filter: Vec<f32> = parameter("Tf")
+        .map(|value| value.split_whitespace().filter_map(|token| token.parse().ok()).collect())
+        .unwrap_or_default();
+
+    if refracting && !filter.is_empty() {
+        utils::clamp(filter.iter().cloned().fold(0.0, f32::max), 0.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+// maps the classic MTL parameters and the PBR extension (Pr, Pm, Ps, Pc, Pcr)
+// onto the principled material
+fn principled_from_mtl(mtl: &tobj::Material, directory: &Path) -> Result<Principled, String> {
+    // only the base color map holds colors, the others are read as raw data
+    let texture = |name: &str, color_space: ColorSpace| -> Result<Option<Box<dyn Texture>>, String> {
+        if name.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Box::new(ImageTexture::read(&texture_path(directory, name)?, color_space)?)))
+    };
+    let parameter = |key: &str| -> Option<f32> {
+        mtl.unknown_param.get(key).and_then(|value| value.parse().ok())
+    };
+    let parameter_texture = |key: &str| -> Result<Option<Box<dyn Texture>>, String> {
+        match mtl.unknown_param.get(key) {
+            Some(name) => texture(name, ColorSpace::Raw),
+            None => Ok(None),
+        }
+    };
+
+    let [r, g, b] = mtl.diffuse;
+    let base_color = texture(&mtl.diffuse_texture, ColorSpace::Srgb)?
+        .unwrap_or_else(|| Box::new(SolidColor::new(Color::new(r, g, b))));
+
+    // blinn-phong exponents are translated to roughness with alpha = sqrt(2 / (n + 2))
+    let roughness = parameter_texture("map_Pr")?
+        .or_else(|| parameter("Pr").map(constant))
+        .unwrap_or_else(|| constant((2.0 / (mtl.shininess.max(0.0) + 2.0)).sqrt().sqrt()));
+
+    let metallic = parameter_texture("map_Pm")?
+        .or_else(|| parameter("Pm").map(constant))
+        .unwrap_or_else(|| constant(0.0));
+
+    let [sr, sg, sb] = mtl.specular;
+    let specular = texture(&mtl.specular_texture, ColorSpace::Raw)?
+        .unwrap_or_else(|| constant(if sr + sg + sb > 0.0 { sr.max(sg).max(sb).min(1.0) } else { 0.5 }));
+
+    // tobj reports a missing Ni as 1.0 which would make the surface invisible
+    let ior = if mtl.optical_density > 1.0 { mtl.optical_density } else { 1.45 };
+
+    Ok(Principled {
+        base_color,
+        metallic,
+        roughness,
+        specular,
+        sheen: parameter("Ps").map(constant).unwrap_or_else(|| constant(0.0)),
+        clearcoat: parameter("Pc").map(constant).unwrap_or_else(|| constant(0.0)),
+        clearcoat_roughness: parameter("Pcr").map(constant).unwrap_or_else(|| constant(0.03)),
+        transmission: constant(transmission_from_mtl(mtl)),
+        ior: constant(ior),
+        ..Principled::new(SolidColor::new(Color::new(r, g, b)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::io::BufReader;
+
+    fn materials(text: &str) -> Vec<tobj::Material> {
+        tobj::load_mtl_buf(&mut BufReader::new(text.as_bytes())).unwrap().0
+    }
+
+    #[test]
+    fn missing_files_load_nothing() {
+        let material: Arc<dyn Material> = Arc::new(Principled::new(SolidColor::new(Color::new(1.0, 1.0, 1.0))));
+
+        assert!(load_file("files/missing.obj", material.clone()).is_none());
+        assert!(read_file("files/missing.obj", material).is_err());
+    }
+
+    #[test]
+    fn mtl_parameters_map_onto_principled() {
+        let mtl = materials("newmtl leaf\nKd 0.2 0.6 0.1\nd 0.5\nNs 0\nPm 0.25\n\n\
+            newmtl glass\nKd 1 1 1\nillum 7\nTf 0.9 0.9 0.9\nNi 1.5\n\n\
+            newmtl coated\nKd 1 0 0\nTr 0.25\nPt 0.1\nPc 1\n");
+        let p = Vec3::new(0.0, 0.0, 0.0);
+
+        // dissolve is opacity, not glass
+        let leaf = principled_from_mtl(&mtl[0], Path::new("")).unwrap();
+        assert_eq!(opacity_from_mtl(&mtl[0]), 0.5);
+        assert_eq!(leaf.transmission.value(0.0, 0.0, &p), 0.0);
+        assert_eq!(leaf.metallic.value(0.0, 0.0, &p), 0.25);
+        assert_eq!(leaf.base_color.color(0.0, 0.0, &p).g, 0.6);
+        // Ns 0 is as rough as it gets
+        assert!((leaf.roughness.value(0.0, 0.0, &p) - 1.0).abs() < 1e-6);
+
+        let glass = principled_from_mtl(&mtl[1], Path::new("")).unwrap();
+        assert_eq!(opacity_from_mtl(&mtl[1]), 1.0);
+        assert!((glass.transmission.value(0.0, 0.0, &p) - 0.9).abs() < 1e-6);
+        assert_eq!(glass.ior.value(0.0, 0.0, &p), 1.5);
+
+        let coated = principled_from_mtl(&mtl[2], Path::new("")).unwrap();
+        assert_eq!(opacity_from_mtl(&mtl[2]), 0.75);
+        assert!((coated.transmission.value(0.0, 0.0, &p) - 0.1).abs() < 1e-6);
+        assert_eq!(coated.clearcoat.value(0.0, 0.0, &p), 1.0);
+    }
+}
@@ -0,0 +1,813 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::bvh::BVH;
+use crate::color::Color;
+use crate::environment::{EnvironmentMap, UniformEnvironment};
+use crate::hitable::{Hitable, HitableList, Surface};
+use crate::io::ies::IesProfile;
+use crate::io::obj;
+use crate::lights::{Light, DirectionalLight, PointLight, SpotLight};
+use crate::lights::area::AreaLight;
+use crate::materials::{Material, Lambertian, Metal, Dielectric, DiffuseLight};
+use crate::materials::conductor::Conductor;
+use crate::materials::mix::MixMaterial;
+use crate::materials::principled::Principled;
+use crate::materials::rough_dielectric::RoughDielectric;
+use crate::objects::{Translate, RotateY, FlipFace};
+use crate::objects::rect::{XyRect, XzRect, YzRect};
+use crate::objects::rectbox::RectBox;
+use crate::objects::sphere::Sphere;
+use crate::scene::Scene;
+use crate::structures::camera::Camera;
+use crate::structures::vec3::Vec3;
+use crate::texture::{Texture, SolidColor, CheckerTexture, NoiseTexture, ImageTexture, ColorSpace};
+use crate::texture::nodes::{AddTexture, MultiplyTexture, MixTexture, InvertTexture, ClampTexture, RampTexture, ScalarToColor, UvTransform, CoordinateTexture, CoordinateSpace};
+use crate::texture::mipmap::WrapMode;
+use crate::texture::perlin::Perlin;
+use crate::texture::procedural::ColorRamp;
+
+// A scene described in a text file, one statement per line:
+//
+//     keyword [name] [kind] key=value ...
+//
+// Values are numbers, comma separated vectors and colors like `1,0.5,0`,
+// names of textures and materials defined on earlier lines, or paths
+// relative to the scene file. Wherever a texture is expected a number or a
+// color can be given instead, so textures nest into node graphs. Empty lines
+// and lines starting with `#` are skipped.
+//
+//     image width=400 height=300 samples=100 depth=50
+//     camera from=0,1,5 at=0,0.5,0 fov=40
+//     environment color=0.5,0.7,1
+//     texture tiles checker odd=0.1 even=0.9
+//     texture faded mix first=tiles second=0.5,0.4,0.3 factor=0.25
+//     material floor principled base_color=tiles roughness=0.3
+//     sphere center=0,-1000,0 radius=1000 material=floor
+//     mesh path=teapot.obj material=floor rotate_y=30 translate=0,1,0
+//     point position=0,4,0 color=1,1,1 power=500
+pub struct SceneFile {
+    pub camera: Camera,
+    pub scene: Scene,
+    pub width: u32,
+    pub height: u32,
+    pub samples_per_pixel: u32,
+    pub max_depth: u32,
+}
+
+impl SceneFile {
+    pub fn load(path: &str) -> Self {
+        let text = fs::read_to_string(path).expect("Scene file could not be read");
+        let directory = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+
+        SceneFile::parse(&text, directory).unwrap_or_else(|error| panic!("Invalid scene file {}: {}", path, error))
+    }
+
+    // `directory` is where relative paths are looked up
+    pub fn parse(text: &str, directory: &Path) -> Result<Self, String> {
+        let mut parser = Parser::new(directory);
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            parser.statement(line).map_err(|error| format!("line {}: {}", number + 1, error))?;
+        }
+
+        parser.finish()
+    }
+}
+
+struct CameraParameters {
+    from: Vec3,
+    at: Vec3,
+    up: Vec3,
+    fov: f32,
+    aperture: f32,
+    focus: Option<f32>,
+    shutter: f32,
+}
+
+struct Parser<'a> {
+    directory: &'a Path,
+    camera: CameraParameters,
+    width: u32,
+    height: u32,
+    samples_per_pixel: u32,
+    max_depth: u32,
+    textures: HashMap<String, Arc<dyn Texture>>,
+    materials: HashMap<String, Arc<dyn Material>>,
+    world: Vec<Box<dyn Hitable>>,
+    scene: Scene,
+}
+
+impl<'a> Parser<'a> {
+    fn new(directory: &'a Path) -> Self {
+        Parser {
+            directory,
+            camera: CameraParameters {
+                from: Vec3::new(0.0, 0.0, 1.0),
+                at: Vec3::new(0.0, 0.0, 0.0),
+                up: Vec3::new(0.0, 1.0, 0.0),
+                fov: 40.0,
+                aperture: 0.0,
+                focus: None,
+                shutter: 1.0,
+            },
+            width: 400,
+            height: 300,
+            samples_per_pixel: 100,
+            max_depth: 50,
+            textures: HashMap::new(),
+            materials: HashMap::new(),
+            world: Vec::new(),
+            scene: Scene::new(HitableList::default()),
+        }
+    }
+
+    fn statement(&mut self, line: &str) -> Result<(), String> {
+        let mut tokens = line.split_whitespace();
+        let keyword = tokens.next().unwrap_or_default();
+        let mut positional = Vec::new();
+        let mut arguments = Vec::new();
+
+        for token in tokens {
+            match token.find('=') {
+                Some(i) => arguments.push((&token[..i], &token[i + 1..])),
+                None if arguments.is_empty() => positional.push(token),
+                None => return Err(format!("expected key=value, found '{}'", token)),
+            }
+        }
+
+        let mut arguments = Arguments { values: arguments };
+        let name = |i: usize, what: &str| positional.get(i).copied().ok_or(format!("{} is missing its {}", keyword, what));
+
+        match keyword {
+            "image" => self.image(&mut arguments)?,
+            "camera" => self.camera(&mut arguments)?,
+            "environment" => self.environment(&mut arguments)?,
+            "texture" => {
+                let texture = self.texture_definition(name(1, "kind")?, &mut arguments)?;
+                self.textures.insert(name(0, "name")?.to_string(), texture);
+            }
+            "material" => {
+                let material = self.material_definition(name(1, "kind")?, &mut arguments)?;
+                self.materials.insert(name(0, "name")?.to_string(), material);
+            }
+            "sphere" | "rect" | "box" | "mesh" => self.object(keyword, &mut arguments)?,
+            "point" | "spot" | "directional" => self.light(keyword, &mut arguments)?,
+            _ => return Err(format!("unknown statement '{}'", keyword)),
+        }
+
+        arguments.finish()
+    }
+
+    fn image(&mut self, arguments: &mut Arguments) -> Result<(), String> {
+        self.width = arguments.number_or("width", self.width as f32)? as u32;
+        self.height = arguments.number_or("height", self.height as f32)? as u32;
+        self.samples_per_pixel = arguments.number_or("samples", self.samples_per_pixel as f32)? as u32;
+        self.max_depth = arguments.number_or("depth", self.max_depth as f32)? as u32;
+
+        if self.width == 0 || self.height == 0 {
+            return Err("the image must not be empty".to_string());
+        }
+
+        Ok(())
+    }
+
+    fn camera(&mut self, arguments: &mut Arguments) -> Result<(), String> {
+        let camera = &mut self.camera;
+
+        camera.from = arguments.vector("from")?.unwrap_or(camera.from);
+        camera.at = arguments.vector("at")?.unwrap_or(camera.at);
+        camera.up = arguments.vector("up")?.unwrap_or(camera.up);
+        camera.fov = arguments.number_or("fov", camera.fov)?;
+        camera.aperture = arguments.number_or("aperture", camera.aperture)?;
+        camera.focus = arguments.number("focus")?.or(camera.focus);
+        camera.shutter = arguments.number_or("shutter", camera.shutter)?;
+
+        if camera.shutter <= 0.0 {
+            return Err("the shutter must stay open for some time".to_string());
+        }
+
+        Ok(())
+    }
+
+    fn environment(&mut self, arguments: &mut Arguments) -> Result<(), String> {
+        let group = arguments.string("group");
+
+        self.scene.environment = match arguments.path("map", self.directory)? {
+            Some(path) => {
+                let mut map = EnvironmentMap::read(&path)
+                    .map_err(|error| format!("{}: {}", path, error))?
+                    .with_rotation(arguments.number_or("rotation", 0.0)?)
+                    .with_intensity(arguments.number_or("intensity", 1.0)?);
+                if let Some(group) = group {
+                    map = map.with_group(group);
+                }
+                Some(Box::new(map))
+            }
+            None => {
+                let mut environment = UniformEnvironment::new(arguments.required_color("color")?);
+                if let Some(group) = group {
+                    environment = environment.with_group(group);
+                }
+                Some(Box::new(environment))
+            }
+        };
+
+        Ok(())
+    }
+
+    fn texture_definition(&self, kind: &str, arguments: &mut Arguments) -> Result<Arc<dyn Texture>, String> {
+        let texture: Arc<dyn Texture> = match kind {
+            "color" => Arc::new(SolidColor::new(arguments.required_color("color")?)),
+            "image" => {
+                let path = arguments.path("path", self.directory)?.ok_or("missing path")?;
+                let color_space = match arguments.string("color_space").unwrap_or("srgb") {
+                    "srgb" => ColorSpace::Srgb,
+                    "linear" => ColorSpace::Linear,
+                    "raw" => ColorSpace::Raw,
+                    other => return Err(format!("unknown color space '{}'", other)),
+                };
+                let wrap = match arguments.string("wrap").unwrap_or("clamp") {
+                    "clamp" => WrapMode::Clamp,
+                    "repeat" => WrapMode::Repeat,
+                    "mirror" => WrapMode::Mirror,
+                    other => return Err(format!("unknown wrap mode '{}'", other)),
+                };
+                let texture = ImageTexture::read(&path, color_space).map_err(|error| format!("{}: {}", path, error))?;
+                Arc::new(texture.with_wrap(wrap))
+            }
+            "checker" => Arc::new(CheckerTexture::new(self.required_texture(arguments, "odd")?, self.required_texture(arguments, "even")?)),
+            "noise" => {
+                let noise = match arguments.number("seed")? {
+                    Some(seed) => Perlin::seeded(seed as u64),
+                    None => Perlin::default(),
+                };
+                Arc::new(NoiseTexture { noise, ..NoiseTexture::new(arguments.number_or("scale", 1.0)?) })
+            }
+            // nodes combining other textures
+            "add" => Arc::new(AddTexture::new(self.required_texture(arguments, "first")?, self.required_texture(arguments, "second")?)),
+            "multiply" => Arc::new(MultiplyTexture::new(self.required_texture(arguments, "first")?, self.required_texture(arguments, "second")?)),
+            "mix" => {
+                let first = self.required_texture(arguments, "first")?;
+                let second = self.required_texture(arguments, "second")?;
+                Arc::new(MixTexture::new(first, second, self.texture_or(arguments, "factor", 0.5)?))
+            }
+            "invert" => Arc::new(InvertTexture::new(self.required_texture(arguments, "texture")?)),
+            "clamp" => {
+                let texture = self.required_texture(arguments, "texture")?;
+                Arc::new(ClampTexture::new(texture, arguments.number_or("min", 0.0)?, arguments.number_or("max", 1.0)?))
+            }
+            "ramp" => Arc::new(RampTexture::new(self.required_texture(arguments, "factor")?, self.color_ramp(arguments)?)),
+            "scalar_to_color" => Arc::new(ScalarToColor::new(self.required_texture(arguments, "texture")?)),
+            "uv_transform" => {
+                let pair = |arguments: &mut Arguments, key: &str, default: (f32, f32)| -> Result<(f32, f32), String> {
+                    match arguments.take(key) {
+                        None => Ok(default),
+                        Some(value) => match parse_numbers(value)?[..] {
+                            [a, b] => Ok((a, b)),
+                            _ => Err(format!("{} needs two values", key)),
+                        },
+                    }
+                };
+
+                Arc::new(UvTransform {
+                    scale: pair(arguments, "scale", (1.0, 1.0))?,
+                    rotation: arguments.number_or("rotation", 0.0)?,
+                    offset: pair(arguments, "offset", (0.0, 0.0))?,
+                    ..UvTransform::new(self.required_texture(arguments, "texture")?)
+                })
+            }
+            "coordinate" => {
+                let space = match arguments.string("space").unwrap_or("world") {
+                    "world" => CoordinateSpace::World,
+                    "uv" => CoordinateSpace::Uv,
+                    "object" => CoordinateSpace::Object {
+                        origin: arguments.vector("origin")?.unwrap_or(Vec3::ZERO),
+                        scale: arguments.number_or("scale", 1.0)?,
+                    },
+                    other => return Err(format!("unknown coordinate space '{}'", other)),
+                };
+                Arc::new(CoordinateTexture::new(self.required_texture(arguments, "texture")?, space))
+            }
+            _ => return Err(format!("unknown texture '{}'", kind)),
+        };
+
+        Ok(texture)
+    }
+
+    fn material_definition(&self, kind: &str, arguments: &mut Arguments) -> Result<Arc<dyn Material>, String> {
+        let material: Arc<dyn Material> = match kind {
+            "lambertian" => Arc::new(Lambertian::new(self.required_texture(arguments, "albedo")?)),
+            "metal" => Arc::new(Metal::new(arguments.required_color("color")?, arguments.number_or("fuzz", 0.0)?)),
+            "dielectric" => Arc::new(Dielectric::new(arguments.number_or("ior", 1.5)?)),
+            "rough_dielectric" => {
+                let roughness = self.texture_or(arguments, "roughness", 0.1)?;
+                Arc::new(RoughDielectric::new(arguments.number_or("ior", 1.5)?, roughness))
+            }
+            "conductor" => {
+                let roughness = self.texture_or(arguments, "roughness", 0.1)?;
+                match arguments.string("preset") {
+                    Some("gold") => Arc::new(Conductor::gold(roughness)),
+                    Some("copper") => Arc::new(Conductor::copper(roughness)),
+                    Some("aluminium") => Arc::new(Conductor::aluminium(roughness)),
+                    Some(other) => return Err(format!("unknown conductor '{}'", other)),
+                    None => Arc::new(Conductor::new(arguments.required_color("eta")?, arguments.required_color("k")?, roughness)),
+                }
+            }
+            "principled" => Arc::new(self.principled(arguments)?),
+            "mix" => {
+                let first = self.required_material(arguments, "first")?;
+                let second = self.required_material(arguments, "second")?;
+                Arc::new(MixMaterial::new(first, second, self.texture_or(arguments, "factor", 0.5)?))
+            }
+            "light" => {
+                let mut light = DiffuseLight::new(self.required_texture(arguments, "color")?)
+                    .with_strength(arguments.number_or("strength", 1.0)?);
+                if arguments.flag("one_sided")? {
+                    light = light.one_sided();
+                }
+                if let Some(group) = arguments.string("group") {
+                    light = light.with_group(group);
+                }
+                Arc::new(light)
+            }
+            _ => return Err(format!("unknown material '{}'", kind)),
+        };
+
+        Ok(material)
+    }
+
+    // every parameter is optional and named like the field it sets
+    fn principled(&self, arguments: &mut Arguments) -> Result<Principled, String> {
+        let defaults = Principled::new(SolidColor::new(Color::new(0.8, 0.8, 0.8)));
+        let mut parameter = |key: &str, default: Box<dyn Texture>| -> Result<Box<dyn Texture>, String> {
+            Ok(self.texture(arguments, key)?.map_or(default, |texture| Box::new(texture) as Box<dyn Texture>))
+        };
+
+        Ok(Principled {
+            base_color: parameter("base_color", defaults.base_color)?,
+            metallic: parameter("metallic", defaults.metallic)?,
+            roughness: parameter("roughness", defaults.roughness)?,
+            specular: parameter("specular", defaults.specular)?,
+            specular_tint: parameter("specular_tint", defaults.specular_tint)?,
+            sheen: parameter("sheen", defaults.sheen)?,
+            sheen_tint: parameter("sheen_tint", defaults.sheen_tint)?,
+            clearcoat: parameter("clearcoat", defaults.clearcoat)?,
+            clearcoat_roughness: parameter("clearcoat_roughness", defaults.clearcoat_roughness)?,
+            transmission: parameter("transmission", defaults.transmission)?,
+            ior: parameter("ior", defaults.ior)?,
+        })
+    }
+
+    fn object(&mut self, kind: &str, arguments: &mut Arguments) -> Result<(), String> {
+        let light = arguments.flag("light")?;
+        let flip = arguments.flag("flip")?;
+
+        // meshes without a material use the ones of their MTL library
+        if kind == "mesh" {
+            let path = arguments.path("path", self.directory)?.ok_or("missing path")?;
+            let triangles = match arguments.string("material") {
+                Some(name) => obj::read_file(&path, self.named_material(name)?),
+                None => obj::read_file_with_materials(&path, Arc::new(Lambertian::new(SolidColor::new(Color::new(0.8, 0.8, 0.8))))),
+            };
+            let triangles = triangles.map_err(|error| format!("{}: {}", path, error))?;
+            if triangles.is_empty() {
+                return Err(format!("no triangles in {}", path));
+            }
+
+            return self.place(BVH::new(triangles, 0.0, self.camera.shutter), arguments);
+        }
+
+        let material = self.required_material(arguments, "material")?;
+
+        // area lights are sampled through the light list, which needs the
+        // surface itself rather than a transformed copy
+        if light {
+            return match kind {
+                "sphere" => {
+                    let sphere = Sphere::new(arguments.required_vector("center")?, arguments.required_number("radius")?, material);
+                    self.area_light(sphere, flip);
+                    Ok(())
+                }
+                "rect" => {
+                    let Rect { plane, min, max, k } = rect(arguments)?;
+                    match plane {
+                        "xy" => self.area_light(XyRect::new(min.0, max.0, min.1, max.1, k, material), flip),
+                        "xz" => self.area_light(XzRect::new(min.0, max.0, min.1, max.1, k, material), flip),
+                        _ => self.area_light(YzRect::new(min.0, max.0, min.1, max.1, k, material), flip),
+                    }
+                    Ok(())
+                }
+                _ => Err(format!("a {} can't be a light", kind)),
+            };
+        }
+
+        let object: Box<dyn Hitable> = match kind {
+            "sphere" => Box::new(Sphere::new(arguments.required_vector("center")?, arguments.required_number("radius")?, material)),
+            "rect" => {
+                let Rect { plane, min, max, k } = rect(arguments)?;
+                match plane {
+                    "xy" => Box::new(XyRect::new(min.0, max.0, min.1, max.1, k, material)),
+                    "xz" => Box::new(XzRect::new(min.0, max.0, min.1, max.1, k, material)),
+                    _ => Box::new(YzRect::new(min.0, max.0, min.1, max.1, k, material)),
+                }
+            }
+            _ => Box::new(RectBox::new(&arguments.required_vector("min")?, &arguments.required_vector("max")?, material)),
+        };
+
+        if flip {
+            self.place(FlipFace::new(object), arguments)
+        } else {
+            self.place(object, arguments)
+        }
+    }
+
+    // rotates around the y axis and then moves the object
+    fn place(&mut self, object: impl Hitable + 'static, arguments: &mut Arguments) -> Result<(), String> {
+        let object: Box<dyn Hitable> = match arguments.number("rotate_y")? {
+            Some(angle) => Box::new(RotateY::new(object, angle)),
+            None => Box::new(object),
+        };
+        let object: Box<dyn Hitable> = match arguments.vector("translate")? {
+            Some(displacement) => Box::new(Translate::translate(object, &displacement)),
+            None => object,
+        };
+
+        self.world.push(object);
+        Ok(())
+    }
+
+    fn area_light<S: Surface + 'static>(&mut self, surface: S, flip: bool) {
+        if flip {
+            self.scene.lights.push(Box::new(AreaLight::new(FlipFace::new(surface))));
+        } else {
+            self.scene.lights.push(Box::new(AreaLight::new(surface)));
+        }
+    }
+
+    fn light(&mut self, kind: &str, arguments: &mut Arguments) -> Result<(), String> {
+        let color = arguments.color("color")?.unwrap_or(Color::new(1.0, 1.0, 1.0));
+        let group = arguments.string("group");
+        let profile = match arguments.path("profile", self.directory)? {
+            Some(path) => Some(Arc::new(IesProfile::read(&path).map_err(|error| format!("{}: {}", path, error))?)),
+            None => None,
+        };
+        // where the horizontal angles of the profile start
+        let azimuth = arguments.vector("azimuth")?;
+        let light: Box<dyn Light> = match kind {
+            "point" => {
+                let position = arguments.required_vector("position")?;
+                let mut light = match arguments.number("power")? {
+                    Some(power) => PointLight::from_power(position, color, power),
+                    None => PointLight::new(position, color, arguments.required_number("intensity")?),
+                };
+                if let Some(profile) = profile {
+                    let azimuth = azimuth.unwrap_or(light.azimuth);
+                    light = light.with_profile(profile, azimuth);
+                }
+                if let Some(group) = group {
+                    light = light.with_group(group);
+                }
+                Box::new(light)
+            }
+            "spot" => {
+                let mut light = SpotLight::new(
+                    arguments.required_vector("position")?,
+                    arguments.required_vector("direction")?,
+                    color,
+                    arguments.required_number("intensity")?,
+                    arguments.number_or("angle", 45.0)?)
+                    .with_falloff(arguments.number_or("falloff", 0.2)?);
+                if let Some(profile) = profile {
+                    let azimuth = azimuth.unwrap_or(light.azimuth);
+                    light = light.with_profile(profile, azimuth);
+                }
+                if let Some(group) = group {
+                    light = light.with_group(group);
+                }
+                Box::new(light)
+            }
+            _ => {
+                let mut light = DirectionalLight::new(arguments.required_vector("direction")?, color, arguments.required_number("irradiance")?);
+                if let Some(group) = group {
+                    light = light.with_group(group);
+                }
+                Box::new(light)
+            }
+        };
+
+        self.scene.lights.push(light);
+        Ok(())
+    }
+
+    // a texture by name, or a constant one from a number or a color
+    fn texture(&self, arguments: &mut Arguments, key: &str) -> Result<Option<Arc<dyn Texture>>, String> {
+        arguments.take(key).map(|value| self.texture_value(value)).transpose()
+    }
+
+    fn texture_value(&self, value: &str) -> Result<Arc<dyn Texture>, String> {
+        if let Some(texture) = self.textures.get(value) {
+            return Ok(texture.clone());
+        }
+
+        // names start with a letter, anything else should be a value
+        if value.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+            return Err(format!("unknown texture '{}'", value));
+        }
+
+        Ok(Arc::new(SolidColor::new(parse_color(value)?)))
+    }
+
+    // `position:texture` pairs separated by semicolons, e.g. `0:0,0,0;1:tiles`
+    fn color_ramp(&self, arguments: &mut Arguments) -> Result<ColorRamp, String> {
+        let stops = arguments.take("stops").ok_or("missing stops")?
+            .split(';')
+            .map(|stop| {
+                let (position, texture) = stop.split_at(stop.find(':').ok_or(format!("expected position:texture, found '{}'", stop))?);
+                let position = position.parse::<f32>().map_err(|_| format!("invalid position '{}'", position))?;
+
+                Ok((position, Box::new(self.texture_value(&texture[1..])?) as Box<dyn Texture>))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(ColorRamp::new(stops))
+    }
+
+    fn required_texture(&self, arguments: &mut Arguments, key: &str) -> Result<Arc<dyn Texture>, String> {
+        self.texture(arguments, key)?.ok_or(format!("missing {}", key))
+    }
+
+    fn texture_or(&self, arguments: &mut Arguments, key: &str, default: f32) -> Result<Arc<dyn Texture>, String> {
+        let default = Color::new(default, default, default);
+        Ok(self.texture(arguments, key)?.unwrap_or_else(|| Arc::new(SolidColor::new(default))))
+    }
+
+    fn named_material(&self, name: &str) -> Result<Arc<dyn Material>, String> {
+        self.materials.get(name).cloned().ok_or(format!("unknown material '{}'", name))
+    }
+
+    fn required_material(&self, arguments: &mut Arguments, key: &str) -> Result<Arc<dyn Material>, String> {
+        let name = arguments.string(key).ok_or(format!("missing {}", key))?;
+        self.named_material(name)
+    }
+
+    fn finish(self) -> Result<SceneFile, String> {
+        let camera = &self.camera;
+        let aspect_ratio = self.width as f32 / self.height as f32;
+        let focus = camera.focus.unwrap_or_else(|| (camera.from - camera.at).length());
+
+        let world: Box<dyn Hitable> = if self.world.is_empty() {
+            Box::new(HitableList::default())
+        } else {
+            Box::new(BVH::new(self.world, 0.0, camera.shutter))
+        };
+
+        Ok(SceneFile {
+            camera: Camera::new(camera.from, camera.at, camera.up, camera.fov, aspect_ratio, camera.aperture, focus, 0.0, camera.shutter),
+            scene: Scene { world, ..self.scene },
+            width: self.width,
+            height: self.height,
+            samples_per_pixel: self.samples_per_pixel,
+            max_depth: self.max_depth,
+        })
+    }
+}
+
+// `plane=xy|xz|yz` with the corners `min` and `max` in that plane at `k`
+// along the remaining axis
+struct Rect<'a> {
+    plane: &'a str,
+    min: (f32, f32),
+    max: (f32, f32),
+    k: f32,
+}
+
+fn rect<'a>(arguments: &mut Arguments<'a>) -> Result<Rect<'a>, String> {
+    let plane = arguments.string("plane").ok_or("missing plane")?;
+    if !["xy", "xz", "yz"].contains(&plane) {
+        return Err(format!("unknown plane '{}'", plane));
+    }
+
+    let corner = |arguments: &mut Arguments, key: &str| -> Result<(f32, f32), String> {
+        match parse_numbers(arguments.take(key).ok_or(format!("missing {}", key))?)?[..] {
+            [a, b] => Ok((a, b)),
+            _ => Err(format!("{} needs two values", key)),
+        }
+    };
+
+    Ok(Rect { plane, min: corner(arguments, "min")?, max: corner(arguments, "max")?, k: arguments.number_or("k", 0.0)? })
+}
+
+// the key=value pairs of a statement, every one has to be used
+struct Arguments<'a> {
+    values: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> Arguments<'a> {
+    fn take(&mut self, key: &str) -> Option<&'a str> {
+        let i = self.values.iter().position(|(k, _)| *k == key)?;
+        Some(self.values.remove(i).1)
+    }
+
+    fn string(&mut self, key: &str) -> Option<&'a str> {
+        self.take(key)
+    }
+
+    fn flag(&mut self, key: &str) -> Result<bool, String> {
+        match self.take(key) {
+            None | Some("false") => Ok(false),
+            Some("true") => Ok(true),
+            Some(other) => Err(format!("{} must be true or false, found '{}'", key, other)),
+        }
+    }
+
+    fn path(&mut self, key: &str, directory: &Path) -> Result<Option<String>, String> {
+        self.take(key)
+            .map(|path| directory.join(path).to_str().map(String::from).ok_or(format!("{} is not valid unicode", key)))
+            .transpose()
+    }
+
+    fn number(&mut self, key: &str) -> Result<Option<f32>, String> {
+        self.take(key)
+            .map(|value| value.parse::<f32>().map_err(|_| format!("invalid number '{}' for {}", value, key)))
+            .transpose()
+    }
+
+    fn number_or(&mut self, key: &str, default: f32) -> Result<f32, String> {
+        Ok(self.number(key)?.unwrap_or(default))
+    }
+
+    fn required_number(&mut self, key: &str) -> Result<f32, String> {
+        self.number(key)?.ok_or(format!("missing {}", key))
+    }
+
+    fn vector(&mut self, key: &str) -> Result<Option<Vec3>, String> {
+        self.take(key)
+            .map(|value| match parse_numbers(value)?[..] {
+                [x, y, z] => Ok(Vec3::new(x, y, z)),
+                _ => Err(format!("{} needs three values", key)),
+            })
+            .transpose()
+    }
+
+    fn required_vector(&mut self, key: &str) -> Result<Vec3, String> {
+        self.vector(key)?.ok_or(format!("missing {}", key))
+    }
+
+    fn color(&mut self, key: &str) -> Result<Option<Color>, String> {
+        self.take(key).map(parse_color).transpose()
+    }
+
+    fn required_color(&mut self, key: &str) -> Result<Color, String> {
+        self.color(key)?.ok_or(format!("missing {}", key))
+    }
+
+    fn finish(&self) -> Result<(), String> {
+        match self.values.first() {
+            Some((key, _)) => Err(format!("unknown parameter '{}'", key)),
+            None => Ok(()),
+        }
+    }
+}
+
+fn parse_numbers(value: &str) -> Result<Vec<f32>, String> {
+    value.split(',')
+        .map(|token| token.parse::<f32>().map_err(|_| format!("invalid number '{}'", token)))
+        .collect()
+}
+
+// a gray from a single number or a color from three
+fn parse_color(value: &str) -> Result<Color, String> {
+    match parse_numbers(value)?[..] {
+        [gray] => Ok(Color::new(gray, gray, gray)),
+        [r, g, b] => Ok(Color::new(r, g, b)),
+        _ => Err(format!("expected one or three values, found '{}'", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::structures::ray::Ray;
+
+    #[test]
+    fn parse_scene() {
+        let text = "# a sphere under a lamp\n\
+            image width=200 height=100 samples=16 depth=8\n\
+            camera from=0,0,5 at=0,0,0 fov=30\n\
+            environment color=0.1\n\
+            texture tiles checker odd=0.2 even=0.8,0.8,0.7\n\
+            material red principled base_color=0.8,0.1,0.1 roughness=tiles clearcoat=1\n\
+            material lamp light color=4 one_sided=true\n\
+            sphere center=0,0,0 radius=1 material=red\n\
+            rect plane=xz min=-1,-1 max=1,1 k=3 material=lamp light=true flip=true\n\
+            box min=-1,0,-1 max=1,1,1 material=red rotate_y=45 translate=4,0,0\n\
+            point position=0,4,0 power=100\n";
+
+        let scene_file = SceneFile::parse(text, Path::new("")).unwrap();
+        assert_eq!((scene_file.width, scene_file.height), (200, 100));
+        assert_eq!((scene_file.samples_per_pixel, scene_file.max_depth), (16, 8));
+        assert_eq!(scene_file.scene.lights.len(), 2);
+        assert!(scene_file.scene.environment.is_some());
+
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let hit_record = scene_file.scene.world.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!((hit_record.t - 4.0).abs() < 1e-4);
+
+        // the corner of the turned box points along z
+        let ray = Ray::new(Vec3::new(4.0, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let hit_record = scene_file.scene.world.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!((hit_record.t - (5.0 - 2f32.sqrt())).abs() < 1e-4);
+
+        // the area light is not part of the world
+        let up = Ray::new(Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 0.0);
+        assert!(scene_file.scene.world.hit(&up, 0.001, f32::INFINITY).is_none());
+        assert!(scene_file.scene.hit(&up, 0.001, f32::INFINITY).is_some());
+    }
+
+    #[test]
+    fn textures_nest_into_node_graphs() {
+        let mut parser = Parser::new(Path::new(""));
+        let lines = [
+            "texture quarter color color=0.25",
+            "texture sum add first=quarter second=0.5",
+            "texture graph clamp texture=sum min=0 max=0.7",
+            "texture inverted invert texture=graph",
+            "texture product multiply first=inverted second=1,0,0.5",
+            "texture blend mix first=quarter second=1 factor=0.5",
+            "texture ramp ramp factor=blend stops=0:0;0.5:1,0,0;1:quarter",
+            "texture scaled uv_transform texture=ramp scale=2,2 rotation=90 offset=0.5,0",
+            "texture solid coordinate texture=scaled space=uv",
+            "texture gray scalar_to_color texture=product",
+        ];
+        for line in lines.iter() {
+            parser.statement(line).unwrap();
+        }
+
+        let color = |name: &str| parser.textures[name].color(0.0, 0.0, &Vec3::ZERO);
+
+        assert!((color("product").r - 0.3).abs() < 1e-6 && color("product").g == 0.0);
+        assert!((color("gray").b - 0.3).abs() < 1e-6);
+        // the blend is 0.625, a quarter of the way from red to the quarter gray
+        assert!((color("ramp").r - 0.8125).abs() < 1e-6 && (color("ramp").g - 0.0625).abs() < 1e-6);
+        assert!(parser.statement("texture broken ramp factor=blend stops=0:0;half:1").is_err());
+        assert!(parser.statement("texture broken coordinate texture=quarter space=screen").is_err());
+    }
+
+    #[test]
+    fn noise_is_the_same_for_the_same_seed() {
+        let mut parser = Parser::new(Path::new(""));
+        for line in ["texture a noise scale=3", "texture b noise scale=3", "texture c noise scale=3 seed=7", "texture d noise scale=3 seed=7"].iter() {
+            parser.statement(line).unwrap();
+        }
+
+        let p = Vec3::new(0.3, 1.7, -0.4);
+        let value = |name: &str| parser.textures[name].value(0.0, 0.0, &p);
+
+        assert_eq!(value("a"), value("b"));
+        assert_eq!(value("c"), value("d"));
+        assert!(value("a") != value("c"));
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let error = |text: &str| SceneFile::parse(text, Path::new("")).err().unwrap();
+
+        assert_eq!(error("image width=10\nsphere center=0,0,0 radius=1 material=missing\n"), "line 2: unknown material 'missing'");
+        assert_eq!(error("\ncamera from=0,0,1 colour=1\n"), "line 2: unknown parameter 'colour'");
+        assert_eq!(error("material a lambertian albedo=1,2\n"), "line 1: expected one or three values, found '1,2'");
+        assert_eq!(error("sphere center=0,0 radius=1 material=a\n"), "line 1: unknown material 'a'");
+        assert_eq!(error("teapot\n"), "line 1: unknown statement 'teapot'");
+    }
+
+    #[test]
+    fn profiles_turn_with_their_azimuth() {
+        let brightness = |line: &str| {
+            let scene_file = SceneFile::parse(line, Path::new("files/ies")).unwrap();
+            scene_file.scene.lights[0].sample(&Vec3::new(0.0, -1.0, 1.0)).unwrap().irradiance.r
+        };
+
+        let along_x = brightness("point position=0,0,0 intensity=1 profile=streetlight.ies azimuth=1,0,0\n");
+        let along_z = brightness("point position=0,0,0 intensity=1 profile=streetlight.ies azimuth=0,0,1\n");
+        assert!((along_x - 2.0 * along_z).abs() < 1e-4);
+    }
+
+    #[test]
+    fn missing_files_are_errors() {
+        let error = |text: &str| SceneFile::parse(text, Path::new("missing")).err().unwrap();
+
+        assert!(error("mesh path=model.obj\n").starts_with("line 1: missing/model.obj: "));
+        assert!(error("material a lambertian albedo=1\nmesh path=model.obj material=a\n").starts_with("line 2: missing/model.obj: "));
+        assert!(error("texture t image path=map.png\n").starts_with("line 1: missing/map.png: "));
+        assert!(error("environment map=sky.hdr\n").starts_with("line 1: missing/sky.hdr: "));
+        assert!(error("point position=0,1,0 intensity=1 profile=lamp.ies\n").starts_with("line 1: missing/lamp.ies: "));
+    }
+}
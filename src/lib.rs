@@ -11,21 +11,62 @@ pub mod hitable;
 pub mod aabb;
 pub mod bvh;
 pub mod texture;
+pub mod spectrum;
+pub mod environment;
+pub mod lights;
+pub mod scene;
+pub mod light_groups;
+pub mod aov;
+pub mod denoise;
+pub mod tonemap;
+pub mod post;
+pub mod film;
 mod utils;
 
-use std::f32::INFINITY;
 use std::time::Instant;
 
 use crate::random::random_double;
 use crate::structures::camera::Camera;
-use crate::hitable::Hitable;
-use crate::color::{Color, BLACK, gamma_correct, map_color_256};
+use crate::aov::{Aov, AovImages, AovSample};
+use crate::color::{Color, BLACK, WHITE};
+use crate::film::Film;
+use crate::light_groups::{GroupedRadiance, LightGroupImages};
+use crate::materials::HitRecord;
+use crate::scene::{Scene, SampleClamp};
+use crate::tonemap::ToneMapping;
 use crate::structures::ray::Ray;
 
 use indicatif::{ProgressBar, ProgressStyle, HumanDuration};
 use rayon::prelude::*;
 
-pub fn render(camera: &Camera, world: &Box<dyn Hitable>, image_width: u32, image_height: u32, samples_per_pixel: u32, max_depth: u32) -> Vec<u8> {
+pub fn render(camera: &Camera, scene: &Scene, image_width: u32, image_height: u32, samples_per_pixel: u32, max_depth: u32) -> Vec<u8> {
+    render_tone_mapped(camera, scene, image_width, image_height, samples_per_pixel, max_depth, &ToneMapping::default())
+}
+
+// 8 bit sRGB pixels with the given exposure and tone mapping operator, after
+// the post process effects of the scene
+#[allow(clippy::too_many_arguments)]
+pub fn render_tone_mapped(camera: &Camera, scene: &Scene, image_width: u32, image_height: u32, samples_per_pixel: u32, max_depth: u32, tone_mapping: &ToneMapping) -> Vec<u8> {
+    let images = render_light_groups(camera, scene, image_width, image_height, samples_per_pixel, max_depth);
+
+    tone_mapping.to_ldr(&scene.post.apply(image_width, image_height, &images.beauty()))
+}
+
+// the linear radiance of every light group as a separate image
+pub fn render_light_groups(camera: &Camera, scene: &Scene, image_width: u32, image_height: u32, samples_per_pixel: u32, max_depth: u32) -> LightGroupImages {
+    render_passes(camera, scene, image_width, image_height, samples_per_pixel, max_depth, false).0
+}
+
+// the linear beauty image with all auxiliary outputs
+pub fn render_aovs(camera: &Camera, scene: &Scene, image_width: u32, image_height: u32, samples_per_pixel: u32, max_depth: u32) -> AovImages {
+    let (groups, mut aovs) = render_passes(camera, scene, image_width, image_height, samples_per_pixel, max_depth, true);
+    aovs.beauty = groups.beauty();
+
+    aovs
+}
+
+#[allow(clippy::too_many_arguments)]
+fn render_passes(camera: &Camera, scene: &Scene, image_width: u32, image_height: u32, samples_per_pixel: u32, max_depth: u32, with_aovs: bool) -> (LightGroupImages, AovImages) {
 
     let start = Instant::now();
     let pixel_total = image_width * image_height;
@@ -41,30 +82,71 @@ pub fn render(camera: &Camera, world: &Box<dyn Hitable>, image_width: u32, image
     println!("Maximum ray bounces: {}", max_depth);
     println!("Shutter speed: {}s\n", camera.time1 - camera.time0);
 
-    let mut image_data: Vec<u8> = Vec::new();
+    let mut film = Film::new(image_width, image_height, scene.filter);
+    film.outlier_rejection = scene.outlier_rejection;
+    if with_aovs {
+        film = film.with_aovs();
+    }
+    let (mut clamped_direct, mut clamped_indirect, mut rejected) = (0u64, 0u64, 0u64);
+    let (mut material_ids, mut object_ids) = (vec![0; pixel_total as usize], vec![0; pixel_total as usize]);
+    let mut materials: Vec<usize> = Vec::new();
+    let pixel_spread = camera.pixel_spread(image_height);
+    let features = if with_aovs { Some((camera, image_width, image_height)) } else { None };
 
     for pixel_y in (0..image_height).rev() {
 
         for pixel_x in 0..image_width {
 
-            let pixel_color = (0..samples_per_pixel).into_par_iter().map(|_sample_n| {
+            let row = image_height - 1 - pixel_y;
+
+            let samples: Vec<(f32, f32, SampleRadiance, (bool, bool))> = (0..samples_per_pixel).into_par_iter().map(|_sample_n| {
 
-                let u = (pixel_x as f32 + random_double()) / (image_width as f32 - 1.0);
-                let v = (pixel_y as f32 + random_double()) / (image_height as f32 - 1.0);
+                // the position on the film, from the top left corner
+                let x = pixel_x as f32 + random_double();
+                let y = row as f32 + random_double();
+
+                let u = x / (image_width as f32 - 1.0);
+                let v = (image_height as f32 - y) / (image_height as f32 - 1.0);
                 
-                let ray = camera.get_ray(u, v);
+                let ray = camera.get_ray(u, v).with_spread(pixel_spread);
+                let mut radiance = SampleRadiance::new(features);
                 
-                ray_color(&ray, &world, max_depth, BLACK)
+                ray_color(&ray, scene, max_depth, None, WHITE, PathState::CAMERA, &mut radiance);
+                let clamped = radiance.finish(&scene.clamp);
+
+                (x, y, radiance, clamped)
             })
-            .reduce(|| BLACK, |final_color, next_color| final_color + next_color);
+            .collect();
+
+            rejected += film.add_pixel_samples(samples.iter().map(|(x, y, radiance, _)| (*x, *y, &radiance.groups, radiance.scattered))) as u64;
+            clamped_direct += samples.iter().filter(|(_, _, _, (direct, _))| *direct).count() as u64;
+            clamped_indirect += samples.iter().filter(|(_, _, _, (_, indirect))| *indirect).count() as u64;
 
-            for value in  
-                map_color_256(
-                    gamma_correct(
-                        average_samples(pixel_color, samples_per_pixel)))
-                            .iter() {
-                                image_data.push(*value)
-                            }
+            let index = (row * image_width + pixel_x) as usize;
+
+            if with_aovs {
+                for (x, y, radiance, _) in samples.iter() {
+                    film.add_aov_sample(*x, *y, &radiance.aovs);
+                }
+
+                let u = (pixel_x as f32 + 0.5) / (image_width as f32 - 1.0);
+                let v = (pixel_y as f32 + 0.5) / (image_height as f32 - 1.0);
+                let ray = Ray { time: camera.time0, ..camera.get_ray(u, v) };
+
+                if let Some((hit_record, _)) = scene.hit(&ray, 0.001, f32::INFINITY) {
+                    let material = hit_record.material as *const dyn materials::Material as *const () as usize;
+                    let material_id = match materials.iter().position(|m| *m == material) {
+                        Some(position) => position + 1,
+                        None => {
+                            materials.push(material);
+                            materials.len()
+                        },
+                    };
+
+                    material_ids[index] = material_id as u32;
+                    object_ids[index] = hit_record.object_id;
+                }
+            }
 
             pixel_processed += 1;
             pb.set_position(pixel_processed);
@@ -75,30 +157,412 @@ pub fn render(camera: &Camera, world: &Box<dyn Hitable>, image_width: u32, image
     println!();
     println!("Finished rendering in {}", HumanDuration(start.elapsed()));
 
-    image_data
+    let sample_total = pixel_total as u64 * samples_per_pixel as u64;
+    let percentage = |count: u64| 100.0 * count as f64 / sample_total as f64;
+    if scene.clamp.direct.is_some() || scene.clamp.indirect.is_some() {
+        println!("Clamped samples: {} direct ({:.3}%), {} indirect ({:.3}%)", clamped_direct, percentage(clamped_direct), clamped_indirect, percentage(clamped_indirect));
+    }
+    if scene.outlier_rejection.is_some() {
+        println!("Rejected outliers: {} ({:.3}%)", rejected, percentage(rejected));
+    }
+
+    let mut aovs = film.aovs().unwrap_or_else(|| AovImages::new(image_width, image_height));
+    aovs.material_id = material_ids;
+    aovs.object_id = object_ids;
+
+    (film.images(1.0 / samples_per_pixel as f32), aovs)
+}
+
+// what a path has gone through before a ray, to split its light for the
+// AOVs: the number of bounces and the part of the first bounce that was
+// diffuse
+#[derive(Clone, Copy)]
+struct PathState {
+    bounces: u32,
+    diffuse: f32,
+}
+
+impl PathState {
+    const CAMERA: PathState = PathState { bounces: 0, diffuse: 0.0 };
+}
+
+// everything one camera sample adds to the outputs, the AOVs are only
+// recorded with the camera and image size to project the first hit features.
+// Direct and indirect light are kept apart until the sample is finished so
+// they can be clamped on their own.
+struct SampleRadiance<'a> {
+    groups: GroupedRadiance<'a>,
+    direct: GroupedRadiance<'a>,
+    indirect: GroupedRadiance<'a>,
+    // luminance of the clamped direct and indirect light, all but the
+    // emission seen directly, to tell outliers apart
+    scattered: f32,
+    aovs: AovSample,
+    features: Option<(&'a Camera, u32, u32)>,
+}
+
+impl<'a> SampleRadiance<'a> {
+    fn new(features: Option<(&'a Camera, u32, u32)>) -> Self {
+        SampleRadiance {
+            groups: GroupedRadiance::new(),
+            direct: GroupedRadiance::new(),
+            indirect: GroupedRadiance::new(),
+            scattered: 0.0,
+            aovs: AovSample::new(),
+            features,
+        }
+    }
+
+    // light emitted by whatever the last ray of `path` reached
+    fn add_emitted(&mut self, group: Option<&'a str>, path: PathState, color: Color) {
+        match path.bounces {
+            0 => self.groups.add(group, color),
+            1 => self.direct.add(group, color),
+            _ => self.indirect.add(group, color),
+        }
+
+        if self.features.is_none() {
+            return;
+        }
+
+        match path.bounces {
+            0 => {},
+            1 => self.split(Aov::DirectDiffuse, Aov::DirectSpecular, path.diffuse, color),
+            _ => self.split(Aov::IndirectDiffuse, Aov::IndirectSpecular, path.diffuse, color),
+        }
+    }
+
+    // light sampled directly at the end of `path`, `diffuse` gives the
+    // diffuse part of the reflection there
+    fn add_direct(&mut self, group: Option<&'a str>, path: PathState, diffuse: impl FnOnce() -> f32, color: Color) {
+        if path.bounces == 0 {
+            self.direct.add(group, color);
+        } else {
+            self.indirect.add(group, color);
+        }
+
+        if self.features.is_none() {
+            return;
+        }
+
+        if path.bounces == 0 {
+            self.split(Aov::DirectDiffuse, Aov::DirectSpecular, diffuse(), color);
+        } else {
+            self.split(Aov::IndirectDiffuse, Aov::IndirectSpecular, path.diffuse, color);
+        }
+    }
+
+    // adds the direct and indirect light to the groups, scaled down to the
+    // limits of `clamp`. Returns whether each of them was clamped.
+    fn finish(&mut self, clamp: &SampleClamp) -> (bool, bool) {
+        let scale = |radiance: &GroupedRadiance, limit: Option<f32>| {
+            let total = radiance.total();
+            let brightest = total.r.max(total.g).max(total.b);
+
+            match limit {
+                Some(limit) if brightest > limit => limit / brightest,
+                _ => 1.0,
+            }
+        };
+        let (direct_scale, indirect_scale) = (scale(&self.direct, clamp.direct), scale(&self.indirect, clamp.indirect));
+
+        let direct = std::mem::take(&mut self.direct).scaled(direct_scale);
+        let indirect = std::mem::take(&mut self.indirect).scaled(indirect_scale);
+        self.scattered = (direct.total() + indirect.total()).luminance();
+        self.groups = std::mem::take(&mut self.groups).merge(direct).merge(indirect);
+
+        for (aov, scale) in [(Aov::DirectDiffuse, direct_scale), (Aov::DirectSpecular, direct_scale), (Aov::IndirectDiffuse, indirect_scale), (Aov::IndirectSpecular, indirect_scale)].iter() {
+            self.aovs.set(*aov, *scale * self.aovs.get(*aov));
+        }
+
+        (direct_scale < 1.0, indirect_scale < 1.0)
+    }
+
+    fn split(&mut self, diffuse_aov: Aov, specular_aov: Aov, diffuse: f32, color: Color) {
+        self.aovs.add(diffuse_aov, diffuse * color);
+        self.aovs.add(specular_aov, (1.0 - diffuse) * color);
+    }
+
+    fn first_hit(&mut self, ray: &Ray, hit_record: &HitRecord) {
+        let (camera, width, height) = match self.features {
+            Some(features) => features,
+            None => return,
+        };
+
+        let depth = camera.depth(&hit_record.p);
+        let (p, n) = (hit_record.p, hit_record.shading_normal);
+
+        // the surface at the start and the end of the shutter interval
+        let start = camera.project(&(p + (camera.time0 - ray.time) * hit_record.velocity));
+        let end = camera.project(&(p + (camera.time1 - ray.time) * hit_record.velocity));
+        let motion = match (start, end) {
+            (Some((s0, t0)), Some((s1, t1))) => Color::new((s1 - s0) * (width as f32 - 1.0), (t0 - t1) * (height as f32 - 1.0), 0.0),
+            _ => BLACK,
+        };
+
+        self.aovs.set(Aov::Depth, Color::new(depth, depth, depth));
+        self.aovs.set(Aov::Position, Color::new(p.x, p.y, p.z));
+        self.aovs.set(Aov::Normal, Color::new(n.x, n.y, n.z));
+        self.aovs.set(Aov::Albedo, hit_record.material.albedo(ray, hit_record));
+        self.aovs.set(Aov::Uv, Color::new(hit_record.u, hit_record.v, 0.0));
+        self.aovs.set(Aov::Motion, motion);
+    }
 }
 
-fn ray_color(ray: &Ray, world: &Box<dyn Hitable>, depth: u32, background_color: Color) -> Color {
-    match world.hit(ray, 0.001, INFINITY) {
-        Some(hit_record) => {
+// adds the light reaching the camera along `ray` to `radiance`, scaled by
+// the `throughput` of the `path` so far. `bsdf_pdf` is the density the ray
+// was scattered with when the lights and the environment were also sampled
+// directly at its origin, to weight the strategies with multiple importance
+// sampling.
+fn ray_color<'a>(ray: &Ray, scene: &'a Scene, depth: u32, bsdf_pdf: Option<f32>, throughput: Color, path: PathState, radiance: &mut SampleRadiance<'a>) {
+    match scene.hit(ray, 0.001, f32::INFINITY) {
+        Some((hit_record, light)) => {
+
+            if path.bounces == 0 {
+                radiance.first_hit(ray, &hit_record);
+            }
 
             let emitted = hit_record.material.emitted(hit_record.u, hit_record.v, &hit_record);
+            let emitted = match (light, bsdf_pdf) {
+                (Some(i), Some(bsdf_pdf)) => power_heuristic(bsdf_pdf, scene.lights[i].pdf(&ray.origin, &hit_record)) * emitted,
+                _ => emitted,
+            };
+            radiance.add_emitted(hit_record.material.light_group(), path, throughput * emitted);
 
             if depth == 0 {
-                return emitted;
+                return;
             }
 
-            match hit_record.material.scatter(ray, &hit_record) {
-                Some((attenuation, scattered)) => emitted + attenuation * ray_color(&scattered, world, depth - 1, background_color),
-                None => emitted
+            sample_lights(ray, &hit_record, scene, throughput, path, radiance);
+            sample_environment(ray, &hit_record, scene, throughput, path, radiance);
+
+            if let Some(scatter_record) = hit_record.material.scatter(ray, &hit_record) {
+                let (attenuation, scattered) = (scatter_record.attenuation, scatter_record.ray);
+                let next = PathState {
+                    bounces: path.bounces + 1,
+                    diffuse: if path.bounces == 0 && radiance.features.is_some() {
+                        hit_record.material.diffuse_fraction(ray, &hit_record, &scattered.direction)
+                    } else {
+                        path.diffuse
+                    },
+                };
+
+                ray_color(&scattered, scene, depth - 1, scatter_record.pdf, throughput * attenuation, next, radiance);
             }
         },
-        None => background_color,
+        None => if let Some(environment) = &scene.environment {
+            let color = environment.radiance(&ray.direction);
+            let color = match bsdf_pdf {
+                Some(bsdf_pdf) => power_heuristic(bsdf_pdf, environment.pdf(&ray.direction)) * color,
+                None => color,
+            };
+
+            radiance.add_emitted(environment.group(), path, throughput * color);
+        },
+    }
+}
+
+// light from a sampled direction of the environment, the material must be
+// evaluable for the environment to be sampled
+fn sample_environment<'a>(ray: &Ray, hit_record: &HitRecord, scene: &'a Scene, throughput: Color, path: PathState, radiance: &mut SampleRadiance<'a>) {
+    let environment = match &scene.environment {
+        Some(environment) => environment,
+        None => return,
+    };
+    let (direction, light, light_pdf) = match environment.sample() {
+        Some(sample) => sample,
+        None => return,
+    };
+    let (bsdf, bsdf_pdf) = match hit_record.material.evaluate(ray, hit_record, &direction) {
+        Some(evaluated) => evaluated,
+        None => return,
+    };
+
+    if light_pdf <= 0.0 || bsdf.luminance() <= 0.0 {
+        return;
+    }
+
+    let shadow_ray = Ray::new(hit_record.p, direction, ray.time);
+    if scene.hit(&shadow_ray, 0.001, f32::INFINITY).is_some() {
+        return;
+    }
+
+    let color = (power_heuristic(light_pdf, bsdf_pdf) / light_pdf) * (bsdf * light);
+    radiance.add_direct(environment.group(), path, || hit_record.material.diffuse_fraction(ray, hit_record, &direction), throughput * color);
+}
+
+// light from every light of the scene that isn't occluded, lights with a
+// surface weighted against reaching them by scattering
+fn sample_lights<'a>(ray: &Ray, hit_record: &HitRecord, scene: &'a Scene, throughput: Color, path: PathState, radiance: &mut SampleRadiance<'a>) {
+    for light in scene.lights.iter() {
+        let sample = match light.sample(&hit_record.p) {
+            Some(sample) => sample,
+            None => continue,
+        };
+        let (bsdf, bsdf_pdf) = match hit_record.material.evaluate(ray, hit_record, &sample.direction) {
+            Some((bsdf, bsdf_pdf)) if bsdf.luminance() > 0.0 => (bsdf, bsdf_pdf),
+            _ => continue,
+        };
+        let weight = if sample.pdf > 0.0 { power_heuristic(sample.pdf, bsdf_pdf) } else { 1.0 };
+
+        let shadow_ray = Ray::new(hit_record.p, sample.direction, ray.time);
+        if scene.hit(&shadow_ray, 0.001, sample.distance - 0.001).is_none() {
+            let color = weight * (bsdf * sample.irradiance);
+            radiance.add_direct(light.group(), path, || hit_record.material.diffuse_fraction(ray, hit_record, &sample.direction), throughput * color);
+        }
     }
 }
 
-fn average_samples(color: Color, samples_per_pixel: u32) -> Color {
-    let scale = 1.0 / samples_per_pixel as f32;
+fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+
+    if a + b > 0.0 { a / (a + b) } else { 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::sync::Arc;
+    use crate::environment::UniformEnvironment;
+    use crate::hitable::HitableList;
+    use crate::lights::PointLight;
+    use crate::lights::area::AreaLight;
+    use crate::materials::{Material, Lambertian, DiffuseLight, Dielectric};
+    use crate::materials::layered::Layered;
+    use crate::materials::principled::{Principled, constant};
+    use crate::materials::rough_dielectric::RoughDielectric;
+    use crate::objects::rect::XyRect;
+    use crate::objects::sphere::Sphere;
+    use crate::structures::vec3::Vec3;
+    use crate::texture::SolidColor;
+
+    // the light a point light right above reaches the origin of the z = 0
+    // plane with when it is made of `material`
+    fn lit_by_point_light(material: &dyn Material) -> Color {
+        let mut scene = Scene::new(HitableList::default());
+        scene.lights.push(Box::new(PointLight::new(Vec3::new(0.0, 0.0, 1.0), WHITE, 1.0)));
+
+        let ray = Ray::new(Vec3::new(0.0, -1.0, 1.0), Vec3::new(0.0, 1.0, -1.0), 0.0);
+        let hit_record = HitRecord::new(Vec3::ZERO, 1.0, &ray, &Vec3::new(0.0, 0.0, 1.0), 0.5, 0.5, material);
+        let mut radiance = SampleRadiance::new(None);
+        sample_lights(&ray, &hit_record, &scene, WHITE, PathState::CAMERA, &mut radiance);
+
+        radiance.direct.total()
+    }
+
+    // the mean luminance of all the light `ray` brings back
+    fn seen_along(ray: &Ray, scene: &Scene, samples: u32) -> f32 {
+        let total: f32 = (0..samples)
+            .map(|_| {
+                let mut radiance = SampleRadiance::new(None);
+                ray_color(ray, scene, 8, None, WHITE, PathState::CAMERA, &mut radiance);
 
-    color * scale
-}
\ No newline at end of file
+                (radiance.groups.total() + radiance.direct.total() + radiance.indirect.total()).luminance()
+            })
+            .sum();
+
+        total / samples as f32
+    }
+
+    #[test]
+    fn coated_diffuse_surfaces_are_lit_by_point_lights() {
+        let base: Arc<dyn Material> = Arc::new(Lambertian::new(SolidColor::new(WHITE)));
+        let bare = lit_by_point_light(base.as_ref());
+        let coated = lit_by_point_light(&Layered::new(base.clone(), 1.5, WHITE));
+
+        assert!(coated.g > 0.0 && coated.g < bare.g, "{} {}", coated.g, bare.g);
+    }
+
+    #[test]
+    fn lamps_in_a_coat_reflection_are_not_weighted_down() {
+        let black: Arc<dyn Material> = Arc::new(Lambertian::new(SolidColor::new(BLACK)));
+        let coated = || XyRect::new(-10.0, 10.0, -10.0, 10.0, 0.0, Arc::new(Layered::new(black.clone(), 1.5, WHITE)));
+        let lamp = || Sphere::new(Vec3::new(0.0, 1.0, 1.0), 0.2, Arc::new(DiffuseLight::new(SolidColor::new(WHITE))));
+        let ray = Ray::new(Vec3::new(0.0, -1.0, 1.0), Vec3::new(0.0, 1.0, -1.0), 0.0);
+
+        let mut world = HitableList::default();
+        world.push(coated());
+        world.push(lamp());
+        let geometry = seen_along(&ray, &Scene::new(world), 16384);
+
+        let mut world = HitableList::default();
+        world.push(coated());
+        let mut scene = Scene::new(world);
+        scene.lights.push(Box::new(AreaLight::new(lamp())));
+        let light = seen_along(&ray, &scene, 16384);
+
+        assert!(geometry > 0.0 && (light - geometry).abs() < 0.15 * geometry, "{} {}", light, geometry);
+    }
+
+    #[test]
+    fn white_sky_through_glass_stays_white() {
+        let through = |material: Arc<dyn Material>| {
+            let mut world = HitableList::default();
+            world.push(Sphere::new(Vec3::ZERO, 1.0, material));
+            let mut scene = Scene::new(world);
+            scene.environment = Some(Box::new(UniformEnvironment::new(WHITE)));
+
+            seen_along(&Ray::new(Vec3::new(0.3, 0.2, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0), &scene, 4096)
+        };
+
+        let smooth = through(Arc::new(Dielectric::new(1.5)));
+        let rough = through(Arc::new(RoughDielectric::new(1.5, SolidColor::new(Color::new(0.1, 0.1, 0.1)))));
+        let principled = through(Arc::new(Principled {
+            roughness: constant(0.1),
+            transmission: constant(1.0),
+            ior: constant(1.5),
+            ..Principled::new(SolidColor::new(WHITE))
+        }));
+
+        for seen in [rough, principled].iter() {
+            assert!((seen - smooth).abs() < 0.05 * smooth, "{} {}", seen, smooth);
+        }
+    }
+
+    #[test]
+    fn finishing_clamps_direct_and_indirect_light() {
+        let clamp = SampleClamp { direct: Some(2.0), indirect: Some(1.0) };
+        let bounced = |bounces| PathState { bounces, diffuse: 1.0 };
+
+        let mut radiance = SampleRadiance::new(None);
+        radiance.add_emitted(Some("lamp"), PathState::CAMERA, Color::new(50.0, 50.0, 50.0));
+        radiance.add_direct(Some("lamp"), PathState::CAMERA, || 1.0, Color::new(8.0, 4.0, 0.0));
+        radiance.add_emitted(None, bounced(2), Color::new(0.0, 0.0, 3.0));
+
+        assert_eq!(radiance.finish(&clamp), (true, true));
+        // the largest channel is brought down to the limit, light seen
+        // directly is kept
+        let lamp = radiance.groups.groups[0].1;
+        assert!((lamp.r - 52.0).abs() < 1e-4 && (lamp.g - 51.0).abs() < 1e-4 && (lamp.b - 50.0).abs() < 1e-4);
+        assert!((radiance.groups.ungrouped.b - 1.0).abs() < 1e-4);
+        assert!((radiance.scattered - Color::new(2.0, 1.0, 1.0).luminance()).abs() < 1e-4);
+
+        let mut radiance = SampleRadiance::new(None);
+        radiance.add_direct(None, PathState::CAMERA, || 1.0, Color::new(1.0, 1.0, 1.0));
+        assert_eq!(radiance.finish(&clamp), (false, false));
+        assert_eq!(radiance.groups.ungrouped.g, 1.0);
+    }
+
+    #[test]
+    fn environment_light_goes_to_its_group() {
+        let mut scene = Scene::new(HitableList::default());
+        scene.environment = Some(Box::new(UniformEnvironment::new(WHITE).with_group("sky")));
+        let material = Lambertian::new(SolidColor::new(WHITE));
+
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let hit_record = HitRecord::new(Vec3::ZERO, 1.0, &ray, &Vec3::new(0.0, 0.0, 1.0), 0.5, 0.5, &material);
+        let mut radiance = SampleRadiance::new(None);
+
+        // seen directly and sampled from a surface
+        ray_color(&Ray::new(Vec3::ZERO, Vec3::new(0.0, 0.0, 1.0), 0.0), &scene, 0, None, WHITE, PathState::CAMERA, &mut radiance);
+        for _ in 0..64 {
+            sample_environment(&ray, &hit_record, &scene, WHITE, PathState::CAMERA, &mut radiance);
+        }
+
+        for grouped in [&radiance.groups, &radiance.direct].iter() {
+            assert_eq!(grouped.ungrouped.g, 0.0);
+            assert!(grouped.groups.len() == 1 && grouped.groups[0].0 == "sky" && grouped.groups[0].1.g > 0.0);
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::color::{Color, BLACK};
+
+// radiance split by the light group of the emitters it came from, light
+// without a group is kept on its own
+#[derive(Clone)]
+pub struct GroupedRadiance<'a> {
+    pub ungrouped: Color,
+    pub groups: Vec<(&'a str, Color)>,
+}
+
+impl<'a> GroupedRadiance<'a> {
+    pub fn new() -> Self {
+        GroupedRadiance { ungrouped: BLACK, groups: Vec::new() }
+    }
+
+    pub fn add(&mut self, group: Option<&'a str>, color: Color) {
+        let name = match group {
+            Some(name) => name,
+            None => {
+                self.ungrouped = self.ungrouped + color;
+                return;
+            },
+        };
+
+        match self.groups.iter_mut().find(|(group, _)| *group == name) {
+            Some((_, total)) => *total = *total + color,
+            None => self.groups.push((name, color)),
+        }
+    }
+
+    pub fn merge(mut self, other: GroupedRadiance<'a>) -> Self {
+        self.ungrouped = self.ungrouped + other.ungrouped;
+        for (name, color) in other.groups {
+            self.add(Some(name), color);
+        }
+
+        self
+    }
+
+    pub fn scaled(mut self, scale: f32) -> Self {
+        self.ungrouped = scale * self.ungrouped;
+        for (_, color) in self.groups.iter_mut() {
+            *color = scale * *color;
+        }
+
+        self
+    }
+
+    pub fn total(&self) -> Color {
+        self.groups.iter().fold(self.ungrouped, |total, (_, color)| total + *color)
+    }
+}
+
+impl<'a> Default for GroupedRadiance<'a> {
+    fn default() -> Self {
+        GroupedRadiance::new()
+    }
+}
+
+// one image per light group sorted by name and one for the light without a
+// group, with rows from the top, adding up to the beauty image
+pub struct LightGroupImages {
+    pub width: u32,
+    pub height: u32,
+    pub ungrouped: Vec<Color>,
+    pub groups: Vec<(String, Vec<Color>)>,
+}
+
+impl LightGroupImages {
+    pub fn new(width: u32, height: u32) -> Self {
+        LightGroupImages {
+            width,
+            height,
+            ungrouped: vec![BLACK; (width * height) as usize],
+            groups: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, index: usize, radiance: &GroupedRadiance, scale: f32) {
+        self.ungrouped[index] = self.ungrouped[index] + scale * radiance.ungrouped;
+
+        for (name, color) in radiance.groups.iter() {
+            let position = match self.groups.iter().position(|(group, _)| group == name) {
+                Some(position) => position,
+                None => {
+                    let position = self.groups.partition_point(|(group, _)| group.as_str() < *name);
+                    self.groups.insert(position, (name.to_string(), vec![BLACK; self.ungrouped.len()]));
+                    position
+                },
+            };
+
+            let pixels = &mut self.groups[position].1;
+            pixels[index] = pixels[index] + scale * *color;
+        }
+    }
+
+    pub fn beauty(&self) -> Vec<Color> {
+        let mut beauty = self.ungrouped.clone();
+        for (_, pixels) in self.groups.iter() {
+            for (total, color) in beauty.iter_mut().zip(pixels.iter()) {
+                *total = *total + *color;
+            }
+        }
+
+        beauty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn groups_add_up_to_the_beauty_image() {
+        let mut radiance = GroupedRadiance::new();
+        radiance.add(Some("key"), Color::new(1.0, 0.0, 0.0));
+        radiance.add(None, Color::new(0.0, 1.0, 0.0));
+
+        let mut other = GroupedRadiance::new();
+        other.add(Some("fill"), Color::new(0.0, 0.0, 1.0));
+        other.add(Some("key"), Color::new(1.0, 0.0, 0.0));
+        let radiance = radiance.merge(other);
+
+        let mut images = LightGroupImages::new(2, 1);
+        images.add(1, &radiance, 0.5);
+
+        let names: Vec<&str> = images.groups.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["fill", "key"]);
+        assert_eq!(images.groups[1].1[1].r, 1.0);
+
+        let beauty = images.beauty();
+        let total = 0.5 * radiance.total();
+        assert_eq!((beauty[1].r, beauty[1].g, beauty[1].b), (total.r, total.g, total.b));
+        assert_eq!(beauty[0].r, 0.0);
+    }
+}
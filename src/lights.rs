@@ -0,0 +1,309 @@
+pub mod area;
+
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::io::ies::IesProfile;
+use crate::materials::HitRecord;
+use crate::structures::ray::Ray;
+use crate::structures::onb::Onb;
+use crate::structures::vec3::Vec3;
+use crate::utils;
+
+// Lights reached through shadow rays from the points they illuminate. Lights
+// without a surface can't be hit by any other ray. With distances in meters
+// the intensities are radiant intensities in W/sr and the irradiance of
+// directional lights is in W/m^2.
+pub trait Light: Sync + Send {
+    // the light arriving at `p`, None if there is none
+    fn sample(&self, p: &Vec3) -> Option<LightSample>;
+
+    // the surface of the light, which isn't part of the world
+    fn hit(&self, _ray: &Ray, _t_min: f32, _t_max: f32) -> Option<HitRecord<'_>> {
+        None
+    }
+
+    // solid angle density of `sample` picking the hit on the light seen from `p`
+    fn pdf(&self, _p: &Vec3, _hit_record: &HitRecord) -> f32 {
+        0.0
+    }
+
+    // the light group the light is written to, if any
+    fn group(&self) -> Option<&str> {
+        None
+    }
+}
+
+pub struct LightSample {
+    // unit vector from the shaded point towards the light
+    pub direction: Vec3,
+    // up to where shadow rays look for occluders
+    pub distance: f32,
+    // irradiance on a surface facing the light, for lights with a surface an
+    // estimate of it from a single direction
+    pub irradiance: Color,
+    // solid angle density of `direction`, zero for lights without a surface
+    pub pdf: f32,
+}
+
+// emits equally in all directions, or shaped by a photometric profile
+// hanging straight down with `intensity` at its brightest angle
+pub struct PointLight {
+    pub position: Vec3,
+    pub color: Color,
+    pub intensity: f32,
+    pub profile: Option<Arc<IesProfile>>,
+    // where the horizontal angles of the profile start, see `profile_scale`
+    pub azimuth: Vec3,
+    pub group: Option<String>,
+}
+
+impl PointLight {
+    pub fn new(position: Vec3, color: Color, intensity: f32) -> Self {
+        PointLight { position, color, intensity, profile: None, azimuth: Vec3::new(1.0, 0.0, 0.0), group: None }
+    }
+
+    // from the radiant flux in W
+    pub fn from_power(position: Vec3, color: Color, power: f32) -> Self {
+        PointLight::new(position, color, power / (4.0 * PI))
+    }
+
+    // the horizontal angle 0 of the profile points towards `azimuth`
+    pub fn with_profile(self, profile: Arc<IesProfile>, azimuth: Vec3) -> Self {
+        PointLight { profile: Some(profile), azimuth, ..self }
+    }
+
+    pub fn with_group(self, group: &str) -> Self {
+        PointLight { group: Some(group.to_string()), ..self }
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, p: &Vec3) -> Option<LightSample> {
+        let scale = match &self.profile {
+            Some(profile) => profile_scale(profile, &Vec3::new(0.0, -1.0, 0.0), &self.azimuth, &(*p - self.position)),
+            None => 1.0,
+        };
+
+        if scale <= 0.0 {
+            return None;
+        }
+
+        inverse_square(p, &self.position, (scale * self.intensity) * self.color)
+    }
+
+    fn group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+}
+
+// emits into a cone of `angle` degrees around `direction`, fading out over
+// the outer `falloff` fraction of the cone. A photometric profile is
+// pointed along `direction` and further shapes the light in the cone.
+pub struct SpotLight {
+    pub position: Vec3,
+    pub direction: Vec3,
+    pub color: Color,
+    pub intensity: f32,
+    pub angle: f32,
+    pub falloff: f32,
+    pub profile: Option<Arc<IesProfile>>,
+    pub azimuth: Vec3,
+    pub group: Option<String>,
+}
+
+impl SpotLight {
+    pub fn new(position: Vec3, direction: Vec3, color: Color, intensity: f32, angle: f32) -> Self {
+        let azimuth = Onb::from_w(&direction).u;
+
+        SpotLight { position, direction: direction.normalize(), color, intensity, angle, falloff: 0.2, profile: None, azimuth, group: None }
+    }
+
+    pub fn with_falloff(self, falloff: f32) -> Self {
+        SpotLight { falloff: utils::clamp(falloff, 0.0, 1.0), ..self }
+    }
+
+    // the horizontal angle 0 of the profile points towards `azimuth`
+    pub fn with_profile(self, profile: Arc<IesProfile>, azimuth: Vec3) -> Self {
+        SpotLight { profile: Some(profile), azimuth, ..self }
+    }
+
+    pub fn with_group(self, group: &str) -> Self {
+        SpotLight { group: Some(group.to_string()), ..self }
+    }
+
+    // fraction of the intensity emitted along `direction`, pointing away from the light
+    fn cone(&self, direction: &Vec3) -> f32 {
+        let half_angle = 0.5 * self.angle.to_radians();
+        let cos_outer = half_angle.cos();
+        let cos_inner = (half_angle * (1.0 - self.falloff)).cos();
+        let cos_theta = direction.dot(&self.direction);
+
+        if cos_theta >= cos_inner {
+            1.0
+        } else if cos_theta <= cos_outer {
+            0.0
+        } else {
+            let t = (cos_theta - cos_outer) / (cos_inner - cos_outer);
+            t * t * (3.0 - 2.0 * t)
+        }
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, p: &Vec3) -> Option<LightSample> {
+        let emitted = *p - self.position;
+        let cone = self.cone(&emitted.normalize());
+        let scale = match &self.profile {
+            Some(profile) if cone > 0.0 => cone * profile_scale(profile, &self.direction, &self.azimuth, &emitted),
+            _ => cone,
+        };
+
+        if scale <= 0.0 {
+            return None;
+        }
+
+        inverse_square(p, &self.position, (scale * self.intensity) * self.color)
+    }
+
+    fn group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+}
+
+// parallel light from infinitely far away travelling along `direction`
+pub struct DirectionalLight {
+    pub direction: Vec3,
+    pub color: Color,
+    pub irradiance: f32,
+    pub group: Option<String>,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3, color: Color, irradiance: f32) -> Self {
+        DirectionalLight { direction: direction.normalize(), color, irradiance, group: None }
+    }
+
+    pub fn with_group(self, group: &str) -> Self {
+        DirectionalLight { group: Some(group.to_string()), ..self }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _p: &Vec3) -> Option<LightSample> {
+        Some(LightSample {
+            direction: -self.direction,
+            distance: f32::INFINITY,
+            irradiance: self.irradiance * self.color,
+            pdf: 0.0,
+        })
+    }
+
+    fn group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+}
+
+// the profile relative to its maximum along `emitted`, with the 0 degree
+// vertical angle along `axis` and the 0 degree horizontal angle towards
+// `azimuth`. Horizontal angles turn from there like the u axis of an `Onb`
+// turns into its v axis.
+fn profile_scale(profile: &IesProfile, axis: &Vec3, azimuth: &Vec3, emitted: &Vec3) -> f32 {
+    if profile.max_candela() <= 0.0 {
+        return 0.0;
+    }
+
+    let local = profile_frame(axis, azimuth).to_local(&emitted.normalize());
+    let vertical = utils::clamp(local.z, -1.0, 1.0).acos().to_degrees();
+    let horizontal = local.y.atan2(local.x).to_degrees();
+
+    profile.candela(vertical, horizontal) / profile.max_candela()
+}
+
+// `azimuth` made perpendicular to `axis`, any direction will do when it is
+// along the axis
+fn profile_frame(axis: &Vec3, azimuth: &Vec3) -> Onb {
+    let w = axis.normalize();
+    let u = *azimuth - azimuth.dot(&w) * w;
+
+    if u.near_zero() {
+        return Onb::from_w(axis);
+    }
+
+    let u = u.normalize();
+
+    Onb { u, v: w.cross(&u), w }
+}
+
+fn inverse_square(p: &Vec3, position: &Vec3, intensity: Color) -> Option<LightSample> {
+    let to_light = *position - *p;
+    let distance_squared = to_light.length_squared();
+
+    if distance_squared == 0.0 {
+        return None;
+    }
+
+    let distance = distance_squared.sqrt();
+
+    Some(LightSample {
+        direction: to_light / distance,
+        distance,
+        irradiance: (1.0 / distance_squared) * intensity,
+        pdf: 0.0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::color::WHITE;
+
+    #[test]
+    fn point_lights_fall_off_with_the_square_of_the_distance() {
+        let light = PointLight::from_power(Vec3::new(0.0, 2.0, 0.0), WHITE, 4.0 * PI);
+        let sample = light.sample(&Vec3::new(0.0, -2.0, 0.0)).unwrap();
+
+        assert!((sample.irradiance.g - 1.0 / 16.0).abs() < 0.0001);
+        assert!((sample.distance - 4.0).abs() < 0.0001);
+        assert!(sample.direction.y > 0.9999);
+    }
+
+    #[test]
+    fn spot_lights_only_light_their_cone() {
+        let light = SpotLight::new(Vec3::ZERO, Vec3::new(0.0, -1.0, 0.0), WHITE, 1.0, 60.0).with_falloff(0.5);
+
+        assert_eq!(light.sample(&Vec3::new(0.0, -1.0, 0.0)).unwrap().irradiance.r, 1.0);
+        assert!(light.sample(&Vec3::new(1.0, -1.0, 0.0)).is_none());
+
+        let edge = light.sample(&Vec3::new(0.45, -1.0, 0.0)).unwrap().irradiance.r;
+        assert!(edge > 0.0 && edge < 1.0 / 1.2);
+    }
+
+    #[test]
+    fn profiles_shape_point_lights() {
+        let profile = IesProfile::parse(include_str!("../files/ies/downlight.ies")).unwrap();
+        let light = PointLight::new(Vec3::ZERO, WHITE, 1.0).with_profile(Arc::new(profile), Vec3::new(1.0, 0.0, 0.0));
+
+        assert!((light.sample(&Vec3::new(0.0, -1.0, 0.0)).unwrap().irradiance.r - 1.0).abs() < 0.0001);
+        assert!((light.sample(&Vec3::new(1.0, -3.0f32.sqrt(), 0.0)).unwrap().irradiance.r - 0.8 / 4.0).abs() < 0.0001);
+        assert!(light.sample(&Vec3::new(0.0, 1.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn profiles_turn_with_their_azimuth() {
+        // brightest at 90 degrees, half as bright at 0 and 180
+        let profile = Arc::new(IesProfile::parse(include_str!("../files/ies/streetlight.ies")).unwrap());
+        let irradiance = |azimuth: Vec3, p: Vec3| {
+            PointLight::new(Vec3::ZERO, WHITE, 1.0).with_profile(profile.clone(), azimuth).sample(&p).unwrap().irradiance.r
+        };
+
+        // 90 degrees is towards +z from +x and towards -x from +z
+        assert!((irradiance(Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, -1.0, 1.0)) - 0.5).abs() < 0.0001);
+        assert!((irradiance(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, -1.0, 1.0)) - 0.25).abs() < 0.0001);
+        assert!((irradiance(Vec3::new(0.0, 0.0, 1.0), Vec3::new(-1.0, -1.0, 0.0)) - 0.5).abs() < 0.0001);
+        // only the part across the axis counts
+        assert!((irradiance(Vec3::new(0.0, 5.0, 1.0), Vec3::new(-1.0, -1.0, 0.0)) - 0.5).abs() < 0.0001);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::hitable::Surface;
+use crate::lights::{Light, LightSample};
+use crate::materials::HitRecord;
+use crate::random::random_double;
+use crate::structures::distribution::Distribution2D;
+use crate::structures::ray::Ray;
+use crate::structures::vec3::Vec3;
+
+// cells per side of the grid the emission is importance sampled on
+const RESOLUTION: usize = 64;
+
+// a surface lit by its own emissive material, e.g. a `DiffuseLight`. Points
+// are picked in proportion to the emitted luminance, so textured emitters
+// are sampled where they are bright. The surface must not also be added to
+// the world, the scene intersects it through the light. The light group is
+// the one of the material at the center of the surface.
+pub struct AreaLight<S: Surface> {
+    surface: S,
+    distribution: Distribution2D,
+    group: Option<String>,
+}
+
+impl<S: Surface> AreaLight<S> {
+    pub fn new(surface: S) -> Self {
+        let mut function = Vec::with_capacity(RESOLUTION * RESOLUTION);
+
+        for j in 0..RESOLUTION {
+            for i in 0..RESOLUTION {
+                // 2x2 stratified probes of the emission in every cell
+                let power: f32 = [(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)].iter()
+                    .map(|(du, dv)| {
+                        let u = (i as f32 + du) / RESOLUTION as f32;
+                        let v = (j as f32 + dv) / RESOLUTION as f32;
+
+                        emitted_luminance(&surface, u, v) * surface.point_at(u, v).2
+                    })
+                    .sum();
+
+                function.push(0.25 * power);
+            }
+        }
+
+        // features of the texture smaller than a cell can be missed by the
+        // probes, a small floor keeps them reachable
+        let floor = 0.01 * function.iter().sum::<f32>() / function.len() as f32;
+        let function: Vec<f32> = function.iter().map(|value| value + floor).collect();
+
+        let group = probe(&surface, 0.5, 0.5).iter()
+            .find_map(|hit_record| hit_record.material.light_group().map(String::from));
+
+        AreaLight { surface, distribution: Distribution2D::new(&function, RESOLUTION), group }
+    }
+}
+
+impl<S: Surface> Light for AreaLight<S> {
+    fn sample(&self, p: &Vec3) -> Option<LightSample> {
+        let ((u, v), _) = self.distribution.sample(random_double(), random_double());
+        let (point, _, _) = self.surface.point_at(u, v);
+        let to_light = point - *p;
+        let distance = to_light.length();
+
+        if distance <= 0.0 {
+            return None;
+        }
+
+        // the near side of a closed surface hides the points behind it
+        let direction = to_light / distance;
+        let hit_record = self.surface.hit(&Ray::new(*p, direction, 0.0), 0.001, 1.001 * distance)?;
+        if hit_record.t < 0.999 * distance {
+            return None;
+        }
+
+        let radiance = hit_record.material.emitted(hit_record.u, hit_record.v, &hit_record);
+        let pdf = self.pdf(p, &hit_record);
+
+        if pdf <= 0.0 || radiance.luminance() <= 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            direction,
+            distance: hit_record.t,
+            irradiance: (1.0 / pdf) * radiance,
+            pdf,
+        })
+    }
+
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        self.surface.hit(ray, t_min, t_max)
+    }
+
+    fn pdf(&self, p: &Vec3, hit_record: &HitRecord) -> f32 {
+        let (_, _, area) = self.surface.point_at(hit_record.u, hit_record.v);
+        let to_light = hit_record.p - *p;
+        let cos_theta = hit_record.normal.dot(&to_light.normalize()).abs();
+
+        if area <= 0.0 || cos_theta <= 0.0 {
+            return 0.0;
+        }
+
+        // from a density over (u, v) to one over solid angle
+        self.distribution.pdf(hit_record.u, hit_record.v) * to_light.length_squared() / (area * cos_theta)
+    }
+
+    fn group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+}
+
+// the brighter side of the surface at (u, v)
+fn emitted_luminance<S: Surface>(surface: &S, u: f32, v: f32) -> f32 {
+    probe(surface, u, v).iter()
+        .map(|hit_record| hit_record.material.emitted(hit_record.u, hit_record.v, hit_record).luminance())
+        .fold(0.0, f32::max)
+}
+
+// hits on the surface at (u, v) from just in front of and behind the point
+fn probe<S: Surface>(surface: &S, u: f32, v: f32) -> Vec<HitRecord<'_>> {
+    let (p, normal, _) = surface.point_at(u, v);
+    let offset = 0.001 * p.length().max(1.0);
+
+    [normal, -normal].iter()
+        .filter_map(|side| surface.hit(&Ray::new(p + offset * *side, -*side, 0.0), 0.5 * offset, 1.5 * offset))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::f32::consts::PI;
+    use std::sync::Arc;
+    use crate::color::{Color, BLACK, WHITE};
+    use crate::materials::DiffuseLight;
+    use crate::objects::rect::XzRect;
+    use crate::objects::sphere::Sphere;
+    use crate::texture::{SolidColor, Texture};
+
+    // bright on the u < 0.5 half
+    struct HalfTexture;
+
+    impl Texture for HalfTexture {
+        fn color(&self, u: f32, _v: f32, _p: &Vec3) -> Color {
+            if u < 0.5 { WHITE } else { BLACK }
+        }
+    }
+
+    #[test]
+    fn textured_emitters_are_sampled_where_they_are_bright() {
+        let light = AreaLight::new(XzRect::new(-1.0, 1.0, -1.0, 1.0, 1.0, Arc::new(DiffuseLight::new(HalfTexture))));
+        let p = Vec3::ZERO;
+
+        let bright = (0..1000).filter_map(|_| light.sample(&p)).filter(|sample| sample.direction.x < 0.0).count();
+        assert!(bright > 980);
+
+        let sample = light.sample(&p).unwrap();
+        let hit_record = light.hit(&Ray::new(p, sample.direction, 0.0), 0.001, f32::INFINITY).unwrap();
+        assert!((light.pdf(&p, &hit_record) - sample.pdf).abs() < 0.001 * sample.pdf);
+    }
+
+    #[test]
+    fn spheres_deliver_their_irradiance() {
+        // a uniform sphere of radiance 1 subtending half angle a gives pi sin^2(a)
+        let light = AreaLight::new(Sphere::new(Vec3::new(0.0, 2.0, 0.0), 1.0, Arc::new(DiffuseLight::new(SolidColor::new(WHITE)))));
+        let p = Vec3::ZERO;
+
+        let n = 20000;
+        let irradiance: f32 = (0..n)
+            .filter_map(|_| light.sample(&p))
+            .map(|sample| sample.irradiance.g * sample.direction.y)
+            .sum::<f32>() / n as f32;
+
+        assert!((irradiance - PI * 0.25).abs() < 0.05 * PI * 0.25);
+    }
+}
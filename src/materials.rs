@@ -1,8 +1,24 @@
+pub mod microfacet;
+pub mod conductor;
+pub mod rough_dielectric;
+pub mod principled;
+pub mod mix;
+pub mod layered;
+pub mod oren_nayar;
+pub mod velvet;
+pub mod thin_film;
+pub mod subsurface;
+pub mod normal_map;
+pub mod alpha_mask;
+
+use std::f32::consts::PI;
+
 use crate::random::random_double;
 use crate::color::{Color, WHITE};
 use crate::structures::ray::Ray;
-use crate::structures::vec3::{Vec3, random_in_unit_sphere};
-use crate::texture::Texture;
+use crate::structures::vec3::{Vec3, random_in_unit_sphere, random_unit_vector};
+use crate::texture::{Texture, TextureFootprint};
+use crate::structures::onb::Onb;
 use crate::color::BLACK;
 
 #[derive(Clone, Copy)]
@@ -12,6 +28,18 @@ pub struct HitRecord<'a> {
     pub u: f32,
     pub v: f32,
     pub normal: Vec3,
+    // normal used by the materials, perturbed by normal and bump maps
+    pub shading_normal: Vec3,
+    // partial derivatives of the position along u and v, zero when the
+    // primitive has no parameterisation
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
+    // width of the ray's footprint at the hit, zero for rays without a spread
+    pub footprint: f32,
+    // movement of the surface per unit of time, zero for static objects
+    pub velocity: Vec3,
+    // set by `ObjectId`, zero for objects without one
+    pub object_id: u32,
     pub front_face: bool,
     pub material: &'a dyn Material,
 }
@@ -19,6 +47,10 @@ pub struct HitRecord<'a> {
 impl<'a> HitRecord<'a> {
     pub fn new(p: Vec3, t: f32, ray: &Ray, outward_normal: &Vec3, u: f32, v: f32, material: &'a dyn Material) -> Self {
         let front_face = ray.direction.dot(outward_normal) < 0.0;
+        let normal = match front_face { 
+            true  =>        Vec3::new(outward_normal.x, outward_normal.y, outward_normal.z),
+            false => -1.0 * Vec3::new(outward_normal.x, outward_normal.y, outward_normal.z)
+        };
 
         HitRecord {
             p,
@@ -26,18 +58,118 @@ impl<'a> HitRecord<'a> {
             u,
             v,
             front_face,
-            normal: match front_face { 
-                true  =>        Vec3::new(outward_normal.x, outward_normal.y, outward_normal.z),
-                false => -1.0 * Vec3::new(outward_normal.x, outward_normal.y, outward_normal.z)
-            },
+            normal,
+            shading_normal: normal,
+            dpdu: Vec3::new(0.0, 0.0, 0.0),
+            dpdv: Vec3::new(0.0, 0.0, 0.0),
+            footprint: ray.spread * t * ray.direction.length(),
+            velocity: Vec3::ZERO,
+            object_id: 0,
             material,
         }
     }
+
+    pub fn with_tangents(self, dpdu: Vec3, dpdv: Vec3) -> Self {
+        HitRecord { dpdu, dpdv, ..self }
+    }
+
+    pub fn with_velocity(self, velocity: Vec3) -> Self {
+        HitRecord { velocity, ..self }
+    }
+
+    // the footprint projected onto the surface, stretched along the ray at
+    // grazing angles, and mapped to texture space through dpdu and dpdv
+    pub fn texture_footprint(&self, ray: &Ray) -> TextureFootprint {
+        if self.footprint <= 0.0 || self.dpdu.near_zero() || self.dpdv.near_zero() {
+            return TextureFootprint::default();
+        }
+
+        let direction = ray.direction.normalize();
+        let cos_theta = direction.dot(&self.normal).abs().max(0.05);
+        let along = direction - direction.dot(&self.normal) * self.normal;
+        let (across, along) = if along.near_zero() {
+            let onb = Onb::from_w(&self.normal);
+            (onb.u, onb.v)
+        } else {
+            let along = along.normalize();
+            (self.normal.cross(&along), along)
+        };
+
+        // least squares solution of w = du * dpdu + dv * dpdv
+        let (uu, uv, vv) = (self.dpdu.dot(&self.dpdu), self.dpdu.dot(&self.dpdv), self.dpdv.dot(&self.dpdv));
+        let inv_determinant = 1.0 / (uu * vv - uv * uv);
+        let to_uv = |w: Vec3| {
+            let (wu, wv) = (w.dot(&self.dpdu), w.dot(&self.dpdv));
+            ((vv * wu - uv * wv) * inv_determinant, (uu * wv - uv * wu) * inv_determinant)
+        };
+
+        let (dudx, dvdx) = to_uv(self.footprint * across);
+        let (dudy, dvdy) = to_uv((self.footprint / cos_theta) * along);
+
+        if !(dudx.is_finite() && dvdx.is_finite() && dudy.is_finite() && dvdy.is_finite()) {
+            return TextureFootprint::default();
+        }
+
+        TextureFootprint { dudx, dvdx, dudy, dvdy }
+    }
+}
+
+// a ray continuing the path from a hit, with its weight, the BSDF times the
+// cosine over the density it was sampled with. `pdf` is that solid angle
+// density when the lights could also have been sampled towards the ray,
+// counting every lobe that `evaluate` covers. It is None for specular lobes
+// like mirrors and refraction, whose light only arrives through the rays
+// they scatter.
+pub struct ScatterRecord {
+    pub attenuation: Color,
+    pub ray: Ray,
+    pub pdf: Option<f32>,
+}
+
+impl ScatterRecord {
+    pub fn new(attenuation: Color, ray: Ray, pdf: f32) -> Self {
+        ScatterRecord { attenuation, ray, pdf: Some(pdf) }
+    }
+
+    pub fn specular(attenuation: Color, ray: Ray) -> Self {
+        ScatterRecord { attenuation, ray, pdf: None }
+    }
 }
 
 pub trait Material: Sync + Send {
-    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Color, Ray)>;
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord>;
     fn emitted(&self, u: f32, v: f32, hit_record: &HitRecord) -> Color;
+
+    // whether the surface is there at all at the hit, primitives skip hits
+    // that fail so cutouts let rays through
+    fn alpha_test(&self, _hit_record: &HitRecord) -> bool {
+        true
+    }
+
+    // the BSDF times the cosine towards `direction` and the solid angle
+    // density `scatter` samples it with, leaving out specular lobes.
+    // Materials returning None, like perfect mirrors, are only lit through
+    // the rays they scatter.
+    fn evaluate(&self, _ray_in: &Ray, _hit_record: &HitRecord, _direction: &Vec3) -> Option<(Color, f32)> {
+        None
+    }
+
+    // the light group the emitted light is written to, if any
+    fn light_group(&self) -> Option<&str> {
+        None
+    }
+
+    // the color of the surface for the albedo output, white for materials
+    // like glass whose color comes from what they reflect
+    fn albedo(&self, _ray_in: &Ray, _hit_record: &HitRecord) -> Color {
+        WHITE
+    }
+
+    // the part of the light scattered from `ray_in` towards `direction`
+    // that goes through diffuse lobes, the rest is specular
+    fn diffuse_fraction(&self, _ray_in: &Ray, _hit_record: &HitRecord, _direction: &Vec3) -> f32 {
+        0.0
+    }
 }
 
 pub struct Lambertian<T: Texture> {
@@ -51,16 +183,38 @@ impl<T: Texture> Lambertian<T> {
 }
 
 impl<T: Texture> Material for Lambertian<T> {
-    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Color, Ray)> {
-        let target = hit_record.p + hit_record.normal + random_in_unit_sphere();
-        let scattered_ray = Ray::new(hit_record.p, target - hit_record.p, ray_in.time);
-        
-        Some((self.albedo.color(hit_record.u, hit_record.v, &hit_record.p), scattered_ray))
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        let scatter_direction = hit_record.shading_normal + random_unit_vector();
+        // the random unit vector can cancel out the normal
+        let scatter_direction = if scatter_direction.near_zero() { hit_record.shading_normal } else { scatter_direction };
+        let scattered_ray = Ray::new(hit_record.p, scatter_direction, ray_in.time);
+        let cos_theta = scatter_direction.normalize().dot(&hit_record.shading_normal).max(0.0);
+
+        let footprint = hit_record.texture_footprint(ray_in);
+        let albedo = self.albedo.color_filtered(hit_record.u, hit_record.v, &hit_record.p, &footprint);
+
+        Some(ScatterRecord::new(albedo, scattered_ray, cos_theta / PI))
     }
 
     fn emitted(&self, _u: f32, _v: f32, _hit_record: &HitRecord) -> Color {
         BLACK
     }
+
+    fn evaluate(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> Option<(Color, f32)> {
+        let cos_theta = direction.normalize().dot(&hit_record.shading_normal).max(0.0);
+        let footprint = hit_record.texture_footprint(ray_in);
+        let albedo = self.albedo.color_filtered(hit_record.u, hit_record.v, &hit_record.p, &footprint);
+
+        Some(((cos_theta / PI) * albedo, cos_theta / PI))
+    }
+
+    fn albedo(&self, ray_in: &Ray, hit_record: &HitRecord) -> Color {
+        self.albedo.color_filtered(hit_record.u, hit_record.v, &hit_record.p, &hit_record.texture_footprint(ray_in))
+    }
+
+    fn diffuse_fraction(&self, _ray_in: &Ray, _hit_record: &HitRecord, _direction: &Vec3) -> f32 {
+        1.0
+    }
 }
 
 pub struct Metal {
@@ -75,12 +229,12 @@ impl Metal {
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Color, Ray)> {
-        let reflected = Vec3::reflect(&ray_in.direction.normalize(), &hit_record.normal);
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        let reflected = Vec3::reflect(&ray_in.direction.normalize(), &hit_record.shading_normal);
         let scattered_ray = Ray::new(hit_record.p, reflected + self.fuzz * random_in_unit_sphere(), ray_in.time);
 
-        if scattered_ray.direction.dot(&hit_record.normal) > 0.0 {
-            Some((self.albedo, scattered_ray))
+        if scattered_ray.direction.dot(&hit_record.shading_normal) > 0.0 {
+            Some(ScatterRecord::specular(self.albedo, scattered_ray))
         } else {
             None
         }
@@ -89,6 +243,10 @@ impl Material for Metal {
     fn emitted(&self, _u: f32, _v: f32, _hit_record: &HitRecord) -> Color {
         BLACK
     }
+
+    fn albedo(&self, _ray_in: &Ray, _hit_record: &HitRecord) -> Color {
+        self.albedo
+    }
 }
 
 pub struct Dielectric {
@@ -102,24 +260,24 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<(Color, Ray)> {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
         let etai_over_etat = if hit_record.front_face { 1.0 / self.ref_idx } else { self.ref_idx };
         let unit_direction = ray_in.direction.normalize();
-        let cos_theta = (-1.0 * unit_direction).dot(&hit_record.normal);
+        let cos_theta = (-1.0 * unit_direction).dot(&hit_record.shading_normal);
         let cos_theta = if cos_theta < 1.0 { cos_theta } else { 1.0 };
         let sin_theta = (1.0 - cos_theta*cos_theta).sqrt();
         let reflect_prob = schlick(cos_theta, etai_over_etat);
 
         if etai_over_etat * sin_theta > 1.0 || random_double() < reflect_prob {
-            let reflected = Vec3::reflect(&unit_direction, &hit_record.normal);
+            let reflected = Vec3::reflect(&unit_direction, &hit_record.shading_normal);
             let scattered_ray = Ray::new(hit_record.p, reflected, ray_in.time);
             
-            Some((WHITE, scattered_ray))
+            Some(ScatterRecord::specular(WHITE, scattered_ray))
         } else {
-            let refracted = Vec3::refract(&unit_direction, &hit_record.normal, etai_over_etat);
+            let refracted = Vec3::refract(&unit_direction, &hit_record.shading_normal, etai_over_etat);
             let scattered_ray = Ray::new(hit_record.p, refracted, ray_in.time);
             
-            Some((WHITE, scattered_ray))
+            Some(ScatterRecord::specular(WHITE, scattered_ray))
         }
     }
 
@@ -134,22 +292,47 @@ fn schlick(cosine: f32, ref_idx: f32) -> f32 {
     r0 + (1.0 - r0)*(1.0 - cosine).powi(5)
 }
 
+// emits `strength` times the color of `emit`, from both sides of the
+// surface unless it is made one sided, then only from the front
 pub struct DiffuseLight<T: Texture> {
-    emit: T
+    emit: T,
+    pub strength: f32,
+    pub two_sided: bool,
+    pub group: Option<String>,
 }
 
 impl<T: Texture> DiffuseLight<T> {
     pub fn new(emit: T) -> Self {
-        DiffuseLight { emit }
+        DiffuseLight { emit, strength: 1.0, two_sided: true, group: None }
+    }
+
+    pub fn with_strength(self, strength: f32) -> Self {
+        DiffuseLight { strength, ..self }
+    }
+
+    pub fn one_sided(self) -> Self {
+        DiffuseLight { two_sided: false, ..self }
+    }
+
+    pub fn with_group(self, group: &str) -> Self {
+        DiffuseLight { group: Some(group.to_string()), ..self }
     }
 }
 
 impl<T: Texture> Material for DiffuseLight<T> {
-    fn scatter(&self, _ray_in: &Ray, _hit_record: &HitRecord) -> Option<(Color, Ray)> {
+    fn scatter(&self, _ray_in: &Ray, _hit_record: &HitRecord) -> Option<ScatterRecord> {
         None
     }
 
     fn emitted(&self, u: f32, v: f32, hit_record: &HitRecord) -> Color {
-        self.emit.color(u, v, &hit_record.p)
+        if !self.two_sided && !hit_record.front_face {
+            return BLACK;
+        }
+
+        self.strength * self.emit.color(u, v, &hit_record.p)
+    }
+
+    fn light_group(&self) -> Option<&str> {
+        self.group.as_deref()
     }
 }
\ No newline at end of file
@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::materials::{Material, HitRecord, ScatterRecord};
+use crate::random::random_double;
+use crate::structures::ray::Ray;
+use crate::structures::vec3::Vec3;
+use crate::texture::Texture;
+
+pub enum AlphaMode {
+    // the surface is cut out where the opacity is below the threshold
+    Threshold(f32),
+    // the surface is hit with a probability equal to the opacity, which
+    // renders partial opacity like the edges of leaves smoothly
+    Stochastic,
+}
+
+// cuts parts of the surface of `material` out, for leaves or fences modelled
+// as textured quads
+pub struct AlphaMask<T: Texture> {
+    pub material: Arc<dyn Material>,
+    pub opacity: T,
+    pub mode: AlphaMode,
+}
+
+impl<T: Texture> AlphaMask<T> {
+    pub fn new(material: Arc<dyn Material>, opacity: T) -> Self {
+        AlphaMask { material, opacity, mode: AlphaMode::Threshold(0.5) }
+    }
+
+    pub fn stochastic(material: Arc<dyn Material>, opacity: T) -> Self {
+        AlphaMask { material, opacity, mode: AlphaMode::Stochastic }
+    }
+}
+
+impl<T: Texture> Material for AlphaMask<T> {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        self.material.scatter(ray_in, hit_record)
+    }
+
+    fn emitted(&self, u: f32, v: f32, hit_record: &HitRecord) -> Color {
+        self.material.emitted(u, v, hit_record)
+    }
+
+    fn evaluate(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> Option<(Color, f32)> {
+        self.material.evaluate(ray_in, hit_record, direction)
+    }
+
+    fn albedo(&self, ray_in: &Ray, hit_record: &HitRecord) -> Color {
+        self.material.albedo(ray_in, hit_record)
+    }
+
+    fn diffuse_fraction(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> f32 {
+        self.material.diffuse_fraction(ray_in, hit_record, direction)
+    }
+
+    fn light_group(&self) -> Option<&str> {
+        self.material.light_group()
+    }
+
+    fn alpha_test(&self, hit_record: &HitRecord) -> bool {
+        let opacity = self.opacity.value(hit_record.u, hit_record.v, &hit_record.p);
+
+        let opaque = match self.mode {
+            AlphaMode::Threshold(threshold) => opacity >= threshold,
+            AlphaMode::Stochastic => random_double() < opacity,
+        };
+
+        opaque && self.material.alpha_test(hit_record)
+    }
+}
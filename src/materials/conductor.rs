@@ -0,0 +1,95 @@
+use crate::color::{Color, BLACK};
+use crate::materials::{Material, HitRecord, ScatterRecord};
+use crate::materials::microfacet::{TrowbridgeReitz, reflect, fresnel_conductor};
+use crate::random::random_double;
+use crate::structures::onb::Onb;
+use crate::structures::ray::Ray;
+use crate::structures::vec3::Vec3;
+use crate::texture::Texture;
+
+// complex indices of refraction sampled at red, green and blue wavelengths
+pub const GOLD_ETA: Color = Color { r: 0.143119, g: 0.374957, b: 1.442479 };
+pub const GOLD_K: Color = Color { r: 3.98316, g: 2.385721, b: 1.603215 };
+pub const COPPER_ETA: Color = Color { r: 0.200438, g: 0.924033, b: 1.102212 };
+pub const COPPER_K: Color = Color { r: 3.912949, g: 2.452848, b: 2.142188 };
+pub const ALUMINIUM_ETA: Color = Color { r: 1.65746, g: 0.880369, b: 0.521229 };
+pub const ALUMINIUM_K: Color = Color { r: 9.223869, g: 6.269523, b: 4.837001 };
+
+pub struct Conductor<T: Texture> {
+    pub eta: Color,
+    pub k: Color,
+    pub roughness: T,
+}
+
+impl<T: Texture> Conductor<T> {
+    pub fn new(eta: Color, k: Color, roughness: T) -> Self {
+        Conductor { eta, k, roughness }
+    }
+
+    pub fn gold(roughness: T) -> Self {
+        Conductor::new(GOLD_ETA, GOLD_K, roughness)
+    }
+
+    pub fn copper(roughness: T) -> Self {
+        Conductor::new(COPPER_ETA, COPPER_K, roughness)
+    }
+
+    pub fn aluminium(roughness: T) -> Self {
+        Conductor::new(ALUMINIUM_ETA, ALUMINIUM_K, roughness)
+    }
+}
+
+impl<T: Texture> Material for Conductor<T> {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        let onb = Onb::from_w(&hit_record.shading_normal);
+        let wo = onb.to_local(&(-ray_in.direction.normalize()));
+
+        if wo.z <= 0.0 {
+            return None;
+        }
+
+        let roughness = self.roughness.value(hit_record.u, hit_record.v, &hit_record.p);
+        let distribution = TrowbridgeReitz::from_roughness(roughness);
+
+        let wh = distribution.sample_visible_normal(&wo, random_double(), random_double());
+        let wi = reflect(&wo, &wh);
+
+        if wi.z <= 0.0 {
+            return None;
+        }
+
+        // with visible normal sampling D and most of G cancel against the pdf
+        let fresnel = fresnel_conductor(wo.dot(&wh), self.eta, self.k);
+        let attenuation = fresnel * (distribution.g(&wo, &wi) / distribution.g1(&wo));
+        let pdf = distribution.pdf(&wo, &wh) / (4.0 * wo.dot(&wh));
+
+        Some(ScatterRecord::new(attenuation, Ray::new(hit_record.p, onb.local(&wi), ray_in.time), pdf))
+    }
+
+    fn emitted(&self, _u: f32, _v: f32, _hit_record: &HitRecord) -> Color {
+        BLACK
+    }
+
+    fn evaluate(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> Option<(Color, f32)> {
+        let onb = Onb::from_w(&hit_record.shading_normal);
+        let wo = onb.to_local(&(-ray_in.direction.normalize()));
+        let wi = onb.to_local(&direction.normalize());
+
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Some((BLACK, 0.0));
+        }
+
+        let roughness = self.roughness.value(hit_record.u, hit_record.v, &hit_record.p);
+        let distribution = TrowbridgeReitz::from_roughness(roughness);
+        let wh = (wo + wi).normalize();
+        let pdf = distribution.pdf(&wo, &wh) / (4.0 * wo.dot(&wh));
+        let fresnel = fresnel_conductor(wo.dot(&wh), self.eta, self.k);
+
+        Some(((pdf * distribution.g(&wo, &wi) / distribution.g1(&wo)) * fresnel, pdf))
+    }
+
+    // the reflectance at normal incidence
+    fn albedo(&self, _ray_in: &Ray, _hit_record: &HitRecord) -> Color {
+        fresnel_conductor(1.0, self.eta, self.k)
+    }
+}
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use crate::color::{Color, WHITE};
+use crate::materials::{Material, HitRecord, ScatterRecord};
+use crate::materials::microfacet::fresnel_dielectric;
+use crate::random::random_double;
+use crate::structures::ray::Ray;
+use crate::structures::vec3::Vec3;
+
+// a thin smooth dielectric coat, like varnish or lacquer, on top of any
+// material. Light either reflects off the coat or passes through it twice,
+// once on the way in and once on the way out, and is tinted by the coat
+// color depending on the distance travelled inside the coat.
+pub struct Layered {
+    pub base: Arc<dyn Material>,
+    pub ior: f32,
+    pub coat_color: Color,
+}
+
+impl Layered {
+    pub fn new(base: Arc<dyn Material>, ior: f32, coat_color: Color) -> Self {
+        Layered { base, ior, coat_color }
+    }
+
+    // transmittance through the coat for light entering or leaving at `cos_theta`
+    fn absorption(&self, cos_theta: f32) -> Color {
+        let sin2_t = (1.0 - cos_theta * cos_theta) / (self.ior * self.ior);
+        let path_length = 1.0 / (1.0 - sin2_t).max(0.0001).sqrt();
+
+        Color::new(
+            self.coat_color.r.powf(path_length),
+            self.coat_color.g.powf(path_length),
+            self.coat_color.b.powf(path_length))
+    }
+}
+
+impl Material for Layered {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        let unit_direction = ray_in.direction.normalize();
+        let cos_in = (-unit_direction).dot(&hit_record.shading_normal).max(0.0);
+        let reflect_prob = fresnel_dielectric(cos_in, self.ior);
+
+        // picking the coat with the fresnel probability leaves a unit weight
+        if random_double() < reflect_prob {
+            let reflected = Vec3::reflect(&unit_direction, &hit_record.shading_normal);
+
+            return Some(ScatterRecord::specular(WHITE, Ray::new(hit_record.p, reflected, ray_in.time)));
+        }
+
+        let scatter_record = self.base.scatter(ray_in, hit_record)?;
+        let cos_out = scatter_record.ray.direction.normalize().dot(&hit_record.shading_normal).abs();
+
+        let coat = (1.0 - fresnel_dielectric(cos_out, self.ior))
+            * (self.absorption(cos_in) * self.absorption(cos_out));
+
+        Some(ScatterRecord {
+            attenuation: coat * scatter_record.attenuation,
+            pdf: scatter_record.pdf.map(|pdf| (1.0 - reflect_prob) * pdf),
+            ..scatter_record
+        })
+    }
+
+    fn emitted(&self, u: f32, v: f32, hit_record: &HitRecord) -> Color {
+        self.base.emitted(u, v, hit_record)
+    }
+
+    // the smooth coat is only reached by the rays it reflects, the base is
+    // seen through it and sampled when the coat isn't picked
+    fn evaluate(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> Option<(Color, f32)> {
+        let (value, pdf) = self.base.evaluate(ray_in, hit_record, direction)?;
+
+        let cos_in = (-ray_in.direction.normalize()).dot(&hit_record.shading_normal).max(0.0);
+        let cos_out = direction.normalize().dot(&hit_record.shading_normal).abs();
+        let entering = 1.0 - fresnel_dielectric(cos_in, self.ior);
+
+        let coat = (entering * (1.0 - fresnel_dielectric(cos_out, self.ior)))
+            * (self.absorption(cos_in) * self.absorption(cos_out));
+
+        Some((coat * value, entering * pdf))
+    }
+
+    fn alpha_test(&self, hit_record: &HitRecord) -> bool {
+        self.base.alpha_test(hit_record)
+    }
+
+    fn light_group(&self) -> Option<&str> {
+        self.base.light_group()
+    }
+
+    fn albedo(&self, ray_in: &Ray, hit_record: &HitRecord) -> Color {
+        self.coat_color * self.base.albedo(ray_in, hit_record)
+    }
+
+    // the mirror direction of the coat is specular, anything else comes from the base
+    fn diffuse_fraction(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> f32 {
+        let reflected = Vec3::reflect(&ray_in.direction.normalize(), &hit_record.shading_normal);
+
+        if reflected.dot(&direction.normalize()) > 0.9999 {
+            0.0
+        } else {
+            self.base.diffuse_fraction(ray_in, hit_record, direction)
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::f32::consts::PI;
+
+use crate::color::Color;
+use crate::structures::vec3::Vec3;
+use crate::utils;
+
+// smallest alpha used for sampling, below this the distribution becomes a
+// numerically unstable dirac peak
+const MIN_ALPHA: f32 = 0.001;
+
+// GGX / Trowbridge-Reitz microfacet distribution with Smith masking. All
+// directions are expected in the local shading frame where the normal is +z.
+#[derive(Copy, Clone)]
+pub struct TrowbridgeReitz {
+    pub alpha: f32,
+}
+
+impl TrowbridgeReitz {
+    pub fn new(alpha: f32) -> Self {
+        TrowbridgeReitz { alpha: alpha.max(MIN_ALPHA) }
+    }
+
+    // maps the artist facing roughness in [0, 1] to alpha
+    pub fn from_roughness(roughness: f32) -> Self {
+        let roughness = utils::clamp(roughness, 0.0, 1.0);
+
+        TrowbridgeReitz::new(roughness * roughness)
+    }
+
+    pub fn d(&self, wh: &Vec3) -> f32 {
+        if wh.z <= 0.0 {
+            return 0.0;
+        }
+
+        let alpha2 = self.alpha * self.alpha;
+        let cos2 = wh.z * wh.z;
+        let denom = cos2 * (alpha2 - 1.0) + 1.0;
+
+        alpha2 / (PI * denom * denom)
+    }
+
+    pub fn lambda(&self, w: &Vec3) -> f32 {
+        let cos2 = w.z * w.z;
+        if cos2 == 0.0 {
+            return f32::INFINITY;
+        }
+
+        let tan2 = (w.x * w.x + w.y * w.y) / cos2;
+
+        0.5 * (-1.0 + (1.0 + self.alpha * self.alpha * tan2).sqrt())
+    }
+
+    pub fn g1(&self, w: &Vec3) -> f32 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    // height correlated masking-shadowing
+    pub fn g(&self, wo: &Vec3, wi: &Vec3) -> f32 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    // density of `sample_visible_normal` for the half vector `wh`
+    pub fn pdf(&self, wo: &Vec3, wh: &Vec3) -> f32 {
+        if wo.z == 0.0 {
+            return 0.0;
+        }
+
+        self.g1(wo) * wo.dot(wh).max(0.0) * self.d(wh) / wo.z.abs()
+    }
+
+    // samples a microfacet normal proportional to the normals visible from
+    // `wo`, see Heitz 2018 "Sampling the GGX Distribution of Visible Normals"
+    pub fn sample_visible_normal(&self, wo: &Vec3, u1: f32, u2: f32) -> Vec3 {
+        let flip = wo.z < 0.0;
+        let wo = if flip { -wo } else { *wo };
+
+        let vh = Vec3::new(self.alpha * wo.x, self.alpha * wo.y, wo.z).normalize();
+
+        let lensq = vh.x * vh.x + vh.y * vh.y;
+        let t1 = if lensq > 0.0 {
+            Vec3::new(-vh.y, vh.x, 0.0) / lensq.sqrt()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = vh.cross(&t1);
+
+        let r = u1.sqrt();
+        let phi = 2.0 * PI * u2;
+        let p1 = r * phi.cos();
+        let p2 = r * phi.sin();
+        let s = 0.5 * (1.0 + vh.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * p2;
+
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+        let wh = Vec3::new(self.alpha * nh.x, self.alpha * nh.y, nh.z.max(0.0)).normalize();
+
+        if flip { -wh } else { wh }
+    }
+}
+
+pub fn reflect(wo: &Vec3, wh: &Vec3) -> Vec3 {
+    2.0 * wo.dot(wh) * wh - *wo
+}
+
+// refracts `wo` through the microfacet `wh`, `eta` is the relative index of
+// refraction eta_t / eta_i. Returns None on total internal reflection.
+pub fn refract(wo: &Vec3, wh: &Vec3, eta: f32) -> Option<Vec3> {
+    let cos_i = wo.dot(wh);
+    let sin2_i = (1.0 - cos_i * cos_i).max(0.0);
+    let sin2_t = sin2_i / (eta * eta);
+
+    if sin2_t >= 1.0 {
+        return None;
+    }
+
+    let cos_t = (1.0 - sin2_t).sqrt();
+
+    Some((-wo) / eta + (cos_i / eta - cos_t) * wh)
+}
+
+// (1 - cos)^5 weight of the schlick fresnel approximation
+pub fn schlick_weight(cos_theta: f32) -> f32 {
+    let m = utils::clamp(1.0 - cos_theta, 0.0, 1.0);
+
+    (m * m) * (m * m) * m
+}
+
+// unpolarized fresnel reflectance of a dielectric interface, `eta` is the
+// relative index of refraction eta_t / eta_i
+pub fn fresnel_dielectric(cos_theta_i: f32, eta: f32) -> f32 {
+    let cos_i = utils::clamp(cos_theta_i, 0.0, 1.0);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let rs = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    let rp = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+
+    0.5 * (rs * rs + rp * rp)
+}
+
+// unpolarized fresnel reflectance of a conductor with complex index of
+// refraction eta + ik, evaluated per color channel
+pub fn fresnel_conductor(cos_theta_i: f32, eta: Color, k: Color) -> Color {
+    let cos_i = utils::clamp(cos_theta_i, 0.0, 1.0);
+
+    Color::new(
+        fresnel_conductor_channel(cos_i, eta.r, k.r),
+        fresnel_conductor_channel(cos_i, eta.g, k.g),
+        fresnel_conductor_channel(cos_i, eta.b, k.b),
+    )
+}
+
+fn fresnel_conductor_channel(cos_i: f32, eta: f32, k: f32) -> f32 {
+    let cos2 = cos_i * cos_i;
+    let sin2 = 1.0 - cos2;
+    let eta2 = eta * eta;
+    let k2 = k * k;
+
+    let t0 = eta2 - k2 - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos_i * a;
+    let rs = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+
+    0.5 * (rp + rs)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn visible_normals_face_the_viewer() {
+        let distribution = TrowbridgeReitz::from_roughness(0.7);
+        let wo = Vec3::new(0.6, 0.0, 0.8);
+
+        for i in 0..16 {
+            for j in 0..16 {
+                let wh = distribution.sample_visible_normal(&wo, (i as f32 + 0.5) / 16.0, (j as f32 + 0.5) / 16.0);
+
+                assert!(wh.z >= 0.0);
+                assert!(wo.dot(&wh) >= 0.0);
+                assert!((wh.length() - 1.0).abs() < 0.0001);
+            }
+        }
+    }
+
+    #[test]
+    fn conductor_fresnel_at_normal_incidence() {
+        let (eta, k) = (0.2, 3.9);
+        let expected = ((eta - 1.0) * (eta - 1.0) + k * k) / ((eta + 1.0) * (eta + 1.0) + k * k);
+
+        let f = fresnel_conductor(1.0, Color::new(eta, eta, eta), Color::new(k, k, k));
+
+        assert!((f.r - expected).abs() < 0.0001);
+    }
+
+    #[test]
+    fn dielectric_fresnel() {
+        assert!((fresnel_dielectric(1.0, 1.5) - 0.04).abs() < 0.0001);
+        assert_eq!(fresnel_dielectric(0.1, 1.0 / 1.5), 1.0);
+    }
+}
@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use crate::color::{Color, linear_blend};
+use crate::materials::{Material, HitRecord, ScatterRecord};
+use crate::random::random_double;
+use crate::structures::ray::Ray;
+use crate::structures::vec3::Vec3;
+use crate::texture::Texture;
+use crate::utils;
+
+// blends two materials, a factor of 0 is fully `first` and 1 fully `second`
+pub struct MixMaterial<T: Texture> {
+    pub first: Arc<dyn Material>,
+    pub second: Arc<dyn Material>,
+    pub factor: T,
+}
+
+impl<T: Texture> MixMaterial<T> {
+    pub fn new(first: Arc<dyn Material>, second: Arc<dyn Material>, factor: T) -> Self {
+        MixMaterial { first, second, factor }
+    }
+
+    fn factor(&self, hit_record: &HitRecord) -> f32 {
+        let factor = self.factor.value(hit_record.u, hit_record.v, &hit_record.p);
+
+        utils::clamp(factor, 0.0, 1.0)
+    }
+}
+
+impl<T: Texture> Material for MixMaterial<T> {
+    // picking one material with the mix probability keeps the weight of the
+    // picked material unchanged. A direction from a lobe that isn't specular
+    // could have come from either material, so its density is the blend of
+    // both, and only exists when both can be evaluated.
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        let scatter_record = if random_double() < self.factor(hit_record) {
+            self.second.scatter(ray_in, hit_record)?
+        } else {
+            self.first.scatter(ray_in, hit_record)?
+        };
+
+        let pdf = scatter_record.pdf
+            .and_then(|_| self.evaluate(ray_in, hit_record, &scatter_record.ray.direction))
+            .map(|(_, pdf)| pdf);
+
+        Some(ScatterRecord { pdf, ..scatter_record })
+    }
+
+    fn emitted(&self, u: f32, v: f32, hit_record: &HitRecord) -> Color {
+        linear_blend(
+            self.factor(hit_record),
+            self.first.emitted(u, v, hit_record),
+            self.second.emitted(u, v, hit_record))
+    }
+
+    // only when both materials can be evaluated
+    fn evaluate(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> Option<(Color, f32)> {
+        let factor = self.factor(hit_record);
+        let (first, first_pdf) = self.first.evaluate(ray_in, hit_record, direction)?;
+        let (second, second_pdf) = self.second.evaluate(ray_in, hit_record, direction)?;
+
+        Some((linear_blend(factor, first, second), first_pdf + factor * (second_pdf - first_pdf)))
+    }
+
+    // the surface is there as often as in the blend of the two, by testing
+    // the material picked with the mix probability
+    fn alpha_test(&self, hit_record: &HitRecord) -> bool {
+        if random_double() < self.factor(hit_record) {
+            self.second.alpha_test(hit_record)
+        } else {
+            self.first.alpha_test(hit_record)
+        }
+    }
+
+    // the group of the emitting material, the first one if both emit
+    fn light_group(&self) -> Option<&str> {
+        self.first.light_group().or_else(|| self.second.light_group())
+    }
+
+    fn albedo(&self, ray_in: &Ray, hit_record: &HitRecord) -> Color {
+        linear_blend(self.factor(hit_record), self.first.albedo(ray_in, hit_record), self.second.albedo(ray_in, hit_record))
+    }
+
+    fn diffuse_fraction(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> f32 {
+        let first = self.first.diffuse_fraction(ray_in, hit_record, direction);
+        let second = self.second.diffuse_fraction(ray_in, hit_record, direction);
+
+        first + self.factor(hit_record) * (second - first)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::f32::consts::PI;
+    use crate::color::WHITE;
+    use crate::materials::{Lambertian, DiffuseLight};
+    use crate::materials::alpha_mask::AlphaMask;
+    use crate::materials::layered::Layered;
+    use crate::materials::microfacet::fresnel_dielectric;
+    use crate::texture::SolidColor;
+
+    #[test]
+    fn compositions_forward_their_children() {
+        let gray = |value: f32| SolidColor::new(Color::new(value, value, value));
+        let diffuse: Arc<dyn Material> = Arc::new(Lambertian::new(gray(1.0)));
+        let cutout: Arc<dyn Material> = Arc::new(AlphaMask::new(diffuse.clone(), gray(0.0)));
+        let lamp: Arc<dyn Material> = Arc::new(DiffuseLight::new(gray(1.0)).with_group("lamp"));
+        let coated = Layered::new(diffuse.clone(), 1.5, WHITE);
+
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let surface = Lambertian::new(gray(1.0));
+        let hit_record = HitRecord::new(Vec3::ZERO, 1.0, &ray, &Vec3::new(0.0, 0.0, 1.0), 0.5, 0.5, &surface);
+
+        // cut out only where the cut out material is picked
+        assert!(!MixMaterial::new(diffuse.clone(), cutout.clone(), gray(1.0)).alpha_test(&hit_record));
+        assert!(MixMaterial::new(diffuse.clone(), cutout.clone(), gray(0.0)).alpha_test(&hit_record));
+        assert!(!Layered::new(cutout, 1.5, WHITE).alpha_test(&hit_record));
+
+        assert_eq!(MixMaterial::new(diffuse.clone(), lamp.clone(), gray(0.2)).light_group(), Some("lamp"));
+        assert_eq!(Layered::new(lamp, 1.5, WHITE).light_group(), Some("lamp"));
+
+        // the base seen through the coat at normal incidence, both ways
+        let (value, pdf) = coated.evaluate(&ray, &hit_record, &Vec3::new(0.0, 0.0, 1.0)).unwrap();
+        let transmitted = 1.0 - fresnel_dielectric(1.0, 1.5);
+        assert!((value.g - transmitted * transmitted / PI).abs() < 1e-5);
+        assert!((pdf - transmitted / PI).abs() < 1e-5);
+
+        let mix = MixMaterial::new(Arc::new(coated), diffuse, gray(0.5));
+        let (value, _) = mix.evaluate(&ray, &hit_record, &Vec3::new(0.0, 0.0, 1.0)).unwrap();
+        assert!((value.g - 0.5 * (transmitted * transmitted + 1.0) / PI).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,269 @@
+use std::sync::Arc;
+
+use crate::color::{Color, BLACK};
+use crate::materials::{Material, HitRecord, ScatterRecord};
+use crate::structures::onb::Onb;
+use crate::structures::ray::Ray;
+use crate::structures::vec3::Vec3;
+use crate::texture::Texture;
+
+// offset in texture space for the finite differences of bump maps
+const BUMP_DELTA: f32 = 0.001;
+
+// perturbs the shading normal of `material` with a tangent space normal map,
+// read with +x along u and +y along v. Image maps have to be opened with
+// `ColorSpace::Raw`. `strength` scales the tangential part of the mapped normal.
+pub struct NormalMap<T: Texture> {
+    pub material: Arc<dyn Material>,
+    pub map: T,
+    pub strength: f32,
+}
+
+impl<T: Texture> NormalMap<T> {
+    pub fn new(material: Arc<dyn Material>, map: T, strength: f32) -> Self {
+        NormalMap { material, map, strength }
+    }
+
+    fn shading_normal(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<Vec3> {
+        let (tangent, bitangent, normal) = tangent_frame(hit_record);
+        let mapped = self.map.color_filtered(hit_record.u, hit_record.v, &hit_record.p, &hit_record.texture_footprint(ray_in));
+
+        let x = (2.0 * mapped.r - 1.0) * self.strength;
+        let y = (2.0 * mapped.g - 1.0) * self.strength;
+        let z = (2.0 * mapped.b - 1.0).max(0.0);
+        let shading_normal = x * tangent + y * bitangent + z * normal;
+
+        if shading_normal.near_zero() { None } else { Some(shading_normal.normalize()) }
+    }
+}
+
+impl<T: Texture> Material for NormalMap<T> {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        match self.shading_normal(ray_in, hit_record) {
+            Some(shading_normal) => scatter_with_normal(&*self.material, ray_in, hit_record, shading_normal),
+            None => self.material.scatter(ray_in, hit_record),
+        }
+    }
+
+    fn evaluate(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> Option<(Color, f32)> {
+        match self.shading_normal(ray_in, hit_record) {
+            Some(shading_normal) => evaluate_with_normal(&*self.material, ray_in, hit_record, direction, shading_normal),
+            None => self.material.evaluate(ray_in, hit_record, direction),
+        }
+    }
+
+    fn emitted(&self, u: f32, v: f32, hit_record: &HitRecord) -> Color {
+        self.material.emitted(u, v, hit_record)
+    }
+
+    fn alpha_test(&self, hit_record: &HitRecord) -> bool {
+        self.material.alpha_test(hit_record)
+    }
+
+    fn albedo(&self, ray_in: &Ray, hit_record: &HitRecord) -> Color {
+        self.material.albedo(ray_in, hit_record)
+    }
+
+    fn diffuse_fraction(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> f32 {
+        self.material.diffuse_fraction(ray_in, hit_record, direction)
+    }
+}
+
+// perturbs the shading normal of `material` as if the surface was displaced
+// along the normal by `height` times `strength` world units
+pub struct Bump<T: Texture> {
+    pub material: Arc<dyn Material>,
+    pub height: T,
+    pub strength: f32,
+}
+
+impl<T: Texture> Bump<T> {
+    pub fn new(material: Arc<dyn Material>, height: T, strength: f32) -> Self {
+        Bump { material, height, strength }
+    }
+
+    fn shading_normal(&self, hit_record: &HitRecord) -> Option<Vec3> {
+        let (u, v, p) = (hit_record.u, hit_record.v, hit_record.p);
+        let (dpdu, dpdv, normal) = if hit_record.dpdu.near_zero() || hit_record.dpdv.near_zero() {
+            tangent_frame(hit_record)
+        } else {
+            (hit_record.dpdu, hit_record.dpdv, hit_record.shading_normal)
+        };
+
+        let height = self.height.value(u, v, &p);
+        let height_u = self.height.value(u + BUMP_DELTA, v, &(p + BUMP_DELTA * dpdu));
+        let height_v = self.height.value(u, v + BUMP_DELTA, &(p + BUMP_DELTA * dpdv));
+
+        let displaced_dpdu = dpdu + (self.strength * (height_u - height) / BUMP_DELTA) * normal;
+        let displaced_dpdv = dpdv + (self.strength * (height_v - height) / BUMP_DELTA) * normal;
+        let shading_normal = displaced_dpdu.cross(&displaced_dpdv);
+
+        if shading_normal.near_zero() {
+            return None;
+        }
+
+        // the cross product follows the parameterisation, not the side that was hit
+        let shading_normal = shading_normal.normalize();
+
+        Some(if shading_normal.dot(&normal) < 0.0 { -shading_normal } else { shading_normal })
+    }
+}
+
+impl<T: Texture> Material for Bump<T> {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        match self.shading_normal(hit_record) {
+            Some(shading_normal) => scatter_with_normal(&*self.material, ray_in, hit_record, shading_normal),
+            None => self.material.scatter(ray_in, hit_record),
+        }
+    }
+
+    fn evaluate(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> Option<(Color, f32)> {
+        match self.shading_normal(hit_record) {
+            Some(shading_normal) => evaluate_with_normal(&*self.material, ray_in, hit_record, direction, shading_normal),
+            None => self.material.evaluate(ray_in, hit_record, direction),
+        }
+    }
+
+    fn emitted(&self, u: f32, v: f32, hit_record: &HitRecord) -> Color {
+        self.material.emitted(u, v, hit_record)
+    }
+
+    fn alpha_test(&self, hit_record: &HitRecord) -> bool {
+        self.material.alpha_test(hit_record)
+    }
+
+    fn albedo(&self, ray_in: &Ray, hit_record: &HitRecord) -> Color {
+        self.material.albedo(ray_in, hit_record)
+    }
+
+    fn diffuse_fraction(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> f32 {
+        self.material.diffuse_fraction(ray_in, hit_record, direction)
+    }
+}
+
+// orthonormal tangent, bitangent and normal around the shading normal, with
+// the tangent following u and the bitangent following v where available
+fn tangent_frame(hit_record: &HitRecord) -> (Vec3, Vec3, Vec3) {
+    let normal = hit_record.shading_normal;
+    let tangent = hit_record.dpdu - hit_record.dpdu.dot(&normal) * normal;
+
+    if tangent.near_zero() {
+        let onb = Onb::from_w(&normal);
+        return (onb.u, onb.v, onb.w);
+    }
+
+    let tangent = tangent.normalize();
+    let bitangent = normal.cross(&tangent);
+    let bitangent = if bitangent.dot(&hit_record.dpdv) < 0.0 { -bitangent } else { bitangent };
+
+    (tangent, bitangent, normal)
+}
+
+// scatters with a perturbed normal, discarding directions that end up on the
+// other side of the actual surface than the shading normal says
+fn scatter_with_normal(material: &dyn Material, ray_in: &Ray, hit_record: &HitRecord, shading_normal: Vec3) -> Option<ScatterRecord> {
+    let scatter_record = material.scatter(ray_in, &HitRecord { shading_normal, ..*hit_record })?;
+
+    let geometric_side = scatter_record.ray.direction.dot(&hit_record.normal) > 0.0;
+    let shading_side = scatter_record.ray.direction.dot(&shading_normal) > 0.0;
+
+    if geometric_side != shading_side {
+        return None;
+    }
+
+    Some(scatter_record)
+}
+
+// evaluates with a perturbed normal, with the same directions as
+// `scatter_with_normal` carrying no light
+fn evaluate_with_normal(material: &dyn Material, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3, shading_normal: Vec3) -> Option<(Color, f32)> {
+    let (value, pdf) = material.evaluate(ray_in, &HitRecord { shading_normal, ..*hit_record }, direction)?;
+
+    let geometric_side = direction.dot(&hit_record.normal) > 0.0;
+    let shading_side = direction.dot(&shading_normal) > 0.0;
+
+    if geometric_side != shading_side {
+        return Some((BLACK, pdf));
+    }
+
+    Some((value, pdf))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::color::WHITE;
+    use crate::materials::Lambertian;
+    use crate::texture::SolidColor;
+
+    // a height rising along u
+    struct Slope;
+
+    impl Texture for Slope {
+        fn color(&self, u: f32, _v: f32, _p: &Vec3) -> Color {
+            Color::new(u, u, u)
+        }
+    }
+
+    // scatters every ray towards the same direction
+    struct Towards(Vec3);
+
+    impl Material for Towards {
+        fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+            Some(ScatterRecord::new(WHITE, Ray::new(hit_record.p, self.0, ray_in.time), 1.0))
+        }
+
+        fn emitted(&self, _u: f32, _v: f32, _hit_record: &HitRecord) -> Color {
+            BLACK
+        }
+
+        fn evaluate(&self, _ray_in: &Ray, _hit_record: &HitRecord, _direction: &Vec3) -> Option<(Color, f32)> {
+            Some((WHITE, 1.0))
+        }
+    }
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        (a - b).length() < 1e-3
+    }
+
+    #[test]
+    fn mapped_normals_follow_the_tangents() {
+        let surface: Arc<dyn Material> = Arc::new(Lambertian::new(SolidColor::new(WHITE)));
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let hit_record = HitRecord::new(Vec3::ZERO, 1.0, &ray, &Vec3::new(0.0, 0.0, 1.0), 0.5, 0.5, &*surface)
+            .with_tangents(Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let tilted = Vec3::new(1.0, 0.0, 1.0).normalize();
+
+        let flat = NormalMap::new(surface.clone(), SolidColor::new(Color::new(0.5, 0.5, 1.0)), 1.0);
+        assert!(close(flat.shading_normal(&ray, &hit_record).unwrap(), Vec3::new(0.0, 0.0, 1.0)));
+
+        let along_u = NormalMap::new(surface.clone(), SolidColor::new(Color::new(1.0, 0.5, 1.0)), 1.0);
+        assert!(close(along_u.shading_normal(&ray, &hit_record).unwrap(), tilted));
+
+        let level = Bump::new(surface.clone(), SolidColor::new(Color::new(0.3, 0.3, 0.3)), 1.0);
+        assert!(close(level.shading_normal(&hit_record).unwrap(), Vec3::new(0.0, 0.0, 1.0)));
+
+        // the normal leans away from the rising side
+        let slope = Bump::new(surface.clone(), Slope, 1.0);
+        assert!(close(slope.shading_normal(&hit_record).unwrap(), Vec3::new(-1.0, 0.0, 1.0).normalize()));
+    }
+
+    #[test]
+    fn directions_through_the_surface_carry_no_light() {
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let shading_normal = Vec3::new(1.0, 0.0, 1.0).normalize();
+
+        // above the surface but behind the shading normal
+        let grazing = Towards(Vec3::new(-1.0, 0.0, 0.2));
+        let hit_record = HitRecord::new(Vec3::ZERO, 1.0, &ray, &Vec3::new(0.0, 0.0, 1.0), 0.5, 0.5, &grazing);
+        assert!(scatter_with_normal(&grazing, &ray, &hit_record, shading_normal).is_none());
+        let (value, _) = evaluate_with_normal(&grazing, &ray, &hit_record, &grazing.0, shading_normal).unwrap();
+        assert_eq!(value.g, 0.0);
+
+        let up = Towards(Vec3::new(0.0, 0.0, 1.0));
+        assert!(scatter_with_normal(&up, &ray, &hit_record, shading_normal).is_some());
+        let (value, _) = evaluate_with_normal(&up, &ray, &hit_record, &up.0, shading_normal).unwrap();
+        assert_eq!(value.g, 1.0);
+    }
+}
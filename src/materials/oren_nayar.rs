@@ -0,0 +1,94 @@
+use std::f32::consts::PI;
+
+use crate::color::{Color, BLACK};
+use crate::materials::{Material, HitRecord, ScatterRecord};
+use crate::structures::onb::Onb;
+use crate::structures::ray::Ray;
+use crate::structures::vec3::{Vec3, random_cosine_direction};
+use crate::texture::Texture;
+
+// rough diffuse reflection for clay, concrete or cloth. `sigma` is the
+// standard deviation of the microfacet slope angle in degrees, 0 is lambertian.
+pub struct OrenNayar<T: Texture> {
+    pub albedo: T,
+    a: f32,
+    b: f32,
+}
+
+impl<T: Texture> OrenNayar<T> {
+    pub fn new(albedo: T, sigma: f32) -> Self {
+        let sigma = sigma.to_radians();
+        let sigma2 = sigma * sigma;
+
+        OrenNayar {
+            albedo,
+            a: 1.0 - sigma2 / (2.0 * (sigma2 + 0.33)),
+            b: 0.45 * sigma2 / (sigma2 + 0.09),
+        }
+    }
+}
+
+impl<T: Texture> OrenNayar<T> {
+    // the brdf relative to a lambertian one with the same albedo
+    fn reflectance(&self, wo: &Vec3, wi: &Vec3) -> f32 {
+        let sin_theta_i = (1.0 - wi.z * wi.z).max(0.0).sqrt();
+        let sin_theta_o = (1.0 - wo.z * wo.z).max(0.0).sqrt();
+
+        // cosine of the azimuthal difference between both directions
+        let max_cos = if sin_theta_i > 0.0001 && sin_theta_o > 0.0001 {
+            ((wi.x * wo.x + wi.y * wo.y) / (sin_theta_i * sin_theta_o)).max(0.0)
+        } else {
+            0.0
+        };
+
+        let (sin_alpha, tan_beta) = if wi.z.abs() > wo.z.abs() {
+            (sin_theta_o, sin_theta_i / wi.z.abs())
+        } else {
+            (sin_theta_i, sin_theta_o / wo.z.abs().max(0.0001))
+        };
+
+        self.a + self.b * max_cos * sin_alpha * tan_beta
+    }
+}
+
+impl<T: Texture> Material for OrenNayar<T> {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        let onb = Onb::from_w(&hit_record.shading_normal);
+        let wo = onb.to_local(&(-ray_in.direction.normalize()));
+        let wi = random_cosine_direction();
+
+        // cosine sampling cancels the 1 / pi of the brdf and the cosine term
+        let footprint = hit_record.texture_footprint(ray_in);
+        let albedo = self.albedo.color_filtered(hit_record.u, hit_record.v, &hit_record.p, &footprint);
+        let attenuation = albedo * self.reflectance(&wo, &wi);
+
+        Some(ScatterRecord::new(attenuation, Ray::new(hit_record.p, onb.local(&wi), ray_in.time), wi.z / PI))
+    }
+
+    fn evaluate(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> Option<(Color, f32)> {
+        let onb = Onb::from_w(&hit_record.shading_normal);
+        let wo = onb.to_local(&(-ray_in.direction.normalize()));
+        let wi = onb.to_local(&direction.normalize());
+
+        if wi.z <= 0.0 {
+            return Some((BLACK, 0.0));
+        }
+
+        let footprint = hit_record.texture_footprint(ray_in);
+        let albedo = self.albedo.color_filtered(hit_record.u, hit_record.v, &hit_record.p, &footprint);
+
+        Some(((self.reflectance(&wo, &wi) * wi.z / PI) * albedo, wi.z / PI))
+    }
+
+    fn albedo(&self, ray_in: &Ray, hit_record: &HitRecord) -> Color {
+        self.albedo.color_filtered(hit_record.u, hit_record.v, &hit_record.p, &hit_record.texture_footprint(ray_in))
+    }
+
+    fn diffuse_fraction(&self, _ray_in: &Ray, _hit_record: &HitRecord, _direction: &Vec3) -> f32 {
+        1.0
+    }
+
+    fn emitted(&self, _u: f32, _v: f32, _hit_record: &HitRecord) -> Color {
+        BLACK
+    }
+}
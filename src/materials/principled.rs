@@ -0,0 +1,396 @@
+use std::f32::consts::PI;
+
+use crate::color::{Color, BLACK, WHITE, linear_blend};
+use crate::materials::{Material, HitRecord, ScatterRecord};
+use crate::materials::microfacet::{TrowbridgeReitz, reflect, refract, fresnel_dielectric, schlick_weight};
+use crate::random::random_double;
+use crate::structures::onb::Onb;
+use crate::structures::ray::Ray;
+use crate::structures::vec3::{Vec3, random_cosine_direction};
+use crate::texture::{Texture, SolidColor};
+use crate::utils;
+
+// Disney style uber material. Every parameter is a texture, scalar
+// parameters are read through `Texture::value`.
+pub struct Principled {
+    pub base_color: Box<dyn Texture>,
+    pub metallic: Box<dyn Texture>,
+    pub roughness: Box<dyn Texture>,
+    pub specular: Box<dyn Texture>,
+    pub specular_tint: Box<dyn Texture>,
+    pub sheen: Box<dyn Texture>,
+    pub sheen_tint: Box<dyn Texture>,
+    pub clearcoat: Box<dyn Texture>,
+    pub clearcoat_roughness: Box<dyn Texture>,
+    pub transmission: Box<dyn Texture>,
+    pub ior: Box<dyn Texture>,
+}
+
+impl Principled {
+    // a rough dielectric with the given base color, the other parameters can
+    // be set with struct update syntax
+    pub fn new(base_color: impl Texture + 'static) -> Self {
+        Principled {
+            base_color: Box::new(base_color),
+            metallic: constant(0.0),
+            roughness: constant(0.5),
+            specular: constant(0.5),
+            specular_tint: constant(0.0),
+            sheen: constant(0.0),
+            sheen_tint: constant(0.5),
+            clearcoat: constant(0.0),
+            clearcoat_roughness: constant(0.03),
+            transmission: constant(0.0),
+            ior: constant(1.45),
+        }
+    }
+}
+
+pub fn constant(value: f32) -> Box<dyn Texture> {
+    Box::new(SolidColor::new(Color::new(value, value, value)))
+}
+
+// all texture lookups for a single shading point
+struct Parameters {
+    base_color: Color,
+    metallic: f32,
+    roughness: f32,
+    specular: f32,
+    specular_tint: f32,
+    sheen: f32,
+    sheen_tint: f32,
+    clearcoat: f32,
+    clearcoat_roughness: f32,
+    transmission: f32,
+    ior: f32,
+}
+
+impl Principled {
+    fn parameters(&self, ray_in: &Ray, hit_record: &HitRecord) -> Parameters {
+        let (u, v, p) = (hit_record.u, hit_record.v, &hit_record.p);
+
+        Parameters {
+            base_color: self.base_color.color_filtered(u, v, p, &hit_record.texture_footprint(ray_in)),
+            metallic: unit(self.metallic.value(u, v, p)),
+            roughness: unit(self.roughness.value(u, v, p)),
+            specular: unit(self.specular.value(u, v, p)),
+            specular_tint: unit(self.specular_tint.value(u, v, p)),
+            sheen: self.sheen.value(u, v, p).max(0.0),
+            sheen_tint: unit(self.sheen_tint.value(u, v, p)),
+            clearcoat: unit(self.clearcoat.value(u, v, p)),
+            clearcoat_roughness: unit(self.clearcoat_roughness.value(u, v, p)),
+            transmission: unit(self.transmission.value(u, v, p)),
+            ior: self.ior.value(u, v, p).max(1.0),
+        }
+    }
+}
+
+fn unit(value: f32) -> f32 {
+    utils::clamp(value, 0.0, 1.0)
+}
+
+// hue and saturation of the base color with unit luminance
+fn tint(base_color: Color) -> Color {
+    let luminance = base_color.luminance();
+
+    if luminance > 0.0 { base_color * (1.0 / luminance) } else { WHITE }
+}
+
+fn schlick(f0: Color, cos_theta: f32) -> Color {
+    linear_blend(schlick_weight(cos_theta), f0, WHITE)
+}
+
+const CLEARCOAT_F0: f32 = 0.04;
+
+// weights and selection probabilities of the lobes for one outgoing direction
+struct Lobes {
+    tint: Color,
+    dielectric_f0: Color,
+    specular_f0: Color,
+    diffuse_weight: f32,
+    transmission_weight: f32,
+    specular_weight: f32,
+    clearcoat_prob: f32,
+    base_scale: f32,
+    diffuse_prob: f32,
+    transmission_prob: f32,
+}
+
+impl Lobes {
+    fn new(params: &Parameters, wo: &Vec3) -> Self {
+        let tint = tint(params.base_color);
+        let dielectric_f0 = (0.08 * params.specular) * linear_blend(params.specular_tint, WHITE, tint);
+        let specular_f0 = linear_blend(params.metallic, dielectric_f0, params.base_color);
+
+        let diffuse_weight = (1.0 - params.metallic) * (1.0 - params.transmission);
+        let transmission_weight = (1.0 - params.metallic) * params.transmission;
+        // the transmission lobe already reflects at the dielectric interface
+        let specular_weight = 1.0 - transmission_weight;
+
+        // the clearcoat sits on top of everything and removes the energy it reflects
+        let clearcoat_prob = params.clearcoat * (CLEARCOAT_F0 + (1.0 - CLEARCOAT_F0) * schlick_weight(wo.z));
+        let base_scale = 1.0 - clearcoat_prob;
+
+        let diffuse_albedo = diffuse_weight * params.base_color.luminance();
+        let specular_albedo = specular_weight * schlick(specular_f0, wo.z).luminance();
+        let lobe_total = diffuse_albedo + specular_albedo + transmission_weight;
+        let (diffuse_prob, transmission_prob) = if lobe_total > 0.0 {
+            (diffuse_albedo / lobe_total, transmission_weight / lobe_total)
+        } else {
+            (0.0, 0.0)
+        };
+
+        Lobes {
+            tint,
+            dielectric_f0,
+            specular_f0,
+            diffuse_weight,
+            transmission_weight,
+            specular_weight,
+            clearcoat_prob,
+            base_scale,
+            diffuse_prob,
+            transmission_prob,
+        }
+    }
+
+    // diffuse reflection only receives what the specular layer transmits
+    fn diffuse(&self, params: &Parameters, wo: &Vec3, wi: &Vec3) -> Color {
+        let wh = (*wo + *wi).normalize();
+        let diffuse = (1.0 - schlick(self.dielectric_f0, wo.z).luminance()) * params.base_color;
+        let sheen = (params.sheen * schlick_weight(wi.dot(&wh))) * linear_blend(params.sheen_tint, WHITE, self.tint);
+
+        diffuse + sheen
+    }
+}
+
+impl Material for Principled {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        let params = self.parameters(ray_in, hit_record);
+        let onb = Onb::from_w(&hit_record.shading_normal);
+        let wo = onb.to_local(&(-ray_in.direction.normalize()));
+
+        if wo.z <= 0.0 {
+            return None;
+        }
+
+        // leaving a transmissive object only the dielectric interface remains
+        if !hit_record.front_face && params.transmission > 0.0 {
+            let distribution = TrowbridgeReitz::from_roughness(params.roughness);
+            let wi = sample_dielectric(&distribution, &wo, 1.0 / params.ior)?;
+            let attenuation = WHITE * (distribution.g(&wo, &wi) / distribution.g1(&wo));
+            let scattered = Ray::new(hit_record.p, onb.local(&wi), ray_in.time);
+            if wi.z < 0.0 {
+                return Some(ScatterRecord::specular(attenuation, scattered));
+            }
+
+            let (_, pdf, _) = self.reflection(&params, ray_in, hit_record, &scattered.direction);
+
+            return Some(ScatterRecord::new(attenuation, scattered, pdf));
+        }
+
+        let lobes = Lobes::new(&params, &wo);
+        let (clearcoat_prob, diffuse_prob, transmission_prob) = (lobes.clearcoat_prob, lobes.diffuse_prob, lobes.transmission_prob);
+
+        let selector = random_double();
+
+        let (attenuation, wi) = if selector < clearcoat_prob {
+            let distribution = TrowbridgeReitz::from_roughness(params.clearcoat_roughness);
+            let wh = distribution.sample_visible_normal(&wo, random_double(), random_double());
+            let wi = reflect(&wo, &wh);
+            if wi.z <= 0.0 {
+                return None;
+            }
+
+            let fresnel = CLEARCOAT_F0 + (1.0 - CLEARCOAT_F0) * schlick_weight(wo.dot(&wh));
+            let weight = params.clearcoat * fresnel * distribution.g(&wo, &wi) / distribution.g1(&wo);
+
+            (WHITE * (weight / clearcoat_prob), wi)
+        } else {
+            let selector = (selector - clearcoat_prob) / lobes.base_scale;
+
+            if selector < diffuse_prob {
+                let wi = random_cosine_direction();
+
+                ((lobes.diffuse_weight / diffuse_prob) * lobes.diffuse(&params, &wo, &wi), wi)
+            } else if selector < diffuse_prob + transmission_prob {
+                let distribution = TrowbridgeReitz::from_roughness(params.roughness);
+                let wi = sample_dielectric(&distribution, &wo, params.ior)?;
+                let weight = distribution.g(&wo, &wi) / distribution.g1(&wo);
+
+                // tint refracted light on the way in
+                let color = if wi.z < 0.0 { params.base_color } else { WHITE };
+
+                ((lobes.transmission_weight * weight / transmission_prob) * color, wi)
+            } else {
+                let distribution = TrowbridgeReitz::from_roughness(params.roughness);
+                let wh = distribution.sample_visible_normal(&wo, random_double(), random_double());
+                let wi = reflect(&wo, &wh);
+                if wi.z <= 0.0 {
+                    return None;
+                }
+
+                let fresnel = schlick(lobes.specular_f0, wo.dot(&wh));
+                let weight = distribution.g(&wo, &wi) / distribution.g1(&wo);
+
+                ((lobes.specular_weight * weight / (1.0 - diffuse_prob - transmission_prob)) * fresnel, wi)
+            }
+        };
+
+        // refracted rays are left to themselves. Every reflection lobe is
+        // rough, a reflected direction could have come from any of them.
+        let scattered = Ray::new(hit_record.p, onb.local(&wi), ray_in.time);
+        if wi.z < 0.0 {
+            return Some(ScatterRecord::specular(attenuation, scattered));
+        }
+
+        let (_, pdf, _) = self.reflection(&params, ray_in, hit_record, &scattered.direction);
+
+        Some(ScatterRecord::new(attenuation, scattered, pdf))
+    }
+
+    fn emitted(&self, _u: f32, _v: f32, _hit_record: &HitRecord) -> Color {
+        BLACK
+    }
+
+    // the sum of the reflection lobes weighted like `scatter` picks them.
+    // Refraction is only sampled, transmissive surfaces evaluate the light
+    // their interface reflects.
+    fn evaluate(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> Option<(Color, f32)> {
+        let params = self.parameters(ray_in, hit_record);
+        let (value, pdf, _) = self.reflection(&params, ray_in, hit_record, direction);
+
+        Some((value, pdf))
+    }
+
+    fn albedo(&self, ray_in: &Ray, hit_record: &HitRecord) -> Color {
+        self.base_color.color_filtered(hit_record.u, hit_record.v, &hit_record.p, &hit_record.texture_footprint(ray_in))
+    }
+
+    // refraction counts as specular
+    fn diffuse_fraction(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> f32 {
+        let params = self.parameters(ray_in, hit_record);
+        let (value, _, diffuse) = self.reflection(&params, ray_in, hit_record, direction);
+
+        if value.luminance() > 0.0 { utils::clamp(diffuse.luminance() / value.luminance(), 0.0, 1.0) } else { 0.0 }
+    }
+}
+
+impl Principled {
+    // the reflection lobes evaluated like `evaluate`, with the part of the
+    // value coming from the diffuse lobe
+    fn reflection(&self, params: &Parameters, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> (Color, f32, Color) {
+        let onb = Onb::from_w(&hit_record.shading_normal);
+        let wo = onb.to_local(&(-ray_in.direction.normalize()));
+        let wi = onb.to_local(&direction.normalize());
+
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return (BLACK, 0.0, BLACK);
+        }
+
+        let wh = (wo + wi).normalize();
+
+        // visible normal sampling of the half vector followed by a reflection
+        let reflection_pdf = |distribution: &TrowbridgeReitz| distribution.pdf(&wo, &wh) / (4.0 * wo.dot(&wh));
+
+        // leaving a transmissive object only the interface reflects
+        if !hit_record.front_face && params.transmission > 0.0 {
+            let distribution = TrowbridgeReitz::from_roughness(params.roughness);
+            let pdf = fresnel_dielectric(wo.dot(&wh), 1.0 / params.ior) * reflection_pdf(&distribution);
+
+            return (WHITE * (pdf * distribution.g(&wo, &wi) / distribution.g1(&wo)), pdf, BLACK);
+        }
+
+        let lobes = Lobes::new(params, &wo);
+
+        let diffuse_pdf = wi.z / PI;
+        let diffuse = (lobes.diffuse_weight * diffuse_pdf) * lobes.diffuse(params, &wo, &wi);
+
+        let distribution = TrowbridgeReitz::from_roughness(params.roughness);
+        let specular_pdf = reflection_pdf(&distribution);
+        let specular_weight = lobes.specular_weight * specular_pdf * distribution.g(&wo, &wi) / distribution.g1(&wo);
+        let specular = specular_weight * schlick(lobes.specular_f0, wo.dot(&wh));
+
+        // the transmission lobe reflects what its interface doesn't refract
+        let transmission_pdf = fresnel_dielectric(wo.dot(&wh), params.ior) * specular_pdf;
+        let transmission = lobes.transmission_weight * transmission_pdf * distribution.g(&wo, &wi) / distribution.g1(&wo);
+
+        let distribution = TrowbridgeReitz::from_roughness(params.clearcoat_roughness);
+        let clearcoat_pdf = reflection_pdf(&distribution);
+        let fresnel = CLEARCOAT_F0 + (1.0 - CLEARCOAT_F0) * schlick_weight(wo.dot(&wh));
+        let clearcoat = params.clearcoat * fresnel * clearcoat_pdf * distribution.g(&wo, &wi) / distribution.g1(&wo);
+
+        let value = clearcoat * WHITE + lobes.base_scale * (diffuse + specular + transmission * WHITE);
+        let specular_prob = 1.0 - lobes.diffuse_prob - lobes.transmission_prob;
+        let pdf = lobes.clearcoat_prob * clearcoat_pdf
+            + lobes.base_scale * (lobes.diffuse_prob * diffuse_pdf + specular_prob * specular_pdf + lobes.transmission_prob * transmission_pdf);
+
+        (value, pdf, lobes.base_scale * diffuse)
+    }
+}
+
+// samples reflection or refraction through a rough dielectric interface
+// choosing between them by the fresnel term
+fn sample_dielectric(distribution: &TrowbridgeReitz, wo: &Vec3, eta: f32) -> Option<Vec3> {
+    let wh = distribution.sample_visible_normal(wo, random_double(), random_double());
+
+    if random_double() < fresnel_dielectric(wo.dot(&wh), eta) {
+        let wi = reflect(wo, &wh);
+        if wi.z > 0.0 { Some(wi) } else { None }
+    } else {
+        let wi = refract(wo, &wh, eta)?;
+        if wi.z < 0.0 { Some(wi) } else { None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // the directional albedo estimated from the sampled rays and from
+    // evaluating cosine distributed directions
+    fn albedo(material: &Principled, incoming: Vec3, outward_normal: Vec3) -> (f32, f32) {
+        let ray = Ray::new(-incoming, incoming, 0.0);
+        let hit_record = HitRecord::new(Vec3::ZERO, 1.0, &ray, &outward_normal, 0.5, 0.5, material);
+        let samples = 200_000;
+
+        // refraction isn't evaluated
+        let sampled: f32 = (0..samples)
+            .filter_map(|_| material.scatter(&ray, &hit_record))
+            .filter(|scatter_record| scatter_record.ray.direction.z > 0.0)
+            .map(|scatter_record| scatter_record.attenuation.luminance())
+            .sum();
+        let evaluated: f32 = (0..samples)
+            .filter_map(|_| {
+                let direction = random_cosine_direction();
+                material.evaluate(&ray, &hit_record, &direction).map(|(value, _)| value.luminance() / (direction.z / PI))
+            })
+            .sum();
+
+        (sampled / samples as f32, evaluated / samples as f32)
+    }
+
+    #[test]
+    fn sampling_matches_evaluation_and_conserves_energy() {
+        let materials = [
+            Principled { roughness: constant(0.6), ..Principled::new(SolidColor::new(WHITE)) },
+            Principled { metallic: constant(1.0), roughness: constant(0.5), ..Principled::new(SolidColor::new(Color::new(0.9, 0.6, 0.3))) },
+            Principled { clearcoat: constant(1.0), clearcoat_roughness: constant(0.3), sheen: constant(0.5), ..Principled::new(SolidColor::new(Color::new(0.2, 0.4, 0.8))) },
+            Principled { transmission: constant(0.7), roughness: constant(0.4), ..Principled::new(SolidColor::new(Color::new(0.8, 0.9, 0.6))) },
+        ];
+
+        for material in materials.iter() {
+            for incoming in [Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.6, 0.0, -0.8)].iter() {
+                let (sampled, evaluated) = albedo(material, *incoming, Vec3::new(0.0, 0.0, 1.0));
+
+                assert!(sampled <= 1.02, "{}", sampled);
+                assert!((sampled - evaluated).abs() < 0.02, "{} {}", sampled, evaluated);
+            }
+        }
+
+        // leaving a transmissive object
+        let (sampled, evaluated) = albedo(&materials[3], Vec3::new(0.3, 0.0, -0.95).normalize(), Vec3::new(0.0, 0.0, -1.0));
+        assert!(evaluated > 0.0 && (sampled - evaluated).abs() < 0.02, "{} {}", sampled, evaluated);
+    }
+}
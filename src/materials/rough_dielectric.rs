@@ -0,0 +1,117 @@
+use crate::color::{Color, BLACK, WHITE};
+use crate::materials::{Material, HitRecord, ScatterRecord};
+use crate::materials::microfacet::{TrowbridgeReitz, reflect, refract, fresnel_dielectric};
+use crate::random::random_double;
+use crate::structures::onb::Onb;
+use crate::structures::ray::Ray;
+use crate::structures::vec3::Vec3;
+use crate::texture::Texture;
+
+// frosted glass: a dielectric interface made of GGX distributed microfacets
+pub struct RoughDielectric<T: Texture> {
+    pub ref_idx: f32,
+    pub roughness: T,
+}
+
+impl<T: Texture> RoughDielectric<T> {
+    pub fn new(ref_idx: f32, roughness: T) -> Self {
+        RoughDielectric { ref_idx, roughness }
+    }
+}
+
+impl<T: Texture> Material for RoughDielectric<T> {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        let eta = if hit_record.front_face { self.ref_idx } else { 1.0 / self.ref_idx };
+        let onb = Onb::from_w(&hit_record.shading_normal);
+        let wo = onb.to_local(&(-ray_in.direction.normalize()));
+
+        if wo.z <= 0.0 {
+            return None;
+        }
+
+        let roughness = self.roughness.value(hit_record.u, hit_record.v, &hit_record.p);
+        let distribution = TrowbridgeReitz::from_roughness(roughness);
+
+        let wh = distribution.sample_visible_normal(&wo, random_double(), random_double());
+        let reflect_prob = fresnel_dielectric(wo.dot(&wh), eta);
+
+        // choosing between reflection and transmission by the fresnel term
+        // cancels it out of the weight, leaving only the masking ratio. Only
+        // the reflection is evaluated, refracted rays are left to themselves.
+        if random_double() < reflect_prob {
+            let wi = reflect(&wo, &wh);
+            if wi.z <= 0.0 {
+                return None;
+            }
+
+            let attenuation = WHITE * (distribution.g(&wo, &wi) / distribution.g1(&wo));
+            let pdf = reflect_prob * distribution.pdf(&wo, &wh) / (4.0 * wo.dot(&wh));
+
+            Some(ScatterRecord::new(attenuation, Ray::new(hit_record.p, onb.local(&wi), ray_in.time), pdf))
+        } else {
+            let wi = refract(&wo, &wh, eta)?;
+            if wi.z >= 0.0 {
+                return None;
+            }
+
+            let attenuation = WHITE * (distribution.g(&wo, &wi) / distribution.g1(&wo));
+
+            Some(ScatterRecord::specular(attenuation, Ray::new(hit_record.p, onb.local(&wi), ray_in.time)))
+        }
+    }
+
+    fn emitted(&self, _u: f32, _v: f32, _hit_record: &HitRecord) -> Color {
+        BLACK
+    }
+
+    // the reflection off the interface, refraction is only sampled
+    fn evaluate(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> Option<(Color, f32)> {
+        let eta = if hit_record.front_face { self.ref_idx } else { 1.0 / self.ref_idx };
+        let onb = Onb::from_w(&hit_record.shading_normal);
+        let wo = onb.to_local(&(-ray_in.direction.normalize()));
+        let wi = onb.to_local(&direction.normalize());
+
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Some((BLACK, 0.0));
+        }
+
+        let roughness = self.roughness.value(hit_record.u, hit_record.v, &hit_record.p);
+        let distribution = TrowbridgeReitz::from_roughness(roughness);
+        let wh = (wo + wi).normalize();
+        let pdf = fresnel_dielectric(wo.dot(&wh), eta) * distribution.pdf(&wo, &wh) / (4.0 * wo.dot(&wh));
+
+        Some((WHITE * (pdf * distribution.g(&wo, &wi) / distribution.g1(&wo)), pdf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::f32::consts::PI;
+    use crate::structures::vec3::random_cosine_direction;
+    use crate::texture::SolidColor;
+
+    #[test]
+    fn evaluation_matches_the_sampled_reflection() {
+        let material = RoughDielectric::new(1.5, SolidColor::new(Color::new(0.4, 0.4, 0.4)));
+        let ray = Ray::new(Vec3::new(-0.6, 0.0, 0.8), Vec3::new(0.6, 0.0, -0.8), 0.0);
+        let hit_record = HitRecord::new(Vec3::ZERO, 1.0, &ray, &Vec3::new(0.0, 0.0, 1.0), 0.5, 0.5, &material);
+        let samples = 200_000;
+
+        let sampled: f32 = (0..samples)
+            .filter_map(|_| material.scatter(&ray, &hit_record))
+            .filter(|scatter_record| scatter_record.ray.direction.z > 0.0)
+            .map(|scatter_record| scatter_record.attenuation.g)
+            .sum();
+        let evaluated: f32 = (0..samples)
+            .filter_map(|_| {
+                let direction = random_cosine_direction();
+                material.evaluate(&ray, &hit_record, &direction).map(|(value, _)| value.g / (direction.z / PI))
+            })
+            .sum();
+        let (sampled, evaluated) = (sampled / samples as f32, evaluated / samples as f32);
+
+        assert!(evaluated > 0.0 && (sampled - evaluated).abs() < 0.01, "{} {}", sampled, evaluated);
+    }
+}
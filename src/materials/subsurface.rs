@@ -0,0 +1,75 @@
+use crate::color::{Color, BLACK, WHITE};
+use crate::materials::{Material, HitRecord, ScatterRecord};
+use crate::materials::microfacet::fresnel_dielectric;
+use crate::random::random_double;
+use crate::structures::ray::Ray;
+use crate::structures::vec3::Vec3;
+use crate::utils;
+
+// translucent material for skin, wax or marble. The interface is a smooth
+// dielectric, the interior a random walk performed by wrapping the closed
+// object in a `SubsurfaceVolume`. `albedo` is the color of the object after
+// all the scattering, `mean_free_path` the average distance light travels
+// inside before scattering, both per channel.
+pub struct Subsurface {
+    pub ior: f32,
+    pub sigma_t: Color,
+    pub single_scatter_albedo: Color,
+    // the color the object ends up with, which the single scattering albedo
+    // is derived from
+    pub albedo: Color,
+}
+
+impl Subsurface {
+    pub fn new(albedo: Color, mean_free_path: Color, ior: f32) -> Self {
+        Subsurface {
+            ior,
+            sigma_t: Color::new(1.0 / mean_free_path.r, 1.0 / mean_free_path.g, 1.0 / mean_free_path.b),
+            single_scatter_albedo: Color::new(
+                albedo_inversion(albedo.r),
+                albedo_inversion(albedo.g),
+                albedo_inversion(albedo.b)),
+            albedo,
+        }
+    }
+}
+
+// single scattering albedo that results in the multiple scattering albedo
+// `albedo` for a random walk, see Chiang et al. 2016 "Practical and
+// Controllable Subsurface Scattering for Production Path Tracing"
+fn albedo_inversion(albedo: f32) -> f32 {
+    let a = utils::clamp(albedo, 0.0, 0.999);
+
+    1.0 - (a * (-5.09406 + a * (2.61188 - a * 4.31805))).exp()
+}
+
+impl Material for Subsurface {
+    // refracts into the object, without a surrounding volume this behaves
+    // like a clear dielectric
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        let etai_over_etat = if hit_record.front_face { 1.0 / self.ior } else { self.ior };
+        let unit_direction = ray_in.direction.normalize();
+        let cos_theta = (-unit_direction).dot(&hit_record.shading_normal).max(0.0);
+
+        let direction = if random_double() < fresnel_dielectric(cos_theta, 1.0 / etai_over_etat) {
+            Vec3::reflect(&unit_direction, &hit_record.shading_normal)
+        } else {
+            Vec3::refract(&unit_direction, &hit_record.shading_normal, etai_over_etat)
+        };
+
+        Some(ScatterRecord::specular(WHITE, Ray::new(hit_record.p, direction, ray_in.time)))
+    }
+
+    fn emitted(&self, _u: f32, _v: f32, _hit_record: &HitRecord) -> Color {
+        BLACK
+    }
+
+    fn albedo(&self, _ray_in: &Ray, _hit_record: &HitRecord) -> Color {
+        self.albedo
+    }
+
+    // light entering the object comes back out diffusely
+    fn diffuse_fraction(&self, _ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> f32 {
+        if direction.dot(&hit_record.normal) < 0.0 { 1.0 } else { 0.0 }
+    }
+}
@@ -0,0 +1,248 @@
+use std::f32::consts::PI;
+use std::ops::{Add, Sub, Mul, Div};
+
+use crate::color::{Color, BLACK};
+use crate::materials::{Material, HitRecord, ScatterRecord};
+use crate::random::random_double;
+use crate::spectrum::{reflectance_to_rgb, rgb_to_wavelength, LAMBDA_RED, LAMBDA_GREEN, LAMBDA_BLUE};
+use crate::structures::ray::Ray;
+use crate::structures::vec3::Vec3;
+use crate::texture::Texture;
+use crate::utils;
+
+pub enum ThinFilmBase {
+    Dielectric { ior: f32 },
+    Conductor { eta: Color, k: Color },
+}
+
+pub enum SpectralMode {
+    // evaluates the interference at one wavelength per color channel
+    Rgb,
+    // integrates the interference over the visible spectrum
+    Spectral,
+}
+
+// a thin transparent film, like a soap bubble or an oil slick, on top of a
+// smooth dielectric or metal. `thickness` is read in nanometres.
+pub struct ThinFilm<T: Texture> {
+    pub base: ThinFilmBase,
+    pub thickness: T,
+    pub film_ior: f32,
+    pub mode: SpectralMode,
+}
+
+impl<T: Texture> ThinFilm<T> {
+    pub fn dielectric(ior: f32, thickness: T, film_ior: f32) -> Self {
+        ThinFilm { base: ThinFilmBase::Dielectric { ior }, thickness, film_ior, mode: SpectralMode::Spectral }
+    }
+
+    pub fn conductor(eta: Color, k: Color, thickness: T, film_ior: f32) -> Self {
+        ThinFilm { base: ThinFilmBase::Conductor { eta, k }, thickness, film_ior, mode: SpectralMode::Spectral }
+    }
+
+    fn reflectance(&self, cos_theta: f32, thickness: f32, front_face: bool) -> Color {
+        let film = Complex::real(self.film_ior);
+
+        let at = |lambda: f32| -> f32 {
+            // light arriving from inside a dielectric sees the layers reversed
+            let (outside, inside) = match &self.base {
+                ThinFilmBase::Dielectric { ior } if front_face => (Complex::real(1.0), Complex::real(*ior)),
+                ThinFilmBase::Dielectric { ior } => (Complex::real(*ior), Complex::real(1.0)),
+                ThinFilmBase::Conductor { eta, k } => (
+                    Complex::real(1.0),
+                    Complex::new(rgb_to_wavelength(*eta, lambda), rgb_to_wavelength(*k, lambda))),
+            };
+
+            airy_reflectance(cos_theta, outside, film, inside, thickness, lambda)
+        };
+
+        match self.mode {
+            SpectralMode::Rgb => Color::new(at(LAMBDA_RED), at(LAMBDA_GREEN), at(LAMBDA_BLUE)),
+            SpectralMode::Spectral => reflectance_to_rgb(at),
+        }
+    }
+}
+
+impl<T: Texture> Material for ThinFilm<T> {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        let unit_direction = ray_in.direction.normalize();
+        let cos_theta = utils::clamp((-unit_direction).dot(&hit_record.shading_normal), 0.0, 1.0);
+        let thickness = self.thickness.value(hit_record.u, hit_record.v, &hit_record.p).max(0.0);
+
+        // spectra outside of the sRGB gamut convert to channels outside of
+        // [0, 1], which would turn the weights below negative
+        let reflectance = self.reflectance(cos_theta, thickness, hit_record.front_face);
+        let reflectance = Color::new(
+            utils::clamp(reflectance.r, 0.0, 1.0),
+            utils::clamp(reflectance.g, 0.0, 1.0),
+            utils::clamp(reflectance.b, 0.0, 1.0));
+        let reflected = Ray::new(hit_record.p, Vec3::reflect(&unit_direction, &hit_record.shading_normal), ray_in.time);
+
+        match &self.base {
+            ThinFilmBase::Conductor { .. } => Some(ScatterRecord::specular(reflectance, reflected)),
+            ThinFilmBase::Dielectric { ior } => {
+                // pick reflection by the average reflectance and reweight per channel,
+                // the film is parallel so the refracted direction only depends on the base
+                let reflect_prob = (reflectance.r + reflectance.g + reflectance.b) / 3.0;
+
+                if random_double() < reflect_prob {
+                    Some(ScatterRecord::specular(reflectance * (1.0 / reflect_prob), reflected))
+                } else {
+                    let etai_over_etat = if hit_record.front_face { 1.0 / ior } else { *ior };
+                    let refracted = Vec3::refract(&unit_direction, &hit_record.shading_normal, etai_over_etat);
+                    let transmittance = Color::new(1.0 - reflectance.r, 1.0 - reflectance.g, 1.0 - reflectance.b);
+
+                    Some(ScatterRecord::specular(transmittance * (1.0 / (1.0 - reflect_prob)), Ray::new(hit_record.p, refracted, ray_in.time)))
+                }
+            }
+        }
+    }
+
+    fn emitted(&self, _u: f32, _v: f32, _hit_record: &HitRecord) -> Color {
+        BLACK
+    }
+}
+
+// reflectance of a film of index `n2` and `thickness` nm between the media
+// `n1` and `n3` at wavelength `lambda` nm, summing all internal reflections
+// (Airy formula) for both polarizations
+pub fn airy_reflectance(cos_theta1: f32, n1: Complex, n2: Complex, n3: Complex, thickness: f32, lambda: f32) -> f32 {
+    let one = Complex::real(1.0);
+    let cos1 = Complex::real(cos_theta1);
+    let sin1_n1 = n1 * Complex::real((1.0 - cos_theta1 * cos_theta1).max(0.0).sqrt());
+
+    // snell's law with complex angles covers absorption and total internal reflection
+    let cos2 = (one - (sin1_n1 / n2) * (sin1_n1 / n2)).sqrt();
+    let cos3 = (one - (sin1_n1 / n3) * (sin1_n1 / n3)).sqrt();
+
+    let r12_s = (n1 * cos1 - n2 * cos2) / (n1 * cos1 + n2 * cos2);
+    let r12_p = (n2 * cos1 - n1 * cos2) / (n2 * cos1 + n1 * cos2);
+    let r23_s = (n2 * cos2 - n3 * cos3) / (n2 * cos2 + n3 * cos3);
+    let r23_p = (n3 * cos2 - n2 * cos3) / (n3 * cos2 + n2 * cos3);
+
+    // phase difference between successive reflections
+    let delta = Complex::real(4.0 * PI * thickness / lambda) * n2 * cos2;
+    let phase = (Complex::new(0.0, 1.0) * delta).exp();
+
+    let r_s = (r12_s + r23_s * phase) / (one + r12_s * r23_s * phase);
+    let r_p = (r12_p + r23_p * phase) / (one + r12_p * r23_p * phase);
+
+    (0.5 * (r_s.norm_sqr() + r_p.norm_sqr())).min(1.0)
+}
+
+#[derive(Copy, Clone)]
+pub struct Complex {
+    pub re: f32,
+    pub im: f32,
+}
+
+impl Complex {
+    pub fn new(re: f32, im: f32) -> Self {
+        Complex { re, im }
+    }
+
+    pub fn real(re: f32) -> Self {
+        Complex { re, im: 0.0 }
+    }
+
+    pub fn norm_sqr(&self) -> f32 {
+        self.re * self.re + self.im * self.im
+    }
+
+    pub fn exp(&self) -> Complex {
+        let scale = self.re.exp();
+
+        Complex::new(scale * self.im.cos(), scale * self.im.sin())
+    }
+
+    // principal square root
+    pub fn sqrt(&self) -> Complex {
+        let norm = self.norm_sqr().sqrt();
+        let re = (0.5 * (norm + self.re)).max(0.0).sqrt();
+        let im = (0.5 * (norm - self.re)).max(0.0).sqrt();
+
+        Complex::new(re, if self.im < 0.0 { -im } else { im })
+    }
+}
+
+impl Add<Complex> for Complex {
+    type Output = Complex;
+
+    fn add(self, _rhs: Complex) -> Complex {
+        Complex::new(self.re + _rhs.re, self.im + _rhs.im)
+    }
+}
+
+impl Sub<Complex> for Complex {
+    type Output = Complex;
+
+    fn sub(self, _rhs: Complex) -> Complex {
+        Complex::new(self.re - _rhs.re, self.im - _rhs.im)
+    }
+}
+
+impl Mul<Complex> for Complex {
+    type Output = Complex;
+
+    fn mul(self, _rhs: Complex) -> Complex {
+        Complex::new(self.re * _rhs.re - self.im * _rhs.im, self.re * _rhs.im + self.im * _rhs.re)
+    }
+}
+
+impl Div<Complex> for Complex {
+    type Output = Complex;
+
+    fn div(self, _rhs: Complex) -> Complex {
+        let denom = _rhs.norm_sqr();
+
+        Complex::new(
+            (self.re * _rhs.re + self.im * _rhs.im) / denom,
+            (self.im * _rhs.re - self.re * _rhs.im) / denom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::materials::microfacet::fresnel_dielectric;
+    use crate::texture::SolidColor;
+
+    #[test]
+    fn vanishing_film_is_plain_fresnel() {
+        for &cos_theta in &[1.0, 0.7, 0.3] {
+            let r = airy_reflectance(cos_theta, Complex::real(1.0), Complex::real(1.33), Complex::real(1.5), 0.0, 550.0);
+
+            assert!((r - fresnel_dielectric(cos_theta, 1.5)).abs() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn film_matching_the_base_has_no_interference() {
+        let r = airy_reflectance(0.8, Complex::real(1.0), Complex::real(1.5), Complex::real(1.5), 350.0, 550.0);
+
+        assert!((r - fresnel_dielectric(0.8, 1.5)).abs() < 0.0001);
+    }
+
+    #[test]
+    fn scattered_weights_stay_positive() {
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+
+        for &thickness in &[0.0, 150.0, 300.0, 450.0, 600.0, 900.0] {
+            let film = ThinFilm::dielectric(1.5, SolidColor::new(Color::new(thickness, thickness, thickness)), 1.33);
+
+            for &cos_theta in &[1.0f32, 0.6, 0.2] {
+                let direction = Vec3::new((1.0 - cos_theta * cos_theta).sqrt(), 0.0, -cos_theta);
+                let ray = Ray::new(-direction, direction, 0.0);
+                let hit_record = HitRecord::new(Vec3::ZERO, 1.0, &ray, &normal, 0.5, 0.5, &film);
+
+                for _ in 0..100 {
+                    let attenuation = film.scatter(&ray, &hit_record).unwrap().attenuation;
+
+                    assert!(attenuation.r >= 0.0 && attenuation.g >= 0.0 && attenuation.b >= 0.0);
+                    assert!(attenuation.r.is_finite() && attenuation.g.is_finite() && attenuation.b.is_finite());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::f32::consts::PI;
+
+use crate::color::{Color, BLACK};
+use crate::materials::{Material, HitRecord, ScatterRecord};
+use crate::structures::onb::Onb;
+use crate::structures::ray::Ray;
+use crate::structures::vec3::{Vec3, random_cosine_direction};
+use crate::texture::Texture;
+use crate::utils;
+
+// fabric: a diffuse base with a sheen lobe on top that brightens grazing
+// angles, using the "Charlie" sheen distribution of Estevez and Kulla 2017
+pub struct Velvet<A: Texture, S: Texture> {
+    pub albedo: A,
+    pub sheen: S,
+    pub roughness: f32,
+}
+
+impl<A: Texture, S: Texture> Velvet<A, S> {
+    pub fn new(albedo: A, sheen: S, roughness: f32) -> Self {
+        Velvet { albedo, sheen, roughness: utils::clamp(roughness, 0.07, 1.0) }
+    }
+
+    // pi times the brdf for local directions above the surface
+    fn weight(&self, ray_in: &Ray, hit_record: &HitRecord, wo: &Vec3, wi: &Vec3) -> Color {
+        let wh = (*wo + *wi).normalize();
+        let sheen_brdf = charlie_d(self.roughness, wh.z) * sheen_visibility(wo.z, wi.z);
+
+        let footprint = hit_record.texture_footprint(ray_in);
+        let albedo = self.albedo.color_filtered(hit_record.u, hit_record.v, &hit_record.p, &footprint);
+        let sheen = self.sheen.color_filtered(hit_record.u, hit_record.v, &hit_record.p, &footprint);
+
+        albedo + (PI * sheen_brdf) * sheen
+    }
+}
+
+pub fn charlie_d(roughness: f32, cos_theta_h: f32) -> f32 {
+    let inv_r = 1.0 / roughness;
+    let sin2 = (1.0 - cos_theta_h * cos_theta_h).max(0.0);
+
+    (2.0 + inv_r) * sin2.powf(0.5 * inv_r) / (2.0 * PI)
+}
+
+// visibility term of Neubelt and Pettineo 2013 commonly paired with charlie
+pub fn sheen_visibility(cos_theta_o: f32, cos_theta_i: f32) -> f32 {
+    1.0 / (4.0 * (cos_theta_i + cos_theta_o - cos_theta_i * cos_theta_o))
+}
+
+impl<A: Texture, S: Texture> Material for Velvet<A, S> {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        let onb = Onb::from_w(&hit_record.shading_normal);
+        let wo = onb.to_local(&(-ray_in.direction.normalize()));
+        let wi = random_cosine_direction();
+
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return None;
+        }
+
+        // both lobes are sampled with a cosine distribution so pi * f is the weight
+        let attenuation = self.weight(ray_in, hit_record, &wo, &wi);
+
+        Some(ScatterRecord::new(attenuation, Ray::new(hit_record.p, onb.local(&wi), ray_in.time), wi.z / PI))
+    }
+
+    fn evaluate(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> Option<(Color, f32)> {
+        let onb = Onb::from_w(&hit_record.shading_normal);
+        let wo = onb.to_local(&(-ray_in.direction.normalize()));
+        let wi = onb.to_local(&direction.normalize());
+
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Some((BLACK, wi.z.max(0.0) / PI));
+        }
+
+        Some(((wi.z / PI) * self.weight(ray_in, hit_record, &wo, &wi), wi.z / PI))
+    }
+
+    fn albedo(&self, ray_in: &Ray, hit_record: &HitRecord) -> Color {
+        self.albedo.color_filtered(hit_record.u, hit_record.v, &hit_record.p, &hit_record.texture_footprint(ray_in))
+    }
+
+    // the sheen is a diffuse looking lobe of fibers, not a reflection
+    fn diffuse_fraction(&self, _ray_in: &Ray, _hit_record: &HitRecord, _direction: &Vec3) -> f32 {
+        1.0
+    }
+
+    fn emitted(&self, _u: f32, _v: f32, _hit_record: &HitRecord) -> Color {
+        BLACK
+    }
+}
@@ -3,12 +3,12 @@ pub mod moving_sphere;
 pub mod triangle;
 pub mod rect;
 pub mod rectbox;
+pub mod subsurface_volume;
 
 use std::f32::consts::PI;
-use std::f32::MAX;
 
 use crate::structures::vec3::Vec3;
-use crate::hitable::Hitable;
+use crate::hitable::{Hitable, Surface};
 use crate::materials::HitRecord;
 use crate::aabb::AABB;
 use crate::structures::ray::Ray;
@@ -19,6 +19,7 @@ pub struct Translate {
 }
 
 impl Translate {
+    #[allow(clippy::self_named_constructors)]
     pub fn translate(hitable: impl Hitable + 'static, displacement: &Vec3) -> Self {
         Translate {
             offset: *displacement,
@@ -28,32 +29,24 @@ impl Translate {
 }
 
 impl Hitable for Translate {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        let moved_r = Ray::new(ray.origin - self.offset, ray.direction, ray.time);
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let moved_r = Ray::new(ray.origin - self.offset, ray.direction, ray.time).with_spread(ray.spread);
 
-        if let Some(hit_record) = self.hitable.hit(&moved_r, t_min, t_max) {
-            Some(
-                HitRecord {
-                    p: hit_record.p + self.offset,
-                    ..hit_record
-                }
-            )
-        } else {
-            None
-        }
+        self.hitable.hit(&moved_r, t_min, t_max).map(|hit_record| {
+            HitRecord {
+                p: hit_record.p + self.offset,
+                ..hit_record
+            }
+        })
     }
 
     fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
-        if let Some(bounding_box) = self.hitable.bounding_box(t0, t1) {
-            Some(
-                AABB {
-                    min: bounding_box.min + self.offset,
-                    max: bounding_box.max + self.offset,
-                }
-            )
-        } else {
-            None
-        }
+        self.hitable.bounding_box(t0, t1).map(|bounding_box| {
+            AABB {
+                min: bounding_box.min + self.offset,
+                max: bounding_box.max + self.offset,
+            }
+        })
     }
 }
 
@@ -69,8 +62,8 @@ impl RotateY {
         let radians = (PI / 180.) * angle;
         let sin_theta = radians.sin();
         let cos_theta = radians.cos();
-        let mut min = Vec3::new(MAX, MAX, MAX);
-        let mut max = Vec3::new(-MAX, -MAX, -MAX);
+        let mut min = Vec3::new(f32::MAX, f32::MAX, f32::MAX);
+        let mut max = Vec3::new(-f32::MAX, -f32::MAX, -f32::MAX);
 
         let bbox = hitable.bounding_box(0., 1.).unwrap();
 
@@ -83,31 +76,9 @@ impl RotateY {
                     let newx = cos_theta * x + sin_theta * z;
                     let newz = -sin_theta * x + cos_theta * z;
                     let tester = Vec3::new(newx, y, newz);
-                    
-                    if tester.x > max.x {
-                        max = Vec3 {
-                            x: tester.x,
-                            ..max
-                        }
-                    }
-                    if tester.x < max.x {
-                        min = Vec3 {
-                            x: tester.x,
-                            ..min
-                        }
-                    }
-                    if tester.y > max.y {
-                        max = Vec3 {
-                            y: tester.y,
-                            ..max
-                        }
-                    }
-                    if tester.y < max.y {
-                        min = Vec3 {
-                            z: tester.z,
-                            ..min
-                        }
-                    }
+
+                    min = Vec3::new(min.x.min(tester.x), min.y.min(tester.y), min.z.min(tester.z));
+                    max = Vec3::new(max.x.max(tester.x), max.y.max(tester.y), max.z.max(tester.z));
                 }
             }
         }
@@ -122,7 +93,7 @@ impl RotateY {
 }
 
 impl Hitable for RotateY {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let origin = ray.origin;
         let direction = ray.direction;
 
@@ -132,20 +103,23 @@ impl Hitable for RotateY {
         let direction = Vec3 { x: self.cos_theta * ray.direction.x - self.sin_theta * ray.direction.z, ..direction };
         let direction = Vec3 { z: self.sin_theta * ray.direction.x + self.cos_theta * ray.direction.z, ..direction };
         
-        let rotated_r = Ray::new(origin, direction, ray.time);
+        let rotated_r = Ray::new(origin, direction, ray.time).with_spread(ray.spread);
 
         if let Some(hit_record) = self.hitable.hit(&rotated_r, t_min, t_max) {
-            let p = hit_record.p;
-            let normal = hit_record.normal;
-            let p = Vec3 { x: self.cos_theta * hit_record.p.x + self.sin_theta * hit_record.p.z, ..p };
-            let p = Vec3 { z: -self.sin_theta * hit_record.p.x + self.cos_theta * hit_record.p.z, ..p };
-            let normal = Vec3 { x: self.cos_theta * hit_record.normal.x + self.sin_theta * hit_record.normal.z, ..normal };
-            let normal = Vec3 { z: -self.sin_theta * hit_record.normal.x + self.cos_theta * hit_record.normal.z, ..normal };
+            let rotate = |a: Vec3| Vec3 {
+                x: self.cos_theta * a.x + self.sin_theta * a.z,
+                z: -self.sin_theta * a.x + self.cos_theta * a.z,
+                ..a
+            };
 
             Some(
                 HitRecord {
-                    p,
-                    normal,
+                    p: rotate(hit_record.p),
+                    normal: rotate(hit_record.normal),
+                    shading_normal: rotate(hit_record.shading_normal),
+                    dpdu: rotate(hit_record.dpdu),
+                    dpdv: rotate(hit_record.dpdv),
+                    velocity: rotate(hit_record.velocity),
                     ..hit_record
                 }
             )
@@ -157,4 +131,66 @@ impl Hitable for RotateY {
     fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
         Some(self.bbox)
     }
-}
\ No newline at end of file
+}
+
+// swaps the front and back of a surface, e.g. to turn a one sided light
+// towards the other side
+pub struct FlipFace<H: Hitable> {
+    hitable: H,
+}
+
+impl<H: Hitable> FlipFace<H> {
+    pub fn new(hitable: H) -> Self {
+        FlipFace { hitable }
+    }
+}
+
+impl<H: Hitable> Hitable for FlipFace<H> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        self.hitable.hit(ray, t_min, t_max).map(|hit_record| {
+            HitRecord {
+                front_face: !hit_record.front_face,
+                ..hit_record
+            }
+        })
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+        self.hitable.bounding_box(t0, t1)
+    }
+}
+
+impl<S: Surface> Surface for FlipFace<S> {
+    fn point_at(&self, u: f32, v: f32) -> (Vec3, Vec3, f32) {
+        let (p, normal, area) = self.hitable.point_at(u, v);
+
+        (p, -normal, area)
+    }
+}
+
+// tags every hit on `hitable` with `id` for the object id output
+pub struct ObjectId<H: Hitable> {
+    hitable: H,
+    id: u32,
+}
+
+impl<H: Hitable> ObjectId<H> {
+    pub fn new(hitable: H, id: u32) -> Self {
+        ObjectId { hitable, id }
+    }
+}
+
+impl<H: Hitable> Hitable for ObjectId<H> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        self.hitable.hit(ray, t_min, t_max).map(|hit_record| {
+            HitRecord {
+                object_id: self.id,
+                ..hit_record
+            }
+        })
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+        self.hitable.bounding_box(t0, t1)
+    }
+}
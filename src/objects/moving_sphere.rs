@@ -27,7 +27,7 @@ impl MovingSphere {
 }
 
 impl Hitable for MovingSphere {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let oc = ray.origin - self.center(ray.time);
         // optimized version of the quadratic formular components
         // see: https://raytracing.github.io/books/RayTracingInOneWeekend.html#surfacenormalsandmultipleobjects/simplifyingtheray-sphereintersectioncode
@@ -42,24 +42,21 @@ impl Hitable for MovingSphere {
         if discriminant > 0.0 {
             let root = discriminant.sqrt();
 
-            let temp_t = (-half_b - root) / a;
-            if temp_t < t_max && temp_t > t_min {
-                let hit_point = ray.at(temp_t);
-                let outward_normal = (hit_point - self.center(ray.time)) / self.radius;
-                let (u, v) = Sphere::get_sphere_uv((hit_point - self.center(ray.time)) / self.radius);
-                let hit_record = HitRecord::new(hit_point, temp_t, ray, &outward_normal, u, v, &*self.material);
+            // a cut out near side lets the ray continue to the far side
+            for &temp_t in &[(-half_b - root) / a, (-half_b + root) / a] {
+                if temp_t < t_max && temp_t > t_min {
+                    let hit_point = ray.at(temp_t);
+                    let outward_normal = (hit_point - self.center(ray.time)) / self.radius;
+                    let (u, v) = Sphere::get_sphere_uv(outward_normal);
+                    let (dpdu, dpdv) = Sphere::get_sphere_tangents(outward_normal, self.radius);
+                    let hit_record = HitRecord::new(hit_point, temp_t, ray, &outward_normal, u, v, &*self.material)
+                        .with_tangents(dpdu, dpdv)
+                        .with_velocity((self.center1 - self.center0) / (self.time1 - self.time0));
 
-                return Some(hit_record);
-            }
-
-            let temp_t = (-half_b + root) / a;
-            if temp_t < t_max && temp_t > t_min {
-                let hit_point = ray.at(temp_t);
-                let outward_normal = (hit_point - self.center(ray.time)) / self.radius;
-                let (u, v) = Sphere::get_sphere_uv((hit_point - self.center(ray.time)) / self.radius);
-                let hit_record = HitRecord::new(hit_point, temp_t, ray, &outward_normal, u, v, &*self.material);
-
-                return Some(hit_record);
+                    if self.material.alpha_test(&hit_record) {
+                        return Some(hit_record);
+                    }
+                }
             } 
         }
 
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use crate::materials::{Material, HitRecord};
-use crate::hitable::Hitable;
+use crate::hitable::{Hitable, Surface};
 use crate::structures::ray::Ray;
 use crate::aabb::AABB;
 use crate::structures::vec3::Vec3;
@@ -24,7 +24,7 @@ impl XyRect {
 }
 
 impl Hitable for XyRect {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let t = (self.k - ray.origin.z) / ray.direction.z;
         
         if t < t_min || t > t_max {
@@ -45,8 +45,13 @@ impl Hitable for XyRect {
             &Vec3::new(0., 0., 1.), 
             (x - self.x0) / (self.x1 - self.x0), 
             (y - self.y0) / (self.y1 - self.y0), 
-            &*self.material);
-            
+            &*self.material)
+            .with_tangents(Vec3::new(self.x1 - self.x0, 0., 0.), Vec3::new(0., self.y1 - self.y0, 0.));
+
+        if !self.material.alpha_test(&hit_record) {
+            return None;
+        }
+
         Some(hit_record)
     }
 
@@ -60,6 +65,14 @@ impl Hitable for XyRect {
     }
 }
 
+impl Surface for XyRect {
+    fn point_at(&self, u: f32, v: f32) -> (Vec3, Vec3, f32) {
+        let p = Vec3::new(self.x0 + u * (self.x1 - self.x0), self.y0 + v * (self.y1 - self.y0), self.k);
+
+        (p, Vec3::new(0., 0., 1.), ((self.x1 - self.x0) * (self.y1 - self.y0)).abs())
+    }
+}
+
 pub struct XzRect {
     x0: f32,
     x1: f32,
@@ -78,7 +91,7 @@ impl XzRect {
 }
 
 impl Hitable for XzRect {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let t = (self.k - ray.origin.y) / ray.direction.y;
         
         if t < t_min || t > t_max {
@@ -99,8 +112,13 @@ impl Hitable for XzRect {
             &Vec3::new(0., 1., 0.), 
             (x - self.x0) / (self.x1 - self.x0), 
             (z - self.z0) / (self.z1 - self.z0), 
-            &*self.material);
-            
+            &*self.material)
+            .with_tangents(Vec3::new(self.x1 - self.x0, 0., 0.), Vec3::new(0., 0., self.z1 - self.z0));
+
+        if !self.material.alpha_test(&hit_record) {
+            return None;
+        }
+
         Some(hit_record)
     }
 
@@ -114,6 +132,14 @@ impl Hitable for XzRect {
     }
 }
 
+impl Surface for XzRect {
+    fn point_at(&self, u: f32, v: f32) -> (Vec3, Vec3, f32) {
+        let p = Vec3::new(self.x0 + u * (self.x1 - self.x0), self.k, self.z0 + v * (self.z1 - self.z0));
+
+        (p, Vec3::new(0., 1., 0.), ((self.x1 - self.x0) * (self.z1 - self.z0)).abs())
+    }
+}
+
 pub struct YzRect {
     y0: f32,
     y1: f32,
@@ -132,7 +158,7 @@ impl YzRect {
 }
 
 impl Hitable for YzRect {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let t = (self.k - ray.origin.x) / ray.direction.x;
         
         if t < t_min || t > t_max {
@@ -153,8 +179,13 @@ impl Hitable for YzRect {
             &Vec3::new(1., 0., 0.), 
             (y - self.y0) / (self.y1 - self.y0), 
             (z - self.z0) / (self.z1 - self.z0), 
-            &*self.material);
-            
+            &*self.material)
+            .with_tangents(Vec3::new(0., self.y1 - self.y0, 0.), Vec3::new(0., 0., self.z1 - self.z0));
+
+        if !self.material.alpha_test(&hit_record) {
+            return None;
+        }
+
         Some(hit_record)
     }
 
@@ -166,4 +197,12 @@ impl Hitable for YzRect {
             }
         )
     }
-}
\ No newline at end of file
+}
+
+impl Surface for YzRect {
+    fn point_at(&self, u: f32, v: f32) -> (Vec3, Vec3, f32) {
+        let p = Vec3::new(self.k, self.y0 + u * (self.y1 - self.y0), self.z0 + v * (self.z1 - self.z0));
+
+        (p, Vec3::new(1., 0., 0.), ((self.y1 - self.y0) * (self.z1 - self.z0)).abs())
+    }
+}
@@ -67,7 +67,7 @@ impl RectBox {
 }
 
 impl Hitable for RectBox {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         self.hitable.hit(ray, t_min, t_max)
     }
 
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 use std::f32::consts::{PI, FRAC_PI_2};
 
-use crate::hitable::Hitable;
+use crate::hitable::{Hitable, Surface};
 use crate::structures::ray::Ray;
 use crate::structures::vec3::Vec3;
 use crate::materials::{Material, HitRecord};
@@ -26,10 +26,20 @@ impl Sphere{
 
         (u, v)
     }
+
+    // derivatives of the surface position along the u and v of `get_sphere_uv`
+    pub fn get_sphere_tangents(p: Vec3, radius: f32) -> (Vec3, Vec3) {
+        let phi = p.z.atan2(p.x);
+        let rho = (p.x * p.x + p.z * p.z).sqrt();
+        let dpdu = 2.0 * PI * radius * Vec3::new(p.z, 0.0, -p.x);
+        let dpdv = PI * radius * Vec3::new(-p.y * phi.cos(), rho, -p.y * phi.sin());
+
+        (dpdu, dpdv)
+    }
 }
 
 impl Hitable for Sphere {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
         let oc = ray.origin - self.center;
         // optimized version of the quadratic formular components
         // see: https://raytracing.github.io/books/RayTracingInOneWeekend.html#surfacenormalsandmultipleobjects/simplifyingtheray-sphereintersectioncode
@@ -44,24 +54,20 @@ impl Hitable for Sphere {
         if discriminant > 0.0 {
             let root = discriminant.sqrt();
 
-            let temp_t = (-half_b - root) / a;
-            if temp_t < t_max && temp_t > t_min {
-                let hit_point = ray.at(temp_t);
-                let outward_normal = (hit_point - self.center) / self.radius;
-                let (u, v) = Sphere::get_sphere_uv((hit_point - self.center) / self.radius);
-                let hit_record = HitRecord::new(hit_point, temp_t, ray, &outward_normal, u, v, &*self.material);
-
-                return Some(hit_record);
-            }
-
-            let temp_t = (-half_b + root) / a;
-            if temp_t < t_max && temp_t > t_min {
-                let hit_point = ray.at(temp_t);
-                let outward_normal = (hit_point - self.center) / self.radius;
-                let (u, v) = Sphere::get_sphere_uv((hit_point - self.center) / self.radius);
-                let hit_record = HitRecord::new(hit_point, temp_t, ray, &outward_normal, u, v, &*self.material);
+            // a cut out near side lets the ray continue to the far side
+            for &temp_t in &[(-half_b - root) / a, (-half_b + root) / a] {
+                if temp_t < t_max && temp_t > t_min {
+                    let hit_point = ray.at(temp_t);
+                    let outward_normal = (hit_point - self.center) / self.radius;
+                    let (u, v) = Sphere::get_sphere_uv(outward_normal);
+                    let (dpdu, dpdv) = Sphere::get_sphere_tangents(outward_normal, self.radius);
+                    let hit_record = HitRecord::new(hit_point, temp_t, ray, &outward_normal, u, v, &*self.material)
+                        .with_tangents(dpdu, dpdv);
 
-                return Some(hit_record);
+                    if self.material.alpha_test(&hit_record) {
+                        return Some(hit_record);
+                    }
+                }
             } 
         }
 
@@ -74,4 +80,15 @@ impl Hitable for Sphere {
             max: self.center + Vec3 { x: self.radius, y: self.radius, z: self.radius },
         })
     }
-}
\ No newline at end of file
+}
+
+impl Surface for Sphere {
+    fn point_at(&self, u: f32, v: f32) -> (Vec3, Vec3, f32) {
+        // inverse of `get_sphere_uv`
+        let phi = (1.0 - u) * 2.0 * PI - PI;
+        let theta = v * PI - FRAC_PI_2;
+        let normal = Vec3::new(theta.cos() * phi.cos(), theta.sin(), theta.cos() * phi.sin());
+
+        (self.center + self.radius * normal, normal, 2.0 * PI * PI * self.radius * self.radius * theta.cos())
+    }
+}
@@ -0,0 +1,204 @@
+use std::sync::Arc;
+
+use crate::hitable::Hitable;
+use crate::structures::ray::Ray;
+use crate::structures::vec3::{Vec3, random_unit_vector};
+use crate::materials::{Material, HitRecord, ScatterRecord};
+use crate::materials::microfacet::fresnel_dielectric;
+use crate::materials::subsurface::Subsurface;
+use crate::color::{Color, BLACK};
+use crate::aabb::AABB;
+use crate::random::random_double;
+
+const MAX_STEPS: usize = 256;
+
+// fills a closed boundary with the medium of a `Subsurface` material. Hits
+// are the plain hits on the boundary, a ray that reaches the boundary from
+// inside is scattered by a random walk through the medium, using the
+// boundary's own intersection to find where it leaves.
+pub struct SubsurfaceVolume {
+    medium: Medium,
+}
+
+impl SubsurfaceVolume {
+    pub fn new(boundary: impl Hitable + 'static, material: Arc<Subsurface>) -> Self {
+        SubsurfaceVolume { medium: Medium { boundary: Box::new(boundary), material } }
+    }
+}
+
+impl Hitable for SubsurfaceVolume {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
+        let hit_record = self.medium.boundary.hit(ray, t_min, t_max)?;
+
+        Some(HitRecord { material: &self.medium, ..hit_record })
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+        self.medium.boundary.bounding_box(t0, t1)
+    }
+}
+
+// the interface of the material seen from outside and the walk inside
+struct Medium {
+    boundary: Box<dyn Hitable>,
+    material: Arc<Subsurface>,
+}
+
+fn channel(color: &Color, channel: usize) -> f32 {
+    match channel {
+        0 => color.r,
+        1 => color.g,
+        _ => color.b,
+    }
+}
+
+impl Material for Medium {
+    // the walk starts where the ray entered and ends on the boundary, each
+    // walk tracks a single color channel picked with probability 1/3, so the
+    // light leaves with that channel only
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> Option<ScatterRecord> {
+        // only rays reaching a back face are inside the medium
+        if hit_record.front_face {
+            return self.material.scatter(ray_in, hit_record);
+        }
+
+        let c = (random_double() * 3.0).min(2.0) as usize;
+        let sigma_t = channel(&self.material.sigma_t, c);
+        let albedo = channel(&self.material.single_scatter_albedo, c);
+
+        let mut origin = ray_in.origin;
+        let mut direction = ray_in.direction.normalize();
+        let mut boundary = Some(*hit_record);
+
+        for _ in 0..MAX_STEPS {
+            let walk_ray = Ray::new(origin, direction, ray_in.time);
+            let exit = match boundary.take() {
+                Some(hit_record) => hit_record,
+                // the boundary is not closed
+                None => self.boundary.hit(&walk_ray, 0.0001, f32::INFINITY)?,
+            };
+
+            let distance = -(1.0 - random_double()).ln() / sigma_t;
+
+            if distance < (exit.p - origin).length() {
+                if random_double() >= albedo {
+                    return None;
+                }
+
+                origin = walk_ray.at(distance);
+                direction = random_unit_vector();
+                continue;
+            }
+
+            // internal reflection keeps the walk going
+            let cos_theta = (-direction).dot(&exit.normal).max(0.0);
+            if random_double() < fresnel_dielectric(cos_theta, 1.0 / self.material.ior) {
+                origin = exit.p;
+                direction = Vec3::reflect(&direction, &exit.normal);
+                continue;
+            }
+
+            // the radiance inside is close to isotropic, so the light refracted
+            // out is approximated with a cosine distribution around the
+            // outward normal
+            let outward = -exit.normal;
+            let scattered = outward + random_unit_vector();
+            let scattered = if scattered.near_zero() { outward } else { scattered };
+
+            let mut weight = BLACK;
+            match c {
+                0 => weight.r = 3.0,
+                1 => weight.g = 3.0,
+                _ => weight.b = 3.0,
+            }
+
+            return Some(ScatterRecord::specular(weight, Ray::new(exit.p, scattered, ray_in.time)));
+        }
+
+        None
+    }
+
+    fn emitted(&self, _u: f32, _v: f32, _hit_record: &HitRecord) -> Color {
+        BLACK
+    }
+
+    fn albedo(&self, ray_in: &Ray, hit_record: &HitRecord) -> Color {
+        self.material.albedo(ray_in, hit_record)
+    }
+
+    fn diffuse_fraction(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> f32 {
+        if hit_record.front_face { self.material.diffuse_fraction(ray_in, hit_record, direction) } else { 1.0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::color::WHITE;
+    use crate::objects::sphere::Sphere;
+
+    // the average weight leaving a unit sphere for walks starting at its center
+    fn transmitted(material: Subsurface) -> Color {
+        let material = Arc::new(material);
+        let volume = SubsurfaceVolume::new(Sphere::new(Vec3::ZERO, 1.0, material.clone()), material);
+        let walks = 30_000;
+
+        let mut sum = BLACK;
+        for _ in 0..walks {
+            let ray = Ray::new(Vec3::ZERO, random_unit_vector(), 0.0);
+            let hit_record = volume.hit(&ray, 0.001, f32::INFINITY).unwrap();
+
+            if let Some(scatter_record) = hit_record.material.scatter(&ray, &hit_record) {
+                sum = sum + scatter_record.attenuation;
+            }
+        }
+
+        (1.0 / walks as f32) * sum
+    }
+
+    #[test]
+    fn hits_are_on_the_boundary() {
+        let material = Arc::new(Subsurface::new(WHITE, Color::new(0.1, 0.1, 0.1), 1.5));
+        let volume = SubsurfaceVolume::new(Sphere::new(Vec3::ZERO, 1.0, material.clone()), material);
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -2.0), 0.0);
+
+        let front = volume.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        assert!(front.front_face && (ray.at(front.t) - front.p).length() < 1e-5);
+
+        let inside = Ray::new(Vec3::new(0.0, 0.0, 0.5), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let back = volume.hit(&inside, 0.001, f32::INFINITY).unwrap();
+        assert!(!back.front_face && (back.t - 1.5).abs() < 1e-5);
+
+        // a single material for the whole object
+        let id = |material: &dyn Material| material as *const dyn Material as *const () as usize;
+        assert_eq!(id(front.material), id(back.material));
+    }
+
+    #[test]
+    fn the_albedo_is_the_configured_color() {
+        let jade = Color::new(0.3, 0.8, 0.5);
+        let material = Arc::new(Subsurface::new(jade, Color::new(0.1, 0.1, 0.1), 1.5));
+        let volume = SubsurfaceVolume::new(Sphere::new(Vec3::ZERO, 1.0, material.clone()), material);
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+
+        let hit_record = volume.hit(&ray, 0.001, f32::INFINITY).unwrap();
+        let albedo = hit_record.material.albedo(&ray, &hit_record);
+        assert!(albedo.r == jade.r && albedo.g == jade.g && albedo.b == jade.b);
+    }
+
+    #[test]
+    fn walks_without_absorption_conserve_energy() {
+        // a matched interface lets every walk out at the first crossing
+        let color = transmitted(Subsurface { ior: 1.0, sigma_t: Color::new(2.0, 4.0, 8.0), single_scatter_albedo: WHITE, albedo: WHITE });
+
+        assert!((color.r - 1.0).abs() < 0.05 && (color.g - 1.0).abs() < 0.05 && (color.b - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn walks_in_a_black_medium_are_absorbed() {
+        let color = transmitted(Subsurface::new(BLACK, Color::new(0.001, 0.001, 0.001), 1.5));
+
+        assert!(color.r + color.g + color.b < 0.001);
+    }
+}
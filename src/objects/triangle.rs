@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use crate::hitable::Hitable;
 use crate::structures::vec3::Vec3;
+use crate::structures::onb::Onb;
 use crate::structures::ray::Ray;
 use crate::materials::{Material, HitRecord};
 use crate::aabb::AABB;
@@ -12,17 +13,44 @@ pub struct Triangle {
     vertex0: Vec3,
     vertex1: Vec3,
     vertex2: Vec3,
+    uv0: (f32, f32),
+    uv1: (f32, f32),
+    uv2: (f32, f32),
     material: Arc<dyn Material>,
 }
 
 impl Triangle {
     pub fn new(vertex0: Vec3, vertex1: Vec3, vertex2: Vec3, material: Arc<dyn Material>) -> Self {
-        Triangle {vertex0, vertex1, vertex2, material}
+        // without texture coordinates u and v are the barycentric coordinates
+        Triangle {vertex0, vertex1, vertex2, uv0: (0.0, 0.0), uv1: (1.0, 0.0), uv2: (0.0, 1.0), material}
+    }
+
+    pub fn with_uvs(self, uv0: (f32, f32), uv1: (f32, f32), uv2: (f32, f32)) -> Self {
+        Triangle { uv0, uv1, uv2, ..self }
+    }
+
+    // derivatives of the position along the texture coordinates, falls back
+    // to an arbitrary frame when the coordinates are degenerate
+    fn tangents(&self, normal: &Vec3) -> (Vec3, Vec3) {
+        let (du02, dv02) = (self.uv0.0 - self.uv2.0, self.uv0.1 - self.uv2.1);
+        let (du12, dv12) = (self.uv1.0 - self.uv2.0, self.uv1.1 - self.uv2.1);
+        let dp02 = self.vertex0 - self.vertex2;
+        let dp12 = self.vertex1 - self.vertex2;
+        let determinant = du02 * dv12 - dv02 * du12;
+
+        if determinant.abs() < 1e-8 {
+            let onb = Onb::from_w(normal);
+            return (onb.u, onb.v);
+        }
+
+        let inv_determinant = 1.0 / determinant;
+
+        ((dv12 * dp02 - dv02 * dp12) * inv_determinant, (du02 * dp12 - du12 * dp02) * inv_determinant)
     }
 }
 
 impl Hitable for Triangle {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord<'_>> {
          
         let edge1 = self.vertex1 - self.vertex0;
         let edge2 = self.vertex2 - self.vertex0;
@@ -38,7 +66,7 @@ impl Hitable for Triangle {
         let s = ray.origin - self.vertex0;
         let u = f * s.dot(&h);
 
-        if u < 0.0 || u > 1.0 {
+        if !(0.0..=1.0).contains(&u) {
             return None;
         }
 
@@ -53,10 +81,17 @@ impl Hitable for Triangle {
         if t < t_max && t > t_min {
             let hit_point = ray.at(t);
             let normal = edge1.cross(&edge2).normalize();
+            let w = 1.0 - u - v;
+            let tex_u = w * self.uv0.0 + u * self.uv1.0 + v * self.uv2.0;
+            let tex_v = w * self.uv0.1 + u * self.uv1.1 + v * self.uv2.1;
+            let (dpdu, dpdv) = self.tangents(&normal);
+
+            let hit_record = HitRecord::new(hit_point, t, ray, &normal, tex_u, tex_v, &*self.material)
+                .with_tangents(dpdu, dpdv);
 
-            return Some( // TODO: add u & v
-                HitRecord::new(hit_point, t, ray, &normal, 0.0, 0.0, &*self.material)
-            );
+            if self.material.alpha_test(&hit_record) {
+                return Some(hit_record);
+            }
         }
 
         None
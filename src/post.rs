@@ -0,0 +1,73 @@
+pub mod bloom;
+pub mod vignette;
+pub mod chromatic_aberration;
+pub mod grain;
+pub mod white_balance;
+pub mod lut;
+
+use crate::color::Color;
+
+// An image operation on the linear float framebuffer, rows from the top.
+// Effects must give the same result for the same input on every run.
+pub trait Effect: Sync + Send {
+    fn apply(&self, width: u32, height: u32, pixels: &[Color]) -> Vec<Color>;
+}
+
+// effects applied one after the other to the beauty image before it is
+// written as an 8 bit image
+#[derive(Default)]
+pub struct PostProcess {
+    effects: Vec<Box<dyn Effect>>,
+}
+
+impl PostProcess {
+    pub fn new() -> Self {
+        PostProcess::default()
+    }
+
+    pub fn with_effect(mut self, effect: impl Effect + 'static) -> Self {
+        self.effects.push(Box::new(effect));
+        self
+    }
+
+    pub fn push(&mut self, effect: impl Effect + 'static) {
+        self.effects.push(Box::new(effect));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+
+    pub fn apply(&self, width: u32, height: u32, pixels: &[Color]) -> Vec<Color> {
+        self.effects.iter().fold(pixels.to_vec(), |pixels, effect| effect.apply(width, height, &pixels))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::post::bloom::Bloom;
+    use crate::post::grain::FilmGrain;
+    use crate::post::vignette::Vignette;
+
+    #[test]
+    fn chain_is_deterministic() {
+        let (width, height) = (16, 8);
+        let pixels: Vec<Color> = (0..width * height).map(|i| Color::new(i as f32 / 10.0, 0.5, 0.25)).collect();
+
+        let chain = |seed| PostProcess::new()
+            .with_effect(Bloom::new(1.0, 0.1))
+            .with_effect(Vignette::new(0.5))
+            .with_effect(FilmGrain::new(0.05).with_seed(seed));
+
+        let first = chain(1).apply(width, height, &pixels);
+        let second = chain(1).apply(width, height, &pixels);
+        assert!(first.iter().zip(second.iter()).all(|(a, b)| a.r == b.r && a.g == b.g && a.b == b.b));
+
+        let other = chain(2).apply(width, height, &pixels);
+        assert!(first.iter().zip(other.iter()).any(|(a, b)| a.g != b.g));
+
+        assert_eq!(PostProcess::new().apply(width, height, &pixels).len(), pixels.len());
+    }
+}
@@ -0,0 +1,109 @@
+use rayon::prelude::*;
+
+use crate::color::{Color, BLACK};
+use crate::post::Effect;
+
+// Light scattered in the lens and the eye around bright parts of the image.
+// Everything above the threshold is blurred at three scales and added back,
+// the wider blurs give the long faint tail of glare.
+pub struct Bloom {
+    pub threshold: f32,
+    pub intensity: f32,
+    // standard deviation of the narrowest blur as a fraction of the image width
+    pub radius: f32,
+}
+
+// relative weights of the blurs at 1, 4 and 16 times the radius
+const SCALES: [(f32, f32); 3] = [(1.0, 0.6), (4.0, 0.3), (16.0, 0.1)];
+
+impl Bloom {
+    pub fn new(threshold: f32, intensity: f32) -> Self {
+        Bloom { threshold, intensity, radius: 0.005 }
+    }
+
+    pub fn with_radius(self, radius: f32) -> Self {
+        Bloom { radius, ..self }
+    }
+}
+
+impl Effect for Bloom {
+    fn apply(&self, width: u32, height: u32, pixels: &[Color]) -> Vec<Color> {
+        let bright: Vec<Color> = pixels.iter()
+            .map(|c| Color::new((c.r - self.threshold).max(0.0), (c.g - self.threshold).max(0.0), (c.b - self.threshold).max(0.0)))
+            .collect();
+
+        let mut result = pixels.to_vec();
+
+        for (scale, weight) in SCALES.iter() {
+            let sigma = scale * self.radius * width as f32;
+            let blurred = blur(width as usize, height as usize, &bright, sigma);
+
+            for (pixel, glow) in result.iter_mut().zip(blurred.iter()) {
+                *pixel = *pixel + (self.intensity * weight) * *glow;
+            }
+        }
+
+        result
+    }
+}
+
+// separable gaussian blur, the edges are extended
+fn blur(width: usize, height: usize, pixels: &[Color], sigma: f32) -> Vec<Color> {
+    if sigma < 0.5 {
+        return pixels.to_vec();
+    }
+
+    let radius = (3.0 * sigma).ceil() as i64;
+    let kernel: Vec<f32> = (-radius..=radius).map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp()).collect();
+    let total: f32 = kernel.iter().sum();
+    let kernel: Vec<f32> = kernel.iter().map(|k| k / total).collect();
+
+    let convolve = |pixels: &[Color], horizontal: bool| -> Vec<Color> {
+        (0..pixels.len()).into_par_iter().map(|index| {
+            let (x, y) = ((index % width) as i64, (index / width) as i64);
+
+            kernel.iter().enumerate().fold(BLACK, |sum, (k, weight)| {
+                let offset = k as i64 - radius;
+                let q = if horizontal {
+                    y as usize * width + (x + offset).clamp(0, width as i64 - 1) as usize
+                } else {
+                    (y + offset).clamp(0, height as i64 - 1) as usize * width + x as usize
+                };
+
+                sum + *weight * pixels[q]
+            })
+        })
+        .collect()
+    };
+
+    convolve(&convolve(pixels, true), false)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::color::WHITE;
+
+    #[test]
+    fn only_light_above_the_threshold_spreads() {
+        let (width, height) = (64, 64);
+        let mut pixels = vec![0.5 * WHITE; width * height];
+        pixels[32 * width + 32] = 1000.0 * WHITE;
+
+        let bloom = Bloom::new(1.0, 0.1).with_radius(0.02);
+        let result = bloom.apply(width as u32, height as u32, &pixels);
+
+        // the glow is added on top and falls off with the distance
+        assert!(result[32 * width + 34].r > result[32 * width + 40].r);
+        assert!(result[32 * width + 40].r > 0.5);
+
+        // the energy of the glow is the intensity times the excess light
+        let added: f32 = result.iter().zip(pixels.iter()).map(|(a, b)| a.r - b.r).sum();
+        assert!((added - 0.1 * 999.0).abs() < 0.1 * 999.0 * 0.05);
+
+        // nothing changes without bright pixels
+        let dim = vec![0.5 * WHITE; width * height];
+        assert!(bloom.apply(width as u32, height as u32, &dim).iter().all(|c| (c.g - 0.5).abs() < 1e-6));
+    }
+}
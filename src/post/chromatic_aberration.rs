@@ -0,0 +1,50 @@
+use crate::color::Color;
+use crate::post::Effect;
+use crate::utils;
+
+// Lateral chromatic aberration, the lens magnifies red slightly more and
+// blue slightly less than green. The strength is the relative difference in
+// magnification, so colors split more towards the edges of the frame.
+pub struct ChromaticAberration {
+    pub strength: f32,
+}
+
+impl ChromaticAberration {
+    pub fn new(strength: f32) -> Self {
+        ChromaticAberration { strength }
+    }
+}
+
+impl Effect for ChromaticAberration {
+    fn apply(&self, width: u32, height: u32, pixels: &[Color]) -> Vec<Color> {
+        let (center_x, center_y) = (0.5 * width as f32, 0.5 * height as f32);
+
+        (0..pixels.len()).map(|index| {
+            let x = (index % width as usize) as f32 + 0.5 - center_x;
+            let y = (index / width as usize) as f32 + 0.5 - center_y;
+
+            // a magnified channel shows what is closer to the center
+            let red = sample(width, height, pixels, center_x + x / (1.0 + self.strength), center_y + y / (1.0 + self.strength));
+            let blue = sample(width, height, pixels, center_x + x / (1.0 - self.strength), center_y + y / (1.0 - self.strength));
+
+            Color::new(red.r, pixels[index].g, blue.b)
+        })
+        .collect()
+    }
+}
+
+// bilinear lookup at a position in pixels, the edges are extended
+fn sample(width: u32, height: u32, pixels: &[Color], x: f32, y: f32) -> Color {
+    let x = utils::clamp(x - 0.5, 0.0, (width - 1) as f32);
+    let y = utils::clamp(y - 0.5, 0.0, (height - 1) as f32);
+
+    let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+    let (x1, y1) = ((x0 + 1).min(width as usize - 1), (y0 + 1).min(height as usize - 1));
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+
+    let at = |x: usize, y: usize| pixels[y * width as usize + x];
+    let top = (1.0 - fx) * at(x0, y0) + fx * at(x1, y0);
+    let bottom = (1.0 - fx) * at(x0, y1) + fx * at(x1, y1);
+
+    (1.0 - fy) * top + fy * bottom
+}
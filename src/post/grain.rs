@@ -0,0 +1,45 @@
+use crate::color::Color;
+use crate::post::Effect;
+
+// Monochrome film grain. The noise comes from a hash of the pixel and the
+// seed instead of a random generator, so the same seed always gives the same
+// grain. The intensity is the standard deviation relative to the pixel.
+pub struct FilmGrain {
+    pub intensity: f32,
+    pub seed: u32,
+}
+
+impl FilmGrain {
+    pub fn new(intensity: f32) -> Self {
+        FilmGrain { intensity, seed: 0 }
+    }
+
+    pub fn with_seed(self, seed: u32) -> Self {
+        FilmGrain { seed, ..self }
+    }
+}
+
+impl Effect for FilmGrain {
+    fn apply(&self, _width: u32, _height: u32, pixels: &[Color]) -> Vec<Color> {
+        pixels.iter().enumerate().map(|(index, color)| {
+            // the sum of uniform values is close to a gaussian, four of them
+            // have a variance of 1/3
+            let noise: f32 = (0..4u32)
+                .map(|i| hash(index as u32 ^ hash(self.seed.wrapping_mul(4).wrapping_add(i))) as f32 / u32::MAX as f32 - 0.5)
+                .sum();
+
+            (1.0 + self.intensity * 3f32.sqrt() * noise).max(0.0) * *color
+        })
+        .collect()
+    }
+}
+
+// the lowbias32 integer hash of Chris Wellons
+fn hash(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb_352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846c_a68b);
+    x ^= x >> 16;
+    x
+}
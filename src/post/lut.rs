@@ -0,0 +1,41 @@
+use crate::color::{Color, encode_srgb, srgb_to_linear};
+use crate::io::cube::CubeLut;
+use crate::post::Effect;
+
+// A color grade from a 3D lookup table. Most tables are made for sRGB encoded
+// display values, so by default the image is encoded before the lookup and
+// decoded after it. Such tables clip at white and belong after the tone
+// mapping in the chain.
+pub struct ColorLut {
+    lut: CubeLut,
+    pub linear: bool,
+}
+
+impl ColorLut {
+    pub fn new(lut: CubeLut) -> Self {
+        ColorLut { lut, linear: false }
+    }
+
+    pub fn load(path: &str) -> Self {
+        ColorLut::new(CubeLut::load(path))
+    }
+
+    // for tables that take linear values
+    pub fn linear(self) -> Self {
+        ColorLut { linear: true, ..self }
+    }
+}
+
+impl Effect for ColorLut {
+    fn apply(&self, _width: u32, _height: u32, pixels: &[Color]) -> Vec<Color> {
+        pixels.iter().map(|color| {
+            if self.linear {
+                self.lut.lookup(*color)
+            } else {
+                let graded = self.lut.lookup(encode_srgb(*color));
+                Color::new(srgb_to_linear(graded.r), srgb_to_linear(graded.g), srgb_to_linear(graded.b))
+            }
+        })
+        .collect()
+    }
+}
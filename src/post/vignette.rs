@@ -0,0 +1,35 @@
+use crate::color::Color;
+use crate::post::Effect;
+
+// Darkening towards the corners of the frame. At the corners the image is
+// scaled by one minus the strength, the falloff shapes how fast it gets there.
+pub struct Vignette {
+    pub strength: f32,
+    pub falloff: f32,
+}
+
+impl Vignette {
+    pub fn new(strength: f32) -> Self {
+        Vignette { strength, falloff: 2.0 }
+    }
+
+    pub fn with_falloff(self, falloff: f32) -> Self {
+        Vignette { falloff, ..self }
+    }
+}
+
+impl Effect for Vignette {
+    fn apply(&self, width: u32, height: u32, pixels: &[Color]) -> Vec<Color> {
+        let (center_x, center_y) = (0.5 * width as f32, 0.5 * height as f32);
+        let corner = (center_x * center_x + center_y * center_y).sqrt();
+
+        pixels.iter().enumerate().map(|(index, color)| {
+            let x = (index % width as usize) as f32 + 0.5 - center_x;
+            let y = (index / width as usize) as f32 + 0.5 - center_y;
+            let r = (x * x + y * y).sqrt() / corner;
+
+            (1.0 - self.strength * r.powf(self.falloff)).max(0.0) * *color
+        })
+        .collect()
+    }
+}
@@ -0,0 +1,128 @@
+use crate::color::{Color, transform};
+use crate::post::Effect;
+use crate::utils;
+
+// Neutralizes light of the given color temperature in kelvin, like the white
+// balance of a camera. A low temperature cools down a warm image and a high
+// one warms up a cool image, 6500K leaves it unchanged. Positive tint
+// removes a green cast, negative a magenta one. The chromatic adaptation is
+// the Bradford transform.
+pub struct WhiteBalance {
+    pub temperature: f32,
+    pub tint: f32,
+    matrix: [[f32; 3]; 3],
+}
+
+const NEUTRAL: f32 = 6500.0;
+
+const SRGB_TO_XYZ: [[f32; 3]; 3] = [
+    [0.412_456_4, 0.357_576_1, 0.180_437_5],
+    [0.212_672_9, 0.715_152_2, 0.072_175_0],
+    [0.019_333_9, 0.119_192, 0.950_304_1],
+];
+const XYZ_TO_SRGB: [[f32; 3]; 3] = [
+    [3.240_454_2, -1.537_138_5, -0.498_531_4],
+    [-0.969_266, 1.876_010_8, 0.041_556],
+    [0.055_643_4, -0.204_025_9, 1.057_225_2],
+];
+const BRADFORD: [[f32; 3]; 3] = [
+    [0.8951, 0.2664, -0.1614],
+    [-0.7502, 1.7135, 0.0367],
+    [0.0389, -0.0685, 1.0296],
+];
+const BRADFORD_INVERSE: [[f32; 3]; 3] = [
+    [0.986_992_9, -0.147_054_3, 0.159_962_7],
+    [0.432_305_3, 0.518_360_3, 0.049_291_2],
+    [-0.008_528_7, 0.040_042_8, 0.968_486_7],
+];
+
+impl WhiteBalance {
+    pub fn new(temperature: f32) -> Self {
+        WhiteBalance::with_tint(temperature, 0.0)
+    }
+
+    pub fn with_tint(temperature: f32, tint: f32) -> Self {
+        let cone_response = |temperature: f32, tint: f32| transform(BRADFORD, white_point(temperature, tint));
+        let (source, target) = (cone_response(temperature, tint), cone_response(NEUTRAL, 0.0));
+        let scale = [[target.r / source.r, 0.0, 0.0], [0.0, target.g / source.g, 0.0], [0.0, 0.0, target.b / source.b]];
+
+        // back to sRGB after scaling the cone responses of the source white
+        // to those of the neutral one
+        let matrix = [BRADFORD_INVERSE, scale, BRADFORD, SRGB_TO_XYZ].iter()
+            .fold(XYZ_TO_SRGB, |product, m| multiply(product, *m));
+
+        WhiteBalance { temperature, tint, matrix }
+    }
+}
+
+impl Effect for WhiteBalance {
+    fn apply(&self, _width: u32, _height: u32, pixels: &[Color]) -> Vec<Color> {
+        pixels.iter().map(|color| transform(self.matrix, *color)).collect()
+    }
+}
+
+fn multiply(a: [[f32; 3]; 3], b: [[f32; 3]; 3]) -> [[f32; 3]; 3] {
+    let mut product = [[0.0; 3]; 3];
+    for (i, row) in product.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    product
+}
+
+// XYZ of a black body with unit luminance
+fn white_point(temperature: f32, tint: f32) -> Color {
+    let (x, y) = planckian_locus(temperature);
+    let y = y + 0.01 * tint;
+
+    Color::new(x / y, 1.0, (1.0 - x - y) / y)
+}
+
+// xy chromaticity of a black body, the cubic fit of Kim et al. 2002 that is
+// valid from 1667K to 25000K
+fn planckian_locus(temperature: f32) -> (f32, f32) {
+    let t = utils::clamp(temperature, 1667.0, 25000.0) as f64;
+    let (t2, t3) = (t * t, t * t * t);
+
+    let x = if t <= 4000.0 {
+        -0.266_123_9e9 / t3 - 0.234_358_9e6 / t2 + 0.877_695_6e3 / t + 0.179_910
+    } else {
+        -3.025_846_9e9 / t3 + 2.107_037_9e6 / t2 + 0.222_634_7e3 / t + 0.240_390
+    };
+    let (x2, x3) = (x * x, x * x * x);
+
+    let y = if t <= 2222.0 {
+        -1.106_381_4 * x3 - 1.348_110_20 * x2 + 2.185_558_32 * x - 0.202_196_83
+    } else if t <= 4000.0 {
+        -0.954_947_6 * x3 - 1.374_185_93 * x2 + 2.091_370_15 * x - 0.167_488_67
+    } else {
+        3.081_758_0 * x3 - 5.873_386_70 * x2 + 3.751_129_97 * x - 0.370_014_83
+    };
+
+    (x as f32, y as f32)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::color::WHITE;
+
+    #[test]
+    fn light_of_the_temperature_becomes_white() {
+        let neutral = WhiteBalance::new(NEUTRAL).apply(1, 1, &[Color::new(0.2, 0.5, 0.8)])[0];
+        assert!((neutral.r - 0.2).abs() < 1e-4 && (neutral.g - 0.5).abs() < 1e-4 && (neutral.b - 0.8).abs() < 1e-4);
+
+        // a white surface under tungsten light looks like the neutral white
+        let tungsten = transform(XYZ_TO_SRGB, white_point(3000.0, 0.0));
+        let neutral = transform(XYZ_TO_SRGB, white_point(NEUTRAL, 0.0));
+        let balanced = WhiteBalance::new(3000.0).apply(1, 1, &[tungsten])[0];
+        assert!((balanced.r / neutral.r - balanced.b / neutral.b).abs() < 1e-3);
+        assert!((balanced.g / neutral.g - balanced.b / neutral.b).abs() < 1e-3);
+
+        // and white surfaces turn blue
+        let cool = WhiteBalance::new(3000.0).apply(1, 1, &[WHITE])[0];
+        assert!(cool.b > cool.g && cool.g > cool.r);
+    }
+}
@@ -0,0 +1,88 @@
+use crate::environment::Environment;
+use crate::film::Filter;
+use crate::hitable::Hitable;
+use crate::lights::Light;
+use crate::materials::HitRecord;
+use crate::post::{Effect, PostProcess};
+use crate::structures::ray::Ray;
+
+// Limits on the light a single camera sample adds, to suppress fireflies
+// from rare paths such as caustics at the cost of some energy. Direct light
+// reaches the camera after one bounce, indirect light after more. The limit
+// is on the largest color channel, light seen directly is never clamped.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SampleClamp {
+    pub direct: Option<f32>,
+    pub indirect: Option<f32>,
+}
+
+// everything that is rendered, the geometry, the lights and the light
+// arriving from outside of it, with the pixel filter of the film and the
+// effects applied to the 8 bit image. Clamping and outlier rejection are
+// off unless set.
+pub struct Scene {
+    pub world: Box<dyn Hitable>,
+    pub lights: Vec<Box<dyn Light>>,
+    pub environment: Option<Box<dyn Environment>>,
+    pub filter: Filter,
+    pub clamp: SampleClamp,
+    pub outlier_rejection: Option<f32>,
+    pub post: PostProcess,
+}
+
+impl Scene {
+    pub fn new(world: impl Hitable + 'static) -> Self {
+        Scene {
+            world: Box::new(world),
+            lights: Vec::new(),
+            environment: None,
+            filter: Filter::default(),
+            clamp: SampleClamp::default(),
+            outlier_rejection: None,
+            post: PostProcess::new(),
+        }
+    }
+
+    pub fn with_light(mut self, light: impl Light + 'static) -> Self {
+        self.lights.push(Box::new(light));
+        self
+    }
+
+    pub fn with_environment(self, environment: impl Environment + 'static) -> Self {
+        Scene { environment: Some(Box::new(environment)), ..self }
+    }
+
+    pub fn with_filter(self, filter: Filter) -> Self {
+        Scene { filter, ..self }
+    }
+
+    pub fn with_clamp(self, clamp: SampleClamp) -> Self {
+        Scene { clamp, ..self }
+    }
+
+    // see `Film::outlier_rejection`
+    pub fn with_outlier_rejection(self, ratio: f32) -> Self {
+        Scene { outlier_rejection: Some(ratio), ..self }
+    }
+
+    pub fn with_effect(mut self, effect: impl Effect + 'static) -> Self {
+        self.post.push(effect);
+        self
+    }
+
+    // the closest hit on the world or on the surface of a light, with the
+    // index of that light
+    pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(HitRecord<'_>, Option<usize>)> {
+        let mut closest = self.world.hit(ray, t_min, t_max).map(|hit_record| (hit_record, None));
+
+        for (i, light) in self.lights.iter().enumerate() {
+            let closest_so_far = closest.as_ref().map_or(t_max, |(hit_record, _)| hit_record.t);
+
+            if let Some(hit_record) = light.hit(ray, t_min, closest_so_far) {
+                closest = Some((hit_record, Some(i)));
+            }
+        }
+
+        closest
+    }
+}
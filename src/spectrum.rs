@@ -0,0 +1,100 @@
+use std::sync::OnceLock;
+
+use crate::color::Color;
+
+pub const LAMBDA_MIN: f32 = 380.0;
+pub const LAMBDA_MAX: f32 = 780.0;
+const LAMBDA_STEP: f32 = 10.0;
+
+// representative wavelengths in nm for the red, green and blue channels
+pub const LAMBDA_RED: f32 = 630.0;
+pub const LAMBDA_GREEN: f32 = 532.0;
+pub const LAMBDA_BLUE: f32 = 465.0;
+
+fn piecewise_gaussian(lambda: f32, mu: f32, sigma_low: f32, sigma_high: f32) -> f32 {
+    let sigma = if lambda < mu { sigma_low } else { sigma_high };
+    let t = (lambda - mu) / sigma;
+
+    (-0.5 * t * t).exp()
+}
+
+// CIE 1931 2 degree color matching functions, using the multi-lobe fit of
+// Wyman, Sloan and Shirley 2013
+pub fn cie_xyz(lambda: f32) -> (f32, f32, f32) {
+    let x = 1.056 * piecewise_gaussian(lambda, 599.8, 37.9, 31.0)
+        + 0.362 * piecewise_gaussian(lambda, 442.0, 16.0, 26.7)
+        - 0.065 * piecewise_gaussian(lambda, 501.1, 20.4, 26.2);
+    let y = 0.821 * piecewise_gaussian(lambda, 568.8, 46.9, 40.5)
+        + 0.286 * piecewise_gaussian(lambda, 530.9, 16.3, 31.1);
+    let z = 1.217 * piecewise_gaussian(lambda, 437.0, 11.8, 36.0)
+        + 0.681 * piecewise_gaussian(lambda, 459.0, 26.0, 13.8);
+
+    (x, y, z)
+}
+
+pub fn xyz_to_linear_srgb(x: f32, y: f32, z: f32) -> Color {
+    Color::new(
+         3.2406 * x - 1.5372 * y - 0.4986 * z,
+        -0.9689 * x + 1.8758 * y + 0.0415 * z,
+         0.0557 * x - 0.2040 * y + 1.0570 * z,
+    )
+}
+
+fn integrate_xyz(spectrum: impl Fn(f32) -> f32) -> Color {
+    let (mut x, mut y, mut z) = (0.0, 0.0, 0.0);
+    let mut lambda = LAMBDA_MIN;
+
+    while lambda <= LAMBDA_MAX {
+        let value = spectrum(lambda);
+        let (cx, cy, cz) = cie_xyz(lambda);
+        x += value * cx;
+        y += value * cy;
+        z += value * cz;
+        lambda += LAMBDA_STEP;
+    }
+
+    xyz_to_linear_srgb(x, y, z)
+}
+
+// converts a reflectance spectrum to linear RGB, normalized so that a
+// constant spectrum maps to the same gray value
+pub fn reflectance_to_rgb(spectrum: impl Fn(f32) -> f32) -> Color {
+    // the integral of the constant spectrum only has to be taken once
+    static WHITE: OnceLock<Color> = OnceLock::new();
+
+    let white = *WHITE.get_or_init(|| integrate_xyz(|_| 1.0));
+    let color = integrate_xyz(spectrum);
+
+    Color::new(color.r / white.r, color.g / white.g, color.b / white.b)
+}
+
+// approximates a spectral curve from values at the red, green and blue
+// wavelengths by linear interpolation
+pub fn rgb_to_wavelength(color: Color, lambda: f32) -> f32 {
+    if lambda <= LAMBDA_BLUE {
+        color.b
+    } else if lambda <= LAMBDA_GREEN {
+        let t = (lambda - LAMBDA_BLUE) / (LAMBDA_GREEN - LAMBDA_BLUE);
+        color.b + t * (color.g - color.b)
+    } else if lambda <= LAMBDA_RED {
+        let t = (lambda - LAMBDA_GREEN) / (LAMBDA_RED - LAMBDA_GREEN);
+        color.g + t * (color.r - color.g)
+    } else {
+        color.r
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn constant_reflectance_is_gray() {
+        let color = reflectance_to_rgb(|_| 0.5);
+
+        assert!((color.r - 0.5).abs() < 0.0001);
+        assert!((color.g - 0.5).abs() < 0.0001);
+        assert!((color.b - 0.5).abs() < 0.0001);
+    }
+}
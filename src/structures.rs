@@ -1,3 +1,5 @@
 pub mod ray;
 pub mod vec3;
 pub mod camera;
+pub mod onb;
+pub mod distribution;
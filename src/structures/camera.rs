@@ -8,13 +8,16 @@ pub struct Camera {
     horizontal: Vec3,
     vertical: Vec3,
     lens_radius: f32,
+    viewport_height: f32,
     u: Vec3,
     v: Vec3,
+    w: Vec3,
     pub time0: f32,
     pub time1: f32,
 }
 
 impl Camera {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(lookfrom: Vec3, lookat: Vec3, vup: Vec3, vfov: f32, aspect_ratio: f32, aperture: f32, focus_dist: f32, time0: f32, time1: f32) -> Camera {
         let theta = vfov.to_radians();
         let h = (theta / 2.0).tan();
@@ -37,13 +40,20 @@ impl Camera {
             vertical,
             lower_left_corner,
             lens_radius,
+            viewport_height,
             u,
             v,
+            w,
             time0,
             time1,
         }
     }
 
+    // angle covered by one pixel, the viewport is at unit distance
+    pub fn pixel_spread(&self, image_height: u32) -> f32 {
+        self.viewport_height / image_height as f32
+    }
+
     pub fn get_ray(&self, s: f32, t: f32) -> Ray {
         let rd = self.lens_radius * random_in_unit_disc();
         let offset = self.u * rd.x + self.v * rd.y;
@@ -53,4 +63,24 @@ impl Camera {
             random_double_bounded(self.time0, self.time1)
         )
     }
-}
\ No newline at end of file
+
+    // distance of `p` in front of the camera along the viewing direction
+    pub fn depth(&self, p: &Vec3) -> f32 {
+        (self.origin - *p).dot(&self.w)
+    }
+
+    // the (s, t) `get_ray` aims at to see `p` through the center of the
+    // lens, None behind the camera
+    pub fn project(&self, p: &Vec3) -> Option<(f32, f32)> {
+        let depth = self.depth(p);
+        if depth <= 0.0 {
+            return None;
+        }
+
+        let center = self.lower_left_corner + 0.5 * self.horizontal + 0.5 * self.vertical;
+        let focus_dist = (self.origin - center).dot(&self.w);
+        let q = self.origin + (focus_dist / depth) * (*p - self.origin) - self.lower_left_corner;
+
+        Some((q.dot(&self.horizontal) / self.horizontal.length_squared(), q.dot(&self.vertical) / self.vertical.length_squared()))
+    }
+}
@@ -0,0 +1,130 @@
+// piecewise constant distribution over [0, 1] proportional to `function`,
+// see pbrt 3rd edition chapter 13.6.7
+pub struct Distribution1D {
+    function: Vec<f32>,
+    cdf: Vec<f32>,
+    integral: f32,
+}
+
+impl Distribution1D {
+    pub fn new(function: Vec<f32>) -> Self {
+        assert!(!function.is_empty());
+
+        let n = function.len() as f32;
+        let mut cdf = Vec::with_capacity(function.len() + 1);
+        cdf.push(0.0);
+        for (i, value) in function.iter().enumerate() {
+            cdf.push(cdf[i] + value.max(0.0) / n);
+        }
+
+        let integral = cdf[function.len()];
+
+        // an all zero function falls back to uniform sampling
+        for (i, value) in cdf.iter_mut().enumerate() {
+            *value = if integral > 0.0 { *value / integral } else { i as f32 / n };
+        }
+
+        Distribution1D { function, cdf, integral }
+    }
+
+    pub fn count(&self) -> usize {
+        self.function.len()
+    }
+
+    pub fn integral(&self) -> f32 {
+        self.integral
+    }
+
+    // the sampled position in [0, 1), its density and the index of its segment
+    pub fn sample(&self, u: f32) -> (f32, f32, usize) {
+        // last segment whose cdf is at most u
+        let index = match self.cdf.partition_point(|value| *value <= u) {
+            0 => 0,
+            i => (i - 1).min(self.count() - 1),
+        };
+
+        let width = self.cdf[index + 1] - self.cdf[index];
+        let offset = if width > 0.0 { (u - self.cdf[index]) / width } else { 0.0 };
+        let x = ((index as f32 + offset) / self.count() as f32).min(1.0 - f32::EPSILON);
+
+        (x, self.pdf_at(index), index)
+    }
+
+    pub fn pdf(&self, x: f32) -> f32 {
+        let index = ((x * self.count() as f32) as usize).min(self.count() - 1);
+
+        self.pdf_at(index)
+    }
+
+    fn pdf_at(&self, index: usize) -> f32 {
+        if self.integral > 0.0 { self.function[index].max(0.0) / self.integral } else { 1.0 }
+    }
+}
+
+// piecewise constant distribution over [0, 1]^2 from a row major grid with
+// `width` columns, sampled by picking a row and then a column within it
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(function: &[f32], width: usize) -> Self {
+        let conditional: Vec<Distribution1D> = function.chunks(width)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+        let marginal = Distribution1D::new(conditional.iter().map(|row| row.integral()).collect());
+
+        Distribution2D { conditional, marginal }
+    }
+
+    // the sampled (x, y) and its density
+    pub fn sample(&self, u1: f32, u2: f32) -> ((f32, f32), f32) {
+        let (y, pdf_y, row) = self.marginal.sample(u2);
+        let (x, pdf_x, _) = self.conditional[row].sample(u1);
+
+        ((x, y), pdf_x * pdf_y)
+    }
+
+    pub fn pdf(&self, x: f32, y: f32) -> f32 {
+        let row = ((y * self.marginal.count() as f32) as usize).min(self.marginal.count() - 1);
+
+        self.marginal.pdf(y) * self.conditional[row].pdf(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn samples_follow_the_function() {
+        let distribution = Distribution1D::new(vec![1.0, 0.0, 3.0]);
+
+        assert!((distribution.integral() - 4.0 / 3.0).abs() < 0.0001);
+        assert_eq!(distribution.sample(0.2).2, 0);
+        assert_eq!(distribution.sample(0.3).2, 2);
+        assert!((distribution.pdf(0.9) - 2.25).abs() < 0.0001);
+        assert_eq!(distribution.pdf(0.5), 0.0);
+    }
+
+    #[test]
+    fn pdf_integrates_to_one() {
+        let function: Vec<f32> = (0..12).map(|i| (i % 5) as f32).collect();
+        let distribution = Distribution2D::new(&function, 4);
+
+        let n = 64;
+        let mut integral = 0.0;
+        for j in 0..n {
+            for i in 0..n {
+                integral += distribution.pdf((i as f32 + 0.5) / n as f32, (j as f32 + 0.5) / n as f32);
+            }
+        }
+
+        assert!((integral / (n * n) as f32 - 1.0).abs() < 0.001);
+
+        let ((x, y), pdf) = distribution.sample(0.5, 0.5);
+        assert!((distribution.pdf(x, y) - pdf).abs() < 0.0001);
+    }
+}
@@ -0,0 +1,29 @@
+use crate::structures::vec3::Vec3;
+
+// orthonormal basis around `w`, used to move directions in and out of a
+// local shading frame where the normal is the z axis
+#[derive(Copy, Clone)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    pub fn from_w(n: &Vec3) -> Self {
+        let w = n.normalize();
+        let a = if w.x.abs() > 0.9 { Vec3::new(0.0, 1.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) };
+        let v = w.cross(&a).normalize();
+        let u = w.cross(&v);
+
+        Onb { u, v, w }
+    }
+
+    pub fn local(&self, a: &Vec3) -> Vec3 {
+        a.x * self.u + a.y * self.v + a.z * self.w
+    }
+
+    pub fn to_local(&self, a: &Vec3) -> Vec3 {
+        Vec3::new(a.dot(&self.u), a.dot(&self.v), a.dot(&self.w))
+    }
+}
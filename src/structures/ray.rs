@@ -4,12 +4,19 @@ pub struct Ray {
     pub origin: Vec3,
     pub direction: Vec3,
     pub time: f32,
+    // growth of the ray's footprint per unit distance, used to filter
+    // textures. Only camera rays have one.
+    pub spread: f32,
 }
 
 impl Ray {
 
     pub fn new(origin: Vec3, direction: Vec3, time: f32) -> Ray {
-        Ray { origin, direction, time }
+        Ray { origin, direction, time, spread: 0.0 }
+    }
+
+    pub fn with_spread(self, spread: f32) -> Ray {
+        Ray { spread, ..self }
     }
 
     pub fn at(&self, t: f32) -> Vec3 {
@@ -1,4 +1,4 @@
-use std::ops::{Add, Sub, Mul, Div};
+use std::ops::{Add, Sub, Mul, Div, Neg};
 use std::f32::consts::PI;
 
 use crate::random::random_double_bounded;
@@ -43,6 +43,12 @@ impl Vec3 {
         }
     }
 
+    pub fn near_zero(&self) -> bool {
+        let s = 1e-8;
+
+        self.x.abs() < s && self.y.abs() < s && self.z.abs() < s
+    }
+
     pub fn length(self) -> f32 {
         self.length_squared().sqrt()
     }
@@ -60,9 +66,9 @@ impl Vec3 {
     }
 
     pub fn refract(uv: &Vec3, n: &Vec3, etai_over_etat: f32) -> Vec3 {
-        let cos_theta = (-1.0 * uv).dot(n);
+        let cos_theta = (-uv).dot(n);
         let r_out_perp = etai_over_etat * (uv + cos_theta * n);
-        let r_out_parallel = -1.0 * (1.0 - r_out_perp.length_squared()).abs().sqrt() * n;
+        let r_out_parallel = -(1.0 - r_out_perp.length_squared()).abs().sqrt() * n;
         
         r_out_perp + r_out_parallel
     }
@@ -97,6 +103,20 @@ pub fn random_unit_vector() -> Vec3 {
     }
 }
 
+// cosine weighted direction around +z
+pub fn random_cosine_direction() -> Vec3 {
+    let r1 = random_double_bounded(0.0, 1.0);
+    let r2 = random_double_bounded(0.0, 1.0);
+    let phi = 2.0 * PI * r1;
+    let r = r2.sqrt();
+
+    Vec3 {
+        x: phi.cos() * r,
+        y: phi.sin() * r,
+        z: (1.0 - r2).sqrt(),
+    }
+}
+
 // hemispherical scattering
 // pub fn random_in_hemisphere(normal: Vec3) -> Vec3 {
 //     let in_unit_sphere = random_in_unit_sphere();
@@ -187,6 +207,29 @@ impl Sub<Vec3> for &Vec3 {
     }
 }
 
+impl Neg for Vec3 {
+    type Output = Vec3;
+
+    fn neg(self) -> Vec3 {
+        Vec3 {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+}
+
+impl Neg for &Vec3 {
+    type Output = Vec3;
+
+    fn neg(self) -> Vec3 {
+        Vec3 {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+}
 
 impl Mul<Vec3> for Vec3 {
     type Output = Vec3;
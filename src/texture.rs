@@ -1,9 +1,68 @@
-use crate::color::Color;
+pub mod perlin;
+pub mod procedural;
+pub mod nodes;
+pub mod mipmap;
+
+use std::sync::Arc;
+
+use crate::color::{Color, linear_blend, srgb_to_linear};
+use crate::io::image_file::{self, ImageData};
 use crate::structures::vec3::Vec3;
 use crate::utils;
+use crate::texture::perlin::Perlin;
+use crate::texture::mipmap::{MipMap, ImageFilter, WrapMode};
 
 pub trait Texture: Sync + Send {
     fn color(&self, u: f32, v: f32, p: &Vec3) -> Color;
+
+    // scalar lookup for textures driving parameters like roughness
+    fn value(&self, u: f32, v: f32, p: &Vec3) -> f32 {
+        self.color(u, v, p).r
+    }
+
+    // lookup averaged over a footprint, only image textures filter
+    fn color_filtered(&self, u: f32, v: f32, p: &Vec3, _footprint: &TextureFootprint) -> Color {
+        self.color(u, v, p)
+    }
+}
+
+// extent of a lookup in texture space, the two axes of an ellipse around
+// (u, v). See `HitRecord::texture_footprint`.
+#[derive(Clone, Copy, Default)]
+pub struct TextureFootprint {
+    pub dudx: f32,
+    pub dvdx: f32,
+    pub dudy: f32,
+    pub dvdy: f32,
+}
+
+// lets textures be shared between materials or picked at runtime
+impl<T: Texture + ?Sized> Texture for Box<T> {
+    fn color(&self, u: f32, v: f32, p: &Vec3) -> Color {
+        (**self).color(u, v, p)
+    }
+
+    fn value(&self, u: f32, v: f32, p: &Vec3) -> f32 {
+        (**self).value(u, v, p)
+    }
+
+    fn color_filtered(&self, u: f32, v: f32, p: &Vec3, footprint: &TextureFootprint) -> Color {
+        (**self).color_filtered(u, v, p, footprint)
+    }
+}
+
+impl<T: Texture + ?Sized> Texture for Arc<T> {
+    fn color(&self, u: f32, v: f32, p: &Vec3) -> Color {
+        (**self).color(u, v, p)
+    }
+
+    fn value(&self, u: f32, v: f32, p: &Vec3) -> f32 {
+        (**self).value(u, v, p)
+    }
+
+    fn color_filtered(&self, u: f32, v: f32, p: &Vec3, footprint: &TextureFootprint) -> Color {
+        (**self).color_filtered(u, v, p, footprint)
+    }
 }
 
 pub struct SolidColor {
@@ -31,57 +90,222 @@ impl<T: Texture> CheckerTexture<T> {
     pub fn new(odd: T, even: T) -> Self {
         CheckerTexture { odd, even }
     }
+
+    fn texture_at(&self, p: &Vec3) -> &T {
+        let sines = (p.x * 10.0).sin() * (p.y * 10.0).sin() * (p.z * 10.0).sin();
+
+        if sines < 0.0 { &self.odd } else { &self.even }
+    }
 }
 
 impl<T: Texture> Texture for CheckerTexture<T> {
     fn color(&self, u: f32, v: f32, p: &Vec3) -> Color {
-        let sines = (p.x * 10.0).sin() * (p.y * 10.0).sin() * (p.z * 10.0).sin();
+        self.texture_at(p).color(u, v, p)
+    }
 
-        if sines < 0.0 {
-            self.odd.color(u, v, p)
-        } else {
-            self.even.color(u, v, p)
-        }
+    fn color_filtered(&self, u: f32, v: f32, p: &Vec3, footprint: &TextureFootprint) -> Color {
+        self.texture_at(p).color_filtered(u, v, p, footprint)
+    }
+}
+
+pub struct NoiseTexture {
+    pub noise: Perlin,
+    pub scale: f32,
+    pub octaves: u32,
+}
+
+impl NoiseTexture {
+    pub fn new(scale: f32) -> Self {
+        NoiseTexture { noise: Perlin::default(), scale, octaves: 1 }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn color(&self, u: f32, v: f32, p: &Vec3) -> Color {
+        let value = self.value(u, v, p);
+
+        Color::new(value, value, value)
+    }
+
+    fn value(&self, _u: f32, _v: f32, p: &Vec3) -> f32 {
+        0.5 * (1.0 + self.noise.fbm(&(self.scale * *p), self.octaves))
+    }
+}
+
+// veins along z, distorted by turbulence
+pub struct MarbleTexture {
+    pub noise: Perlin,
+    pub scale: f32,
+    pub octaves: u32,
+    pub distortion: f32,
+    pub base: Color,
+    pub vein: Color,
+}
+
+impl MarbleTexture {
+    pub fn new(scale: f32, base: Color, vein: Color) -> Self {
+        MarbleTexture { noise: Perlin::default(), scale, octaves: 7, distortion: 10.0, base, vein }
+    }
+}
+
+impl Texture for MarbleTexture {
+    fn color(&self, _u: f32, _v: f32, p: &Vec3) -> Color {
+        let turbulence = self.noise.turbulence(p, self.octaves);
+        let t = 0.5 * (1.0 + (self.scale * p.z + self.distortion * turbulence).sin());
+
+        linear_blend(t, self.vein, self.base)
     }
 }
 
+// growth rings around the y axis, distorted by turbulence
+pub struct WoodTexture {
+    pub noise: Perlin,
+    pub scale: f32,
+    pub octaves: u32,
+    pub distortion: f32,
+    pub light: Color,
+    pub dark: Color,
+}
+
+impl WoodTexture {
+    pub fn new(scale: f32, light: Color, dark: Color) -> Self {
+        WoodTexture { noise: Perlin::default(), scale, octaves: 4, distortion: 0.5, light, dark }
+    }
+}
+
+impl Texture for WoodTexture {
+    fn color(&self, _u: f32, _v: f32, p: &Vec3) -> Color {
+        let radius = (p.x * p.x + p.z * p.z).sqrt() * self.scale;
+        let rings = radius + self.distortion * self.noise.turbulence(p, self.octaves);
+
+        // sharp dark edge at the end of each ring
+        let t = rings - rings.floor();
+
+        linear_blend(t * t, self.light, self.dark)
+    }
+}
+
+// how the values of an image are interpreted
+#[derive(Clone, Copy, PartialEq)]
+pub enum ColorSpace {
+    // sRGB encoded colors, decoded to linear on load
+    Srgb,
+    // linear colors, like those of float images
+    Linear,
+    // data that is not a color, like normal or roughness maps
+    Raw,
+}
+
 pub struct ImageTexture {
-    data: Vec<u8>,
-    width: usize,
-    height: usize
+    mipmap: MipMap,
+    filter: ImageFilter,
 }
 
 impl ImageTexture {
-    pub fn new(path: &str) -> Self { 
-        let image = image::open(path).expect("Texture image could not be opened").to_rgb();
-        let (width, height) = image.dimensions();
-        let data = image.into_raw();
-        
-        ImageTexture { data, width: width as usize, height: height as usize } 
+    // float images are read as linear and all others as sRGB
+    pub fn new(path: &str) -> Self {
+        let image = image_file::load(path);
+        let color_space = if image.float { ColorSpace::Linear } else { ColorSpace::Srgb };
+
+        ImageTexture::from_image(image, color_space)
+    }
+
+    pub fn open(path: &str, color_space: ColorSpace) -> Self {
+        ImageTexture::from_image(image_file::load(path), color_space)
+    }
+
+    pub fn read(path: &str, color_space: ColorSpace) -> Result<Self, String> {
+        Ok(ImageTexture::from_image(image_file::read(path)?, color_space))
+    }
+
+    fn from_image(image: ImageData, color_space: ColorSpace) -> Self {
+        let texels = match color_space {
+            ColorSpace::Srgb => image.texels.iter()
+                .map(|c| Color::new(srgb_to_linear(c.r), srgb_to_linear(c.g), srgb_to_linear(c.b)))
+                .collect(),
+            ColorSpace::Linear | ColorSpace::Raw => image.texels,
+        };
+
+        ImageTexture {
+            mipmap: MipMap::new(image.width, image.height, texels, WrapMode::Clamp),
+            filter: ImageFilter::Trilinear,
+        }
+    }
+
+    pub fn with_filter(self, filter: ImageFilter) -> Self {
+        ImageTexture { filter, ..self }
+    }
+
+    // lookups outside [0, 1] are clamped to the edge unless repeat or mirror
+    // wrapping is asked for
+    pub fn with_wrap(mut self, wrap: WrapMode) -> Self {
+        self.mipmap.wrap = wrap;
+        self
     }
 }
 
 impl Texture for ImageTexture {
-    fn color(&self, u: f32, v: f32, _p: &Vec3) -> Color {
-        let u = utils::clamp(u, 0., 1.);
-        let v = 1. - utils::clamp(v, 0., 1.);
+    fn color(&self, u: f32, v: f32, p: &Vec3) -> Color {
+        self.color_filtered(u, v, p, &TextureFootprint::default())
+    }
 
-        let mut i = (u * self.width as f32) as usize;
-        let mut j = (v * self.height as f32) as usize;
+    fn color_filtered(&self, u: f32, v: f32, _p: &Vec3, footprint: &TextureFootprint) -> Color {
+        // images are stored top to bottom
+        let (s, t) = (u, 1. - v);
+        let (ds0, dt0) = (footprint.dudx, -footprint.dvdx);
+        let (ds1, dt1) = (footprint.dudy, -footprint.dvdy);
 
-        if i >= self.width  { i = self.width - 1 };
-        if j >= self.height { j = self.height - 1 };
+        match self.filter {
+            ImageFilter::Nearest => self.mipmap.nearest(0, s, t),
+            ImageFilter::Bilinear => self.mipmap.bilinear(0, s, t),
+            ImageFilter::Bicubic => self.mipmap.bicubic(0, s, t),
+            ImageFilter::Trilinear => {
+                let width = 2. * ds0.abs().max(dt0.abs()).max(ds1.abs()).max(dt1.abs());
+                self.mipmap.trilinear(s, t, width)
+            }
+            ImageFilter::Ewa => self.mipmap.ewa(s, t, ds0, dt0, ds1, dt1),
+        }
+    }
+}
+
+// scalar opacity read from the alpha channel of an image, or from its
+// brightness when it has none, as used by the MTL `map_d`
+pub struct OpacityTexture {
+    data: Vec<f32>,
+    width: usize,
+    height: usize
+}
 
-        let color_scale = 1. / 255.;
-        let bytes_per_pixel = 3;
-        let bytes_per_scanline = 3;
+impl OpacityTexture {
+    pub fn new(path: &str) -> Self {
+        OpacityTexture::read(path).unwrap_or_else(|error| panic!("Opacity map {} could not be loaded: {}", path, error))
+    }
 
-        let pixel_idx = j * bytes_per_scanline * self.width + i * bytes_per_pixel;
-        
-        let r = self.data[pixel_idx]     as f32 * color_scale;
-        let g = self.data[pixel_idx + 1] as f32 * color_scale;
-        let b = self.data[pixel_idx + 2] as f32 * color_scale;
+    pub fn read(path: &str) -> Result<Self, String> {
+        let image = image_file::read(path)?;
+        let data = match image.alpha {
+            Some(alpha) => alpha,
+            None => image.texels.iter().map(Color::luminance).collect(),
+        };
 
-        Color::new(r, g, b)
+        Ok(OpacityTexture { data, width: image.width, height: image.height })
     }
-}
\ No newline at end of file
+}
+
+impl Texture for OpacityTexture {
+    fn color(&self, u: f32, v: f32, p: &Vec3) -> Color {
+        let value = self.value(u, v, p);
+
+        Color::new(value, value, value)
+    }
+
+    fn value(&self, u: f32, v: f32, _p: &Vec3) -> f32 {
+        let u = utils::clamp(u, 0., 1.);
+        let v = 1. - utils::clamp(v, 0., 1.);
+
+        let i = ((u * self.width as f32) as usize).min(self.width - 1);
+        let j = ((v * self.height as f32) as usize).min(self.height - 1);
+
+        self.data[j * self.width + i]
+    }
+}
@@ -0,0 +1,291 @@
+use crate::color::{Color, BLACK, linear_blend};
+
+// footprints are limited to this ratio of their axes so very elongated
+// ellipses don't read thousands of texels
+const MAX_ANISOTROPY: f32 = 8.0;
+
+#[derive(Clone, Copy)]
+pub enum WrapMode {
+    Repeat,
+    Mirror,
+    Clamp,
+}
+
+#[derive(Clone, Copy)]
+pub enum ImageFilter {
+    // no interpolation, for pixel art
+    Nearest,
+    Bilinear,
+    // Catmull-Rom over 4x4 texels, sharper than bilinear when magnified
+    Bicubic,
+    // bilinear on the two mip levels closest to the footprint size
+    Trilinear,
+    // elliptical weighted average over the footprint, stays sharp at grazing angles
+    Ewa,
+}
+
+struct Level {
+    width: usize,
+    height: usize,
+    texels: Vec<Color>,
+}
+
+// image pyramid where each level halves the resolution of the previous one.
+// Lookups take (s, t) in [0, 1] with t going down the image.
+pub struct MipMap {
+    levels: Vec<Level>,
+    pub wrap: WrapMode,
+}
+
+impl MipMap {
+    pub fn new(width: usize, height: usize, texels: Vec<Color>, wrap: WrapMode) -> Self {
+        assert!(width > 0 && height > 0 && texels.len() == width * height);
+
+        let mut mipmap = MipMap { levels: vec![Level { width, height, texels }], wrap };
+
+        while mipmap.width(mipmap.levels.len() - 1) > 1 || mipmap.height(mipmap.levels.len() - 1) > 1 {
+            let level = mipmap.levels.len() - 1;
+            let width = mipmap.width(level).div_ceil(2);
+            let height = mipmap.height(level).div_ceil(2);
+            let mut texels = Vec::with_capacity(width * height);
+
+            for y in 0..height as i32 {
+                for x in 0..width as i32 {
+                    let sum = mipmap.texel(level, 2 * x, 2 * y)
+                        + mipmap.texel(level, 2 * x + 1, 2 * y)
+                        + mipmap.texel(level, 2 * x, 2 * y + 1)
+                        + mipmap.texel(level, 2 * x + 1, 2 * y + 1);
+                    texels.push(0.25 * sum);
+                }
+            }
+
+            mipmap.levels.push(Level { width, height, texels });
+        }
+
+        mipmap
+    }
+
+    pub fn levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn width(&self, level: usize) -> usize {
+        self.levels[level].width
+    }
+
+    pub fn height(&self, level: usize) -> usize {
+        self.levels[level].height
+    }
+
+    pub fn texel(&self, level: usize, x: i32, y: i32) -> Color {
+        let level = &self.levels[level];
+        let x = wrap(x, level.width, self.wrap);
+        let y = wrap(y, level.height, self.wrap);
+
+        level.texels[y * level.width + x]
+    }
+
+    pub fn nearest(&self, level: usize, s: f32, t: f32) -> Color {
+        let x = (s * self.width(level) as f32).floor() as i32;
+        let y = (t * self.height(level) as f32).floor() as i32;
+
+        self.texel(level, x, y)
+    }
+
+    pub fn bilinear(&self, level: usize, s: f32, t: f32) -> Color {
+        let x = s * self.width(level) as f32 - 0.5;
+        let y = t * self.height(level) as f32 - 0.5;
+        let (x0, y0) = (x.floor() as i32, y.floor() as i32);
+        let (dx, dy) = (x - x.floor(), y - y.floor());
+
+        linear_blend(dy,
+            linear_blend(dx, self.texel(level, x0, y0), self.texel(level, x0 + 1, y0)),
+            linear_blend(dx, self.texel(level, x0, y0 + 1), self.texel(level, x0 + 1, y0 + 1)))
+    }
+
+    pub fn bicubic(&self, level: usize, s: f32, t: f32) -> Color {
+        let x = s * self.width(level) as f32 - 0.5;
+        let y = t * self.height(level) as f32 - 0.5;
+        let (x0, y0) = (x.floor() as i32, y.floor() as i32);
+        let weights_x = catmull_rom(x - x.floor());
+        let weights_y = catmull_rom(y - y.floor());
+
+        let mut sum = BLACK;
+        for (j, weight_y) in weights_y.iter().enumerate() {
+            for (i, weight_x) in weights_x.iter().enumerate() {
+                sum = sum + (weight_x * weight_y) * self.texel(level, x0 + i as i32 - 1, y0 + j as i32 - 1);
+            }
+        }
+
+        // the negative lobes can overshoot below zero next to sharp edges
+        Color::new(sum.r.max(0.0), sum.g.max(0.0), sum.b.max(0.0))
+    }
+
+    // `width` is the footprint size in [0, 1] texture space
+    pub fn trilinear(&self, s: f32, t: f32, width: f32) -> Color {
+        let level = (self.levels() - 1) as f32 + width.max(1e-8).log2();
+
+        if level <= 0.0 {
+            self.bilinear(0, s, t)
+        } else if level >= (self.levels() - 1) as f32 {
+            self.texel(self.levels() - 1, 0, 0)
+        } else {
+            let lower = level.floor();
+
+            linear_blend(level - lower,
+                self.bilinear(lower as usize, s, t),
+                self.bilinear(lower as usize + 1, s, t))
+        }
+    }
+
+    // filters over the ellipse with the axes (ds0, dt0) and (ds1, dt1)
+    // around (s, t), see pbrt 3rd edition chapter 10.4.5
+    pub fn ewa(&self, s: f32, t: f32, ds0: f32, dt0: f32, ds1: f32, dt1: f32) -> Color {
+        let ((mut major_s, mut major_t), (mut minor_s, mut minor_t)) = ((ds0, dt0), (ds1, dt1));
+        if major_s * major_s + major_t * major_t < minor_s * minor_s + minor_t * minor_t {
+            std::mem::swap(&mut major_s, &mut minor_s);
+            std::mem::swap(&mut major_t, &mut minor_t);
+        }
+
+        let major_length = (major_s * major_s + major_t * major_t).sqrt();
+        let mut minor_length = (minor_s * minor_s + minor_t * minor_t).sqrt();
+
+        if minor_length == 0.0 {
+            return self.bilinear(0, s, t);
+        }
+
+        if minor_length * MAX_ANISOTROPY < major_length {
+            let scale = major_length / (minor_length * MAX_ANISOTROPY);
+            minor_s *= scale;
+            minor_t *= scale;
+            minor_length *= scale;
+        }
+
+        // the level is chosen so the minor axis covers a few texels
+        let level = ((self.levels() - 1) as f32 + minor_length.log2()).max(0.0);
+        let lower = level.floor() as usize;
+        let first = self.ewa_level(lower, s, t, (major_s, major_t), (minor_s, minor_t));
+
+        if level == lower as f32 {
+            return first;
+        }
+
+        linear_blend(level - lower as f32, first, self.ewa_level(lower + 1, s, t, (major_s, major_t), (minor_s, minor_t)))
+    }
+
+    fn ewa_level(&self, level: usize, s: f32, t: f32, axis0: (f32, f32), axis1: (f32, f32)) -> Color {
+        if level >= self.levels() {
+            return self.texel(self.levels() - 1, 0, 0);
+        }
+
+        let (width, height) = (self.width(level) as f32, self.height(level) as f32);
+        let (s, t) = (s * width - 0.5, t * height - 0.5);
+        let (ds0, dt0) = (axis0.0 * width, axis0.1 * height);
+        let (ds1, dt1) = (axis1.0 * width, axis1.1 * height);
+
+        // implicit ellipse a s^2 + b s t + c t^2 = 1, grown by a texel so it
+        // always covers at least one
+        let a = dt0 * dt0 + dt1 * dt1 + 1.0;
+        let b = -2.0 * (ds0 * dt0 + ds1 * dt1);
+        let c = ds0 * ds0 + ds1 * ds1 + 1.0;
+        let inv_f = 1.0 / (a * c - b * b * 0.25);
+        let (a, b, c) = (a * inv_f, b * inv_f, c * inv_f);
+
+        let det = -b * b + 4.0 * a * c;
+        let inv_det = 1.0 / det;
+        let u_sqrt = (det * c).sqrt();
+        let v_sqrt = (a * det).sqrt();
+        let s0 = (s - 2.0 * inv_det * u_sqrt).ceil() as i32;
+        let s1 = (s + 2.0 * inv_det * u_sqrt).floor() as i32;
+        let t0 = (t - 2.0 * inv_det * v_sqrt).ceil() as i32;
+        let t1 = (t + 2.0 * inv_det * v_sqrt).floor() as i32;
+
+        let mut sum = BLACK;
+        let mut weights = 0.0;
+
+        for it in t0..=t1 {
+            let tt = it as f32 - t;
+
+            for is in s0..=s1 {
+                let ss = is as f32 - s;
+                let r2 = a * ss * ss + b * ss * tt + c * tt * tt;
+
+                if r2 < 1.0 {
+                    // gaussian falling off to zero at the border of the ellipse
+                    let weight = (-2.0 * r2).exp() - (-2.0f32).exp();
+                    sum = sum + weight * self.texel(level, is, it);
+                    weights += weight;
+                }
+            }
+        }
+
+        if weights > 0.0 {
+            sum * (1.0 / weights)
+        } else {
+            self.bilinear(level, (s + 0.5) / width, (t + 0.5) / height)
+        }
+    }
+}
+
+fn wrap(x: i32, size: usize, mode: WrapMode) -> usize {
+    let size = size as i32;
+
+    let x = match mode {
+        WrapMode::Repeat => x.rem_euclid(size),
+        WrapMode::Mirror => {
+            let m = x.rem_euclid(2 * size);
+            if m >= size { 2 * size - 1 - m } else { m }
+        }
+        WrapMode::Clamp => x.max(0).min(size - 1),
+    };
+
+    x as usize
+}
+
+fn catmull_rom(t: f32) -> [f32; 4] {
+    [
+        ((-0.5 * t + 1.0) * t - 0.5) * t,
+        (1.5 * t - 2.5) * t * t + 1.0,
+        ((-1.5 * t + 2.0) * t + 0.5) * t,
+        (0.5 * t - 0.5) * t * t,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::color::WHITE;
+
+    fn checker(size: usize) -> MipMap {
+        let texels = (0..size * size)
+            .map(|i| if (i % size + i / size).is_multiple_of(2) { WHITE } else { BLACK })
+            .collect();
+
+        MipMap::new(size, size, texels, WrapMode::Repeat)
+    }
+
+    #[test]
+    fn pyramid_averages_down_to_one_texel() {
+        let mipmap = checker(8);
+
+        assert_eq!(mipmap.levels(), 4);
+        assert!((mipmap.texel(3, 0, 0).r - 0.5).abs() < 0.0001);
+    }
+
+    #[test]
+    fn wrap_modes() {
+        assert_eq!(wrap(-1, 4, WrapMode::Repeat), 3);
+        assert_eq!(wrap(-1, 4, WrapMode::Mirror), 0);
+        assert_eq!(wrap(5, 4, WrapMode::Mirror), 2);
+        assert_eq!(wrap(7, 4, WrapMode::Clamp), 3);
+    }
+
+    #[test]
+    fn wide_footprints_blur_to_the_average() {
+        let mipmap = checker(64);
+
+        assert!((mipmap.trilinear(0.3, 0.6, 1.0).g - 0.5).abs() < 0.0001);
+        assert!((mipmap.ewa(0.3, 0.6, 0.5, 0.0, 0.0, 0.5).g - 0.5).abs() < 0.05);
+    }
+}
@@ -0,0 +1,351 @@
+use crate::color::{Color, WHITE, linear_blend};
+use crate::structures::vec3::Vec3;
+use crate::texture::{Texture, TextureFootprint};
+use crate::texture::procedural::ColorRamp;
+use crate::utils;
+
+// Combinators taking other textures as inputs, nesting them builds a node
+// graph. Scalar inputs are read with `Texture::value`.
+
+pub struct AddTexture<A: Texture, B: Texture> {
+    pub first: A,
+    pub second: B,
+}
+
+impl<A: Texture, B: Texture> AddTexture<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        AddTexture { first, second }
+    }
+}
+
+impl<A: Texture, B: Texture> Texture for AddTexture<A, B> {
+    fn color(&self, u: f32, v: f32, p: &Vec3) -> Color {
+        self.first.color(u, v, p) + self.second.color(u, v, p)
+    }
+
+    fn value(&self, u: f32, v: f32, p: &Vec3) -> f32 {
+        self.first.value(u, v, p) + self.second.value(u, v, p)
+    }
+
+    fn color_filtered(&self, u: f32, v: f32, p: &Vec3, footprint: &TextureFootprint) -> Color {
+        self.first.color_filtered(u, v, p, footprint) + self.second.color_filtered(u, v, p, footprint)
+    }
+}
+
+pub struct MultiplyTexture<A: Texture, B: Texture> {
+    pub first: A,
+    pub second: B,
+}
+
+impl<A: Texture, B: Texture> MultiplyTexture<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        MultiplyTexture { first, second }
+    }
+}
+
+impl<A: Texture, B: Texture> Texture for MultiplyTexture<A, B> {
+    fn color(&self, u: f32, v: f32, p: &Vec3) -> Color {
+        self.first.color(u, v, p) * self.second.color(u, v, p)
+    }
+
+    fn value(&self, u: f32, v: f32, p: &Vec3) -> f32 {
+        self.first.value(u, v, p) * self.second.value(u, v, p)
+    }
+
+    fn color_filtered(&self, u: f32, v: f32, p: &Vec3, footprint: &TextureFootprint) -> Color {
+        self.first.color_filtered(u, v, p, footprint) * self.second.color_filtered(u, v, p, footprint)
+    }
+}
+
+// a factor of 0 is fully `first` and 1 fully `second`
+pub struct MixTexture<A: Texture, B: Texture, F: Texture> {
+    pub first: A,
+    pub second: B,
+    pub factor: F,
+}
+
+impl<A: Texture, B: Texture, F: Texture> MixTexture<A, B, F> {
+    pub fn new(first: A, second: B, factor: F) -> Self {
+        MixTexture { first, second, factor }
+    }
+}
+
+impl<A: Texture, B: Texture, F: Texture> Texture for MixTexture<A, B, F> {
+    fn color(&self, u: f32, v: f32, p: &Vec3) -> Color {
+        let factor = utils::clamp(self.factor.value(u, v, p), 0.0, 1.0);
+
+        linear_blend(factor, self.first.color(u, v, p), self.second.color(u, v, p))
+    }
+
+    fn color_filtered(&self, u: f32, v: f32, p: &Vec3, footprint: &TextureFootprint) -> Color {
+        let factor = utils::clamp(self.factor.value(u, v, p), 0.0, 1.0);
+
+        linear_blend(factor, self.first.color_filtered(u, v, p, footprint), self.second.color_filtered(u, v, p, footprint))
+    }
+}
+
+pub struct InvertTexture<A: Texture> {
+    pub texture: A,
+}
+
+impl<A: Texture> InvertTexture<A> {
+    pub fn new(texture: A) -> Self {
+        InvertTexture { texture }
+    }
+}
+
+impl<A: Texture> Texture for InvertTexture<A> {
+    fn color(&self, u: f32, v: f32, p: &Vec3) -> Color {
+        WHITE + -1.0 * self.texture.color(u, v, p)
+    }
+
+    fn value(&self, u: f32, v: f32, p: &Vec3) -> f32 {
+        1.0 - self.texture.value(u, v, p)
+    }
+
+    fn color_filtered(&self, u: f32, v: f32, p: &Vec3, footprint: &TextureFootprint) -> Color {
+        WHITE + -1.0 * self.texture.color_filtered(u, v, p, footprint)
+    }
+}
+
+// clamps every channel to [min, max]
+pub struct ClampTexture<A: Texture> {
+    pub texture: A,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl<A: Texture> ClampTexture<A> {
+    pub fn new(texture: A, min: f32, max: f32) -> Self {
+        ClampTexture { texture, min, max }
+    }
+}
+
+impl<A: Texture> ClampTexture<A> {
+    fn clamp(&self, color: Color) -> Color {
+        Color::new(
+            utils::clamp(color.r, self.min, self.max),
+            utils::clamp(color.g, self.min, self.max),
+            utils::clamp(color.b, self.min, self.max))
+    }
+}
+
+impl<A: Texture> Texture for ClampTexture<A> {
+    fn color(&self, u: f32, v: f32, p: &Vec3) -> Color {
+        self.clamp(self.texture.color(u, v, p))
+    }
+
+    fn value(&self, u: f32, v: f32, p: &Vec3) -> f32 {
+        utils::clamp(self.texture.value(u, v, p), self.min, self.max)
+    }
+
+    fn color_filtered(&self, u: f32, v: f32, p: &Vec3, footprint: &TextureFootprint) -> Color {
+        self.clamp(self.texture.color_filtered(u, v, p, footprint))
+    }
+}
+
+// maps the scalar `factor` through a color ramp
+pub struct RampTexture<F: Texture> {
+    pub factor: F,
+    pub ramp: ColorRamp,
+}
+
+impl<F: Texture> RampTexture<F> {
+    pub fn new(factor: F, ramp: ColorRamp) -> Self {
+        RampTexture { factor, ramp }
+    }
+}
+
+impl<F: Texture> Texture for RampTexture<F> {
+    fn color(&self, u: f32, v: f32, p: &Vec3) -> Color {
+        let t = utils::clamp(self.factor.value(u, v, p), 0.0, 1.0);
+
+        self.ramp.color_at(t, u, v, p)
+    }
+
+    fn color_filtered(&self, u: f32, v: f32, p: &Vec3, footprint: &TextureFootprint) -> Color {
+        let t = utils::clamp(self.factor.value(u, v, p), 0.0, 1.0);
+
+        self.ramp.color_filtered_at(t, u, v, p, footprint)
+    }
+}
+
+// gray color from the scalar value of `texture`
+pub struct ScalarToColor<A: Texture> {
+    pub texture: A,
+}
+
+impl<A: Texture> ScalarToColor<A> {
+    pub fn new(texture: A) -> Self {
+        ScalarToColor { texture }
+    }
+}
+
+impl<A: Texture> Texture for ScalarToColor<A> {
+    fn color(&self, u: f32, v: f32, p: &Vec3) -> Color {
+        let value = self.texture.value(u, v, p);
+
+        Color::new(value, value, value)
+    }
+
+    fn value(&self, u: f32, v: f32, p: &Vec3) -> f32 {
+        self.texture.value(u, v, p)
+    }
+
+    // the filtered value is read from the red channel, like `Texture::value`
+    fn color_filtered(&self, u: f32, v: f32, p: &Vec3, footprint: &TextureFootprint) -> Color {
+        let value = self.texture.color_filtered(u, v, p, footprint).r;
+
+        Color::new(value, value, value)
+    }
+}
+
+// scales, rotates (in degrees, counterclockwise) and then offsets the
+// texture coordinates before the lookup in `texture`
+pub struct UvTransform<A: Texture> {
+    pub texture: A,
+    pub scale: (f32, f32),
+    pub rotation: f32,
+    pub offset: (f32, f32),
+}
+
+impl<A: Texture> UvTransform<A> {
+    pub fn new(texture: A) -> Self {
+        UvTransform { texture, scale: (1.0, 1.0), rotation: 0.0, offset: (0.0, 0.0) }
+    }
+
+    fn transform(&self, u: f32, v: f32) -> (f32, f32) {
+        let (u, v) = self.transform_vector(u, v);
+
+        (u + self.offset.0, v + self.offset.1)
+    }
+
+    fn transform_vector(&self, u: f32, v: f32) -> (f32, f32) {
+        let (u, v) = (u * self.scale.0, v * self.scale.1);
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+
+        (cos * u - sin * v, sin * u + cos * v)
+    }
+}
+
+impl<A: Texture> Texture for UvTransform<A> {
+    fn color(&self, u: f32, v: f32, p: &Vec3) -> Color {
+        let (u, v) = self.transform(u, v);
+
+        self.texture.color(u, v, p)
+    }
+
+    fn value(&self, u: f32, v: f32, p: &Vec3) -> f32 {
+        let (u, v) = self.transform(u, v);
+
+        self.texture.value(u, v, p)
+    }
+
+    fn color_filtered(&self, u: f32, v: f32, p: &Vec3, footprint: &TextureFootprint) -> Color {
+        let (u, v) = self.transform(u, v);
+        let (dudx, dvdx) = self.transform_vector(footprint.dudx, footprint.dvdx);
+        let (dudy, dvdy) = self.transform_vector(footprint.dudy, footprint.dvdy);
+
+        self.texture.color_filtered(u, v, p, &TextureFootprint { dudx, dvdx, dudy, dvdy })
+    }
+}
+
+pub enum CoordinateSpace {
+    World,
+    // relative to an object placed at `origin` with the given size, so the
+    // pattern moves and scales with it
+    Object { origin: Vec3, scale: f32 },
+    // the texture coordinates as position (u, v, 0), to use solid textures
+    // like the noises in uv space
+    Uv,
+}
+
+// selects the position solid textures are evaluated at
+pub struct CoordinateTexture<A: Texture> {
+    pub texture: A,
+    pub space: CoordinateSpace,
+}
+
+impl<A: Texture> CoordinateTexture<A> {
+    pub fn new(texture: A, space: CoordinateSpace) -> Self {
+        CoordinateTexture { texture, space }
+    }
+
+    fn position(&self, u: f32, v: f32, p: &Vec3) -> Vec3 {
+        match self.space {
+            CoordinateSpace::World => *p,
+            CoordinateSpace::Object { origin, scale } => (*p - origin) / scale,
+            CoordinateSpace::Uv => Vec3::new(u, v, 0.0),
+        }
+    }
+}
+
+impl<A: Texture> Texture for CoordinateTexture<A> {
+    fn color(&self, u: f32, v: f32, p: &Vec3) -> Color {
+        self.texture.color(u, v, &self.position(u, v, p))
+    }
+
+    fn value(&self, u: f32, v: f32, p: &Vec3) -> f32 {
+        self.texture.value(u, v, &self.position(u, v, p))
+    }
+
+    fn color_filtered(&self, u: f32, v: f32, p: &Vec3, footprint: &TextureFootprint) -> Color {
+        self.texture.color_filtered(u, v, &self.position(u, v, p), footprint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::texture::SolidColor;
+
+    #[test]
+    fn nodes_compose() {
+        let half = SolidColor::new(Color::new(0.5, 0.5, 0.5));
+        let quarter = SolidColor::new(Color::new(0.25, 0.25, 0.25));
+        let graph = ClampTexture::new(AddTexture::new(InvertTexture::new(quarter), half), 0.0, 1.0);
+
+        assert_eq!(graph.color(0.0, 0.0, &Vec3::ZERO).g, 1.0);
+    }
+
+    // white at a point and black when filtered
+    struct Filtered;
+
+    impl Texture for Filtered {
+        fn color(&self, _u: f32, _v: f32, _p: &Vec3) -> Color {
+            WHITE
+        }
+
+        fn color_filtered(&self, _u: f32, _v: f32, _p: &Vec3, _footprint: &TextureFootprint) -> Color {
+            Color::new(0.0, 0.0, 0.0)
+        }
+    }
+
+    #[test]
+    fn nodes_forward_filtered_lookups() {
+        let footprint = TextureFootprint { dudx: 0.1, dvdx: 0.0, dudy: 0.0, dvdy: 0.1 };
+        let filtered = |texture: &dyn Texture| texture.color_filtered(0.5, 0.5, &Vec3::ZERO, &footprint).g;
+        let half = || SolidColor::new(Color::new(0.5, 0.5, 0.5));
+        let ramp = ColorRamp::new(vec![(0.0, Box::new(Filtered) as Box<dyn Texture>), (1.0, Box::new(Filtered))]);
+
+        assert_eq!(filtered(&AddTexture::new(Filtered, Filtered)), 0.0);
+        assert_eq!(filtered(&MultiplyTexture::new(Filtered, SolidColor::new(WHITE))), 0.0);
+        assert_eq!(filtered(&MixTexture::new(Filtered, Filtered, half())), 0.0);
+        assert_eq!(filtered(&InvertTexture::new(Filtered)), 1.0);
+        assert_eq!(filtered(&ClampTexture::new(Filtered, 0.0, 1.0)), 0.0);
+        assert_eq!(filtered(&RampTexture::new(half(), ramp)), 0.0);
+        assert_eq!(filtered(&ScalarToColor::new(Filtered)), 0.0);
+        assert_eq!(filtered(&CoordinateTexture::new(Filtered, CoordinateSpace::World)), 0.0);
+        assert_eq!(filtered(&UvTransform::new(Filtered)), 0.0);
+    }
+
+    #[test]
+    fn uv_transform_rotates_counterclockwise() {
+        let transform = UvTransform { rotation: 90.0, offset: (1.0, 0.0), ..UvTransform::new(SolidColor::new(WHITE)) };
+        let (u, v) = transform.transform(1.0, 0.0);
+
+        assert!((u - 1.0).abs() < 0.0001);
+        assert!((v - 1.0).abs() < 0.0001);
+    }
+}
@@ -0,0 +1,133 @@
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+use crate::structures::vec3::Vec3;
+
+const POINT_COUNT: usize = 256;
+
+// gradient noise with random unit vectors on the lattice points, see
+// https://raytracing.github.io/books/RayTracingTheNextWeek.html#perlinnoise
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+// seed of the default noise, so scenes render the same every time
+const DEFAULT_SEED: u64 = 0;
+
+impl Default for Perlin {
+    fn default() -> Self {
+        Perlin::seeded(DEFAULT_SEED)
+    }
+}
+
+impl Perlin {
+    // the same seed gives the same noise, for reproducible renders
+    pub fn seeded(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let gradients = (0..POINT_COUNT).map(|_| {
+            loop {
+                let v = Vec3::new(rng.gen_range(-1.0, 1.0), rng.gen_range(-1.0, 1.0), rng.gen_range(-1.0, 1.0));
+                let length_squared = v.length_squared();
+
+                if length_squared > 0.0001 && length_squared <= 1.0 {
+                    return v.normalize();
+                }
+            }
+        }).collect();
+
+        let permutation = |rng: &mut StdRng| {
+            let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+            p.shuffle(rng);
+            p
+        };
+
+        let perm_x = permutation(&mut rng);
+        let perm_y = permutation(&mut rng);
+        let perm_z = permutation(&mut rng);
+
+        Perlin { gradients, perm_x, perm_y, perm_z }
+    }
+
+    // noise in about [-1, 1], zero on the lattice points
+    pub fn noise(&self, p: &Vec3) -> f32 {
+        let (i, j, k) = (p.x.floor() as i32, p.y.floor() as i32, p.z.floor() as i32);
+        let (u, v, w) = (p.x - p.x.floor(), p.y - p.y.floor(), p.z - p.z.floor());
+
+        // hermite smoothing removes the grid artifacts of plain trilinear interpolation
+        let (uu, vv, ww) = (u * u * (3.0 - 2.0 * u), v * v * (3.0 - 2.0 * v), w * w * (3.0 - 2.0 * w));
+
+        let mut accum = 0.0;
+
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let index = self.perm_x[((i + di) & 255) as usize]
+                        ^ self.perm_y[((j + dj) & 255) as usize]
+                        ^ self.perm_z[((k + dk) & 255) as usize];
+                    let weight = Vec3::new(u - di as f32, v - dj as f32, w - dk as f32);
+                    let (fi, fj, fk) = (di as f32, dj as f32, dk as f32);
+
+                    accum += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                        * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                        * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                        * self.gradients[index].dot(&weight);
+                }
+            }
+        }
+
+        accum
+    }
+
+    // sum of octaves of signed noise, each at twice the frequency and half
+    // the amplitude of the previous one
+    pub fn fbm(&self, p: &Vec3, octaves: u32) -> f32 {
+        self.octaves(p, octaves, |noise| noise)
+    }
+
+    // like `fbm` but summing the absolute noise, giving creases where the
+    // noise changes sign
+    pub fn turbulence(&self, p: &Vec3, octaves: u32) -> f32 {
+        self.octaves(p, octaves, f32::abs)
+    }
+
+    fn octaves(&self, p: &Vec3, octaves: u32, shape: impl Fn(f32) -> f32) -> f32 {
+        let mut accum = 0.0;
+        let mut p = *p;
+        let mut weight = 1.0;
+
+        for _ in 0..octaves {
+            accum += weight * shape(self.noise(&p));
+            weight *= 0.5;
+            p = 2.0 * p;
+        }
+
+        accum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_same_noise() {
+        let p = Vec3::new(1.3, -4.7, 0.25);
+
+        assert_eq!(Perlin::seeded(42).turbulence(&p, 7), Perlin::seeded(42).turbulence(&p, 7));
+        assert!(Perlin::seeded(42).noise(&p) != Perlin::seeded(43).noise(&p));
+        assert_eq!(Perlin::default().noise(&p), Perlin::default().noise(&p));
+    }
+
+    #[test]
+    fn noise_vanishes_on_lattice_points() {
+        let perlin = Perlin::seeded(1);
+
+        assert!(perlin.noise(&Vec3::new(3.0, -2.0, 7.0)).abs() < 0.00001);
+    }
+}
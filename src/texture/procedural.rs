@@ -0,0 +1,461 @@
+use crate::color::{Color, linear_blend};
+use crate::structures::vec3::Vec3;
+use crate::texture::{Texture, TextureFootprint, SolidColor};
+use crate::texture::perlin::Perlin;
+use crate::utils;
+
+// The patterns here compute a factor and blend two child textures with it,
+// or look it up in a color ramp, so they can be nested into each other.
+
+// colors at positions in [0, 1], interpolated linearly in between. The stops
+// are textures themselves.
+pub struct ColorRamp {
+    stops: Vec<(f32, Box<dyn Texture>)>,
+}
+
+impl ColorRamp {
+    pub fn new(mut stops: Vec<(f32, Box<dyn Texture>)>) -> Self {
+        assert!(!stops.is_empty(), "A color ramp needs at least one stop");
+        stops.sort_by(|a, b| a.0.partial_cmp(&b.0).expect("Color ramp positions must not be NaN"));
+
+        ColorRamp { stops }
+    }
+
+    pub fn from_colors(stops: Vec<(f32, Color)>) -> Self {
+        ColorRamp::new(stops.into_iter()
+            .map(|(position, color)| (position, Box::new(SolidColor::new(color)) as Box<dyn Texture>))
+            .collect())
+    }
+
+    pub fn color_at(&self, t: f32, u: f32, v: f32, p: &Vec3) -> Color {
+        self.color_filtered_at(t, u, v, p, &TextureFootprint::default())
+    }
+
+    // the stops are looked up filtered over `footprint`
+    pub fn color_filtered_at(&self, t: f32, u: f32, v: f32, p: &Vec3, footprint: &TextureFootprint) -> Color {
+        let next = self.stops.iter().position(|(position, _)| *position > t);
+        let stop = |i: usize| self.stops[i].1.color_filtered(u, v, p, footprint);
+
+        match next {
+            None => stop(self.stops.len() - 1),
+            Some(0) => stop(0),
+            Some(i) => {
+                let (start, end) = (self.stops[i - 1].0, self.stops[i].0);
+
+                linear_blend((t - start) / (end - start), stop(i - 1), stop(i))
+            }
+        }
+    }
+}
+
+// integer hash to [0, 1), stable across runs and platforms
+fn hash(x: i32, y: i32, z: i32, seed: u32) -> f32 {
+    let mut h = seed
+        ^ (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ (z as u32).wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^= h >> 16;
+
+    (h >> 8) as f32 / (1 << 24) as f32
+}
+
+pub enum VoronoiFeature {
+    // distance to the closest feature point
+    F1,
+    // difference of the two closest distances, zero on the cell borders
+    Edge,
+    // a random value per cell
+    Cell,
+}
+
+// Worley noise, one randomly placed feature point per unit cell
+pub struct VoronoiTexture<A: Texture, B: Texture> {
+    pub first: A,
+    pub second: B,
+    pub scale: f32,
+    // how far the feature points may move away from the cell centers, 0 gives a regular grid
+    pub jitter: f32,
+    pub feature: VoronoiFeature,
+    pub seed: u32,
+}
+
+impl<A: Texture, B: Texture> VoronoiTexture<A, B> {
+    pub fn new(first: A, second: B, scale: f32, feature: VoronoiFeature) -> Self {
+        VoronoiTexture { first, second, scale, jitter: 1.0, feature, seed: 0 }
+    }
+
+    fn factor(&self, p: &Vec3) -> f32 {
+        let p = self.scale * *p;
+        let (ci, cj, ck) = (p.x.floor() as i32, p.y.floor() as i32, p.z.floor() as i32);
+
+        let mut f1 = f32::INFINITY;
+        let mut f2 = f32::INFINITY;
+        let mut closest_cell = (ci, cj, ck);
+
+        for i in ci - 1..=ci + 1 {
+            for j in cj - 1..=cj + 1 {
+                for k in ck - 1..=ck + 1 {
+                    let feature_point = Vec3::new(
+                        i as f32 + 0.5 + self.jitter * (hash(i, j, k, self.seed) - 0.5),
+                        j as f32 + 0.5 + self.jitter * (hash(i, j, k, self.seed.wrapping_add(1)) - 0.5),
+                        k as f32 + 0.5 + self.jitter * (hash(i, j, k, self.seed.wrapping_add(2)) - 0.5));
+                    let distance = (feature_point - p).length();
+
+                    if distance < f1 {
+                        f2 = f1;
+                        f1 = distance;
+                        closest_cell = (i, j, k);
+                    } else if distance < f2 {
+                        f2 = distance;
+                    }
+                }
+            }
+        }
+
+        match self.feature {
+            VoronoiFeature::F1 => f1,
+            VoronoiFeature::Edge => f2 - f1,
+            VoronoiFeature::Cell => hash(closest_cell.0, closest_cell.1, closest_cell.2, self.seed.wrapping_add(3)),
+        }
+    }
+}
+
+impl<A: Texture, B: Texture> Texture for VoronoiTexture<A, B> {
+    fn color(&self, u: f32, v: f32, p: &Vec3) -> Color {
+        let t = utils::clamp(self.factor(p), 0.0, 1.0);
+
+        linear_blend(t, self.first.color(u, v, p), self.second.color(u, v, p))
+    }
+
+    fn color_filtered(&self, u: f32, v: f32, p: &Vec3, footprint: &TextureFootprint) -> Color {
+        let t = utils::clamp(self.factor(p), 0.0, 1.0);
+
+        linear_blend(t, self.first.color_filtered(u, v, p, footprint), self.second.color_filtered(u, v, p, footprint))
+    }
+}
+
+// fractional brownian motion with adjustable frequency and amplitude steps
+pub struct FbmTexture<A: Texture, B: Texture> {
+    pub first: A,
+    pub second: B,
+    pub noise: Perlin,
+    pub scale: f32,
+    pub octaves: u32,
+    // frequency multiplier between octaves
+    pub lacunarity: f32,
+    // amplitude multiplier between octaves
+    pub gain: f32,
+}
+
+impl<A: Texture, B: Texture> FbmTexture<A, B> {
+    pub fn new(first: A, second: B, scale: f32) -> Self {
+        FbmTexture { first, second, noise: Perlin::default(), scale, octaves: 6, lacunarity: 2.0, gain: 0.5 }
+    }
+
+    fn factor(&self, p: &Vec3) -> f32 {
+        let mut point = self.scale * *p;
+        let mut amplitude = 1.0;
+        let mut total = 0.0;
+        let mut norm = 0.0;
+
+        for _ in 0..self.octaves {
+            total += amplitude * self.noise.noise(&point);
+            norm += amplitude;
+            amplitude *= self.gain;
+            point = self.lacunarity * point;
+        }
+
+        if norm > 0.0 { utils::clamp(0.5 * (1.0 + total / norm), 0.0, 1.0) } else { 0.5 }
+    }
+}
+
+impl<A: Texture, B: Texture> Texture for FbmTexture<A, B> {
+    fn color(&self, u: f32, v: f32, p: &Vec3) -> Color {
+        linear_blend(self.factor(p), self.first.color(u, v, p), self.second.color(u, v, p))
+    }
+
+    fn color_filtered(&self, u: f32, v: f32, p: &Vec3, footprint: &TextureFootprint) -> Color {
+        linear_blend(self.factor(p), self.first.color_filtered(u, v, p, footprint), self.second.color_filtered(u, v, p, footprint))
+    }
+}
+
+// ridged multifractal (Musgrave), sharp ridges like mountain ranges where
+// the ridges of each octave are weighted by the previous ones
+pub struct RidgedTexture<A: Texture, B: Texture> {
+    pub first: A,
+    pub second: B,
+    pub noise: Perlin,
+    pub scale: f32,
+    pub octaves: u32,
+    pub lacunarity: f32,
+    pub gain: f32,
+    pub offset: f32,
+}
+
+impl<A: Texture, B: Texture> RidgedTexture<A, B> {
+    pub fn new(first: A, second: B, scale: f32) -> Self {
+        RidgedTexture { first, second, noise: Perlin::default(), scale, octaves: 6, lacunarity: 2.0, gain: 2.0, offset: 1.0 }
+    }
+
+    fn factor(&self, p: &Vec3) -> f32 {
+        let mut point = self.scale * *p;
+        let mut amplitude = 1.0;
+        let mut weight = 1.0;
+        let mut total = 0.0;
+        let mut norm = 0.0;
+
+        for _ in 0..self.octaves {
+            let signal = self.offset - self.noise.noise(&point).abs();
+            let signal = signal * signal * weight;
+
+            total += amplitude * signal;
+            norm += amplitude * self.offset * self.offset;
+            weight = utils::clamp(signal * self.gain, 0.0, 1.0);
+            amplitude *= 0.5;
+            point = self.lacunarity * point;
+        }
+
+        if norm > 0.0 { utils::clamp(total / norm, 0.0, 1.0) } else { 0.0 }
+    }
+}
+
+impl<A: Texture, B: Texture> Texture for RidgedTexture<A, B> {
+    fn color(&self, u: f32, v: f32, p: &Vec3) -> Color {
+        linear_blend(self.factor(p), self.first.color(u, v, p), self.second.color(u, v, p))
+    }
+
+    fn color_filtered(&self, u: f32, v: f32, p: &Vec3, footprint: &TextureFootprint) -> Color {
+        linear_blend(self.factor(p), self.first.color_filtered(u, v, p, footprint), self.second.color_filtered(u, v, p, footprint))
+    }
+}
+
+// ramp from `start` to `end`, constant beyond them
+pub struct LinearGradient {
+    pub start: Vec3,
+    pub end: Vec3,
+    pub ramp: ColorRamp,
+}
+
+impl LinearGradient {
+    pub fn new(start: Vec3, end: Vec3, ramp: ColorRamp) -> Self {
+        LinearGradient { start, end, ramp }
+    }
+
+    fn factor(&self, p: &Vec3) -> f32 {
+        let axis = self.end - self.start;
+        let t = (*p - self.start).dot(&axis) / axis.length_squared();
+
+        utils::clamp(t, 0.0, 1.0)
+    }
+}
+
+impl Texture for LinearGradient {
+    fn color(&self, u: f32, v: f32, p: &Vec3) -> Color {
+        self.ramp.color_at(self.factor(p), u, v, p)
+    }
+
+    fn color_filtered(&self, u: f32, v: f32, p: &Vec3, footprint: &TextureFootprint) -> Color {
+        self.ramp.color_filtered_at(self.factor(p), u, v, p, footprint)
+    }
+}
+
+// ramp from `center` out to `radius`
+pub struct RadialGradient {
+    pub center: Vec3,
+    pub radius: f32,
+    pub ramp: ColorRamp,
+}
+
+impl RadialGradient {
+    pub fn new(center: Vec3, radius: f32, ramp: ColorRamp) -> Self {
+        RadialGradient { center, radius, ramp }
+    }
+
+    fn factor(&self, p: &Vec3) -> f32 {
+        utils::clamp((*p - self.center).length() / self.radius, 0.0, 1.0)
+    }
+}
+
+impl Texture for RadialGradient {
+    fn color(&self, u: f32, v: f32, p: &Vec3) -> Color {
+        self.ramp.color_at(self.factor(p), u, v, p)
+    }
+
+    fn color_filtered(&self, u: f32, v: f32, p: &Vec3, footprint: &TextureFootprint) -> Color {
+        self.ramp.color_filtered_at(self.factor(p), u, v, p, footprint)
+    }
+}
+
+// running bond bricks in uv space, `columns` and `rows` bricks over the
+// unit square with every other row shifted by `row_offset`
+pub struct BrickTexture<A: Texture, B: Texture> {
+    pub brick: A,
+    pub mortar: B,
+    pub columns: f32,
+    pub rows: f32,
+    // width of the mortar joints as a fraction of the brick height
+    pub mortar_width: f32,
+    pub row_offset: f32,
+}
+
+impl<A: Texture, B: Texture> BrickTexture<A, B> {
+    pub fn new(brick: A, mortar: B, columns: f32, rows: f32) -> Self {
+        BrickTexture { brick, mortar, columns, rows, mortar_width: 0.1, row_offset: 0.5 }
+    }
+
+    fn is_mortar(&self, u: f32, v: f32) -> bool {
+        let y = v * self.rows;
+        let row = y.floor();
+        let x = u * self.columns + if (row as i32) % 2 == 0 { 0.0 } else { self.row_offset };
+
+        // joints are equally wide in both directions, bricks are usually not square
+        let half_joint = 0.5 * self.mortar_width;
+        let half_joint_x = half_joint * self.rows / self.columns;
+        let fx = x - x.floor();
+        let fy = y - row;
+
+        fx < half_joint_x || fx > 1.0 - half_joint_x || fy < half_joint || fy > 1.0 - half_joint
+    }
+}
+
+impl<A: Texture, B: Texture> Texture for BrickTexture<A, B> {
+    fn color(&self, u: f32, v: f32, p: &Vec3) -> Color {
+        if self.is_mortar(u, v) { self.mortar.color(u, v, p) } else { self.brick.color(u, v, p) }
+    }
+
+    fn color_filtered(&self, u: f32, v: f32, p: &Vec3, footprint: &TextureFootprint) -> Color {
+        if self.is_mortar(u, v) { self.mortar.color_filtered(u, v, p, footprint) } else { self.brick.color_filtered(u, v, p, footprint) }
+    }
+}
+
+// square tiles in uv space separated by grout lines
+pub struct TileTexture<A: Texture, B: Texture> {
+    pub tile: A,
+    pub grout: B,
+    pub columns: f32,
+    pub rows: f32,
+    // width of the grout as a fraction of a tile
+    pub grout_width: f32,
+}
+
+impl<A: Texture, B: Texture> TileTexture<A, B> {
+    pub fn new(tile: A, grout: B, columns: f32, rows: f32) -> Self {
+        TileTexture { tile, grout, columns, rows, grout_width: 0.05 }
+    }
+
+    fn is_grout(&self, u: f32, v: f32) -> bool {
+        let x = u * self.columns;
+        let y = v * self.rows;
+        let fx = x - x.floor();
+        let fy = y - y.floor();
+        let half_grout = 0.5 * self.grout_width;
+
+        fx < half_grout || fx > 1.0 - half_grout || fy < half_grout || fy > 1.0 - half_grout
+    }
+}
+
+impl<A: Texture, B: Texture> Texture for TileTexture<A, B> {
+    fn color(&self, u: f32, v: f32, p: &Vec3) -> Color {
+        if self.is_grout(u, v) { self.grout.color(u, v, p) } else { self.tile.color(u, v, p) }
+    }
+
+    fn color_filtered(&self, u: f32, v: f32, p: &Vec3, footprint: &TextureFootprint) -> Color {
+        if self.is_grout(u, v) { self.grout.color_filtered(u, v, p, footprint) } else { self.tile.color_filtered(u, v, p, footprint) }
+    }
+}
+
+// checkerboard in uv space, unlike `CheckerTexture` which works on the position
+pub struct UvCheckerTexture<A: Texture, B: Texture> {
+    pub odd: A,
+    pub even: B,
+    pub columns: f32,
+    pub rows: f32,
+}
+
+impl<A: Texture, B: Texture> UvCheckerTexture<A, B> {
+    pub fn new(odd: A, even: B, columns: f32, rows: f32) -> Self {
+        UvCheckerTexture { odd, even, columns, rows }
+    }
+
+    fn is_odd(&self, u: f32, v: f32) -> bool {
+        let cell = (u * self.columns).floor() as i32 + (v * self.rows).floor() as i32;
+
+        cell.rem_euclid(2) == 1
+    }
+}
+
+impl<A: Texture, B: Texture> Texture for UvCheckerTexture<A, B> {
+    fn color(&self, u: f32, v: f32, p: &Vec3) -> Color {
+        if self.is_odd(u, v) { self.odd.color(u, v, p) } else { self.even.color(u, v, p) }
+    }
+
+    fn color_filtered(&self, u: f32, v: f32, p: &Vec3, footprint: &TextureFootprint) -> Color {
+        if self.is_odd(u, v) { self.odd.color_filtered(u, v, p, footprint) } else { self.even.color_filtered(u, v, p, footprint) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::color::{BLACK, WHITE};
+    use crate::texture::CheckerTexture;
+
+    #[test]
+    fn color_ramp_interpolates_between_stops() {
+        let ramp = ColorRamp::from_colors(vec![(1.0, WHITE), (0.5, BLACK)]);
+        let p = Vec3::new(0.0, 0.0, 0.0);
+
+        assert!((ramp.color_at(0.75, 0.0, 0.0, &p).r - 0.5).abs() < 0.0001);
+        assert!(ramp.color_at(0.2, 0.0, 0.0, &p).r.abs() < 0.0001);
+        assert!((ramp.color_at(1.5, 0.0, 0.0, &p).r - 1.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn uv_checker_alternates() {
+        let checker = UvCheckerTexture::new(SolidColor::new(WHITE), SolidColor::new(BLACK), 2.0, 2.0);
+        let p = Vec3::new(0.0, 0.0, 0.0);
+
+        assert_eq!(checker.color(0.25, 0.25, &p).r, 0.0);
+        assert_eq!(checker.color(0.75, 0.25, &p).r, 1.0);
+        assert_eq!(checker.color(0.75, 0.75, &p).r, 0.0);
+    }
+
+    // white at a point and black when filtered
+    struct Filtered;
+
+    impl Texture for Filtered {
+        fn color(&self, _u: f32, _v: f32, _p: &Vec3) -> Color {
+            WHITE
+        }
+
+        fn color_filtered(&self, _u: f32, _v: f32, _p: &Vec3, _footprint: &TextureFootprint) -> Color {
+            BLACK
+        }
+    }
+
+    #[test]
+    fn patterns_forward_filtered_lookups() {
+        let footprint = TextureFootprint { dudx: 0.1, dvdx: 0.0, dudy: 0.0, dvdy: 0.1 };
+        let p = Vec3::new(0.3, 0.6, 0.2);
+        let filtered = |texture: &dyn Texture| texture.color_filtered(0.3, 0.6, &p, &footprint).g;
+        let ramp = || ColorRamp::new(vec![(0.0, Box::new(Filtered) as Box<dyn Texture>), (1.0, Box::new(Filtered))]);
+
+        assert_eq!(filtered(&VoronoiTexture::new(Filtered, Filtered, 4.0, VoronoiFeature::F1)), 0.0);
+        assert_eq!(filtered(&FbmTexture::new(Filtered, Filtered, 4.0)), 0.0);
+        assert_eq!(filtered(&RidgedTexture::new(Filtered, Filtered, 4.0)), 0.0);
+        assert_eq!(filtered(&LinearGradient::new(Vec3::ZERO, Vec3::new(1.0, 0.0, 0.0), ramp())), 0.0);
+        assert_eq!(filtered(&RadialGradient::new(Vec3::ZERO, 1.0, ramp())), 0.0);
+        assert_eq!(filtered(&BrickTexture::new(Filtered, Filtered, 4.0, 8.0)), 0.0);
+        assert_eq!(filtered(&TileTexture::new(Filtered, Filtered, 4.0, 4.0)), 0.0);
+        assert_eq!(filtered(&UvCheckerTexture::new(Filtered, Filtered, 2.0, 2.0)), 0.0);
+        assert_eq!(filtered(&CheckerTexture::new(Filtered, Filtered)), 0.0);
+
+        // unfiltered lookups are unchanged
+        assert_eq!(UvCheckerTexture::new(Filtered, Filtered, 2.0, 2.0).color(0.3, 0.6, &p).g, 1.0);
+    }
+}
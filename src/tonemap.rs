@@ -0,0 +1,161 @@
+use crate::color::{Color, encode_srgb, map_color_256, transform};
+use crate::post::Effect;
+use crate::utils;
+
+// Maps the linear scene radiance to display values in [0, 1]. Only applied
+// when writing 8 bit images, the float outputs stay linear.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToneMap {
+    // no compression, everything above 1 is clipped
+    Clamp,
+    // Reinhard et al. 2002 on the luminance, approaches white asymptotically
+    Reinhard,
+    // reaches white at the given luminance
+    ReinhardExtended { white: f32 },
+    // Stephen Hill's fit of the ACES reference rendering and output transforms
+    Aces,
+    // the minimal AgX of Troy Sobotka, desaturates bright colors towards white
+    Agx,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ToneMapping {
+    pub operator: ToneMap,
+    // exposure in stops, each one doubles the brightness
+    pub exposure: f32,
+}
+
+impl ToneMapping {
+    pub fn new(operator: ToneMap) -> Self {
+        ToneMapping { operator, exposure: 0.0 }
+    }
+
+    pub fn with_exposure(self, exposure: f32) -> Self {
+        ToneMapping { exposure, ..self }
+    }
+
+    // the linear display color of a linear scene color
+    pub fn map(&self, color: Color) -> Color {
+        let color = 2f32.powf(self.exposure) * Color::new(color.r.max(0.0), color.g.max(0.0), color.b.max(0.0));
+
+        let mapped = match self.operator {
+            ToneMap::Clamp => color,
+            ToneMap::Reinhard => scale_luminance(color, |l| l / (1.0 + l)),
+            ToneMap::ReinhardExtended { white } => scale_luminance(color, |l| l * (1.0 + l / (white * white)) / (1.0 + l)),
+            ToneMap::Aces => aces(color),
+            ToneMap::Agx => agx(color),
+        };
+
+        Color::new(utils::clamp(mapped.r, 0.0, 1.0), utils::clamp(mapped.g, 0.0, 1.0), utils::clamp(mapped.b, 0.0, 1.0))
+    }
+
+    // sRGB encoded 8 bit pixels of a linear image
+    pub fn to_ldr(&self, pixels: &[Color]) -> Vec<u8> {
+        pixels.iter()
+            .flat_map(|color| map_color_256(encode_srgb(self.map(*color))).to_vec())
+            .collect()
+    }
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        ToneMapping::new(ToneMap::Clamp)
+    }
+}
+
+// tone mapping inside of a post process chain, for effects that need display
+// values such as color grading tables
+impl Effect for ToneMapping {
+    fn apply(&self, _width: u32, _height: u32, pixels: &[Color]) -> Vec<Color> {
+        pixels.iter().map(|color| self.map(*color)).collect()
+    }
+}
+
+fn scale_luminance<F: Fn(f32) -> f32>(color: Color, curve: F) -> Color {
+    let luminance = color.luminance();
+
+    if luminance > 0.0 { (curve(luminance) / luminance) * color } else { color }
+}
+
+fn aces(color: Color) -> Color {
+    // sRGB => XYZ => D65_2_D60 => AP1 => RRT_SAT
+    const INPUT: [[f32; 3]; 3] = [
+        [0.59719, 0.35458, 0.04823],
+        [0.07600, 0.90834, 0.01566],
+        [0.02840, 0.13383, 0.83777],
+    ];
+    // ODT_SAT => XYZ => D60_2_D65 => sRGB
+    const OUTPUT: [[f32; 3]; 3] = [
+        [1.60475, -0.53108, -0.07367],
+        [-0.10208, 1.10813, -0.00605],
+        [-0.00327, -0.07276, 1.07602],
+    ];
+
+    let fit = |v: f32| (v * (v + 0.024_578_6) - 0.000_090_537) / (v * (0.983_729 * v + 0.432_951) + 0.238_081);
+
+    let c = transform(INPUT, color);
+
+    transform(OUTPUT, Color::new(fit(c.r), fit(c.g), fit(c.b)))
+}
+
+fn agx(color: Color) -> Color {
+    const INSET: [[f32; 3]; 3] = [
+        [0.842_479, 0.078_434, 0.079_224],
+        [0.042_328, 0.878_469, 0.079_166],
+        [0.042_376, 0.078_434, 0.879_143],
+    ];
+    const OUTSET: [[f32; 3]; 3] = [
+        [1.196_879, -0.098_021, -0.099_030],
+        [-0.052_897, 1.151_903, -0.098_961],
+        [-0.052_972, -0.098_043, 1.151_074],
+    ];
+    // the range of the log encoding in stops around middle grey
+    const MIN_EV: f32 = -12.473_93;
+    const MAX_EV: f32 = 4.026_069;
+
+    // polynomial fit of the AgX base contrast curve
+    let curve = |v: f32| {
+        let v = (utils::clamp(v.max(1e-10).log2(), MIN_EV, MAX_EV) - MIN_EV) / (MAX_EV - MIN_EV);
+        let (v2, v4) = (v * v, v * v * v * v);
+
+        15.5 * v4 * v2 - 40.14 * v4 * v + 31.96 * v4 - 6.868 * v2 * v + 0.4298 * v2 + 0.1191 * v - 0.00232
+    };
+
+    let c = transform(INSET, color);
+    let c = transform(OUTSET, Color::new(curve(c.r), curve(c.g), curve(c.b)));
+
+    // the curve targets a 2.2 gamma display
+    Color::new(c.r.max(0.0).powf(2.2), c.g.max(0.0).powf(2.2), c.b.max(0.0).powf(2.2))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::color::{BLACK, WHITE};
+
+    #[test]
+    fn operators_are_monotonic_and_bounded() {
+        let operators = [ToneMap::Clamp, ToneMap::Reinhard, ToneMap::ReinhardExtended { white: 4.0 }, ToneMap::Aces, ToneMap::Agx];
+
+        for operator in operators.iter() {
+            let tone_mapping = ToneMapping::new(*operator);
+            let mut previous = tone_mapping.map(BLACK).r;
+            assert!(previous < 0.01);
+
+            for i in 1..=100 {
+                let value = tone_mapping.map((i as f32 * 0.2) * WHITE).r;
+                assert!(value >= previous && value <= 1.0, "{:?}", operator);
+                previous = value;
+            }
+        }
+
+        // white is reached exactly at the white point
+        let extended = ToneMapping::new(ToneMap::ReinhardExtended { white: 4.0 });
+        assert!((extended.map(4.0 * WHITE).g - 1.0).abs() < 0.0001);
+
+        // one stop of exposure doubles the input
+        let exposed = ToneMapping::new(ToneMap::Reinhard).with_exposure(1.0);
+        assert!((exposed.map(WHITE).r - ToneMapping::new(ToneMap::Reinhard).map(2.0 * WHITE).r).abs() < 0.0001);
+    }
+}